@@ -0,0 +1,110 @@
+//! Embeds the files of `static/` in the binary, see `src/assets.rs`.
+//!
+//! Each file gets a fingerprinted name, from the hash of its content, and
+//! gzip and brotli variants compressed once here instead of on every request.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+const STATIC_DIR: &str = "static";
+
+fn main() {
+    println!("cargo:rerun-if-changed={STATIC_DIR}");
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    let mut files = vec![];
+    collect_files(Path::new(STATIC_DIR), &mut files);
+    files.sort();
+
+    let mut table = String::from("static ASSETS: &[Asset] = &[\n");
+    for file in files {
+        let body = fs::read(&file).unwrap();
+        let path = file
+            .strip_prefix(STATIC_DIR)
+            .unwrap()
+            .to_str()
+            .expect("Static file names must be UTF-8.")
+            .replace('\\', "/");
+        let digest = hex(&Sha256::digest(&body));
+        let hash = &digest[..16];
+        let hashed_path = match path.rsplit_once('.') {
+            Some((stem, extension)) if !stem.ends_with('/') => {
+                format!("{stem}.{hash}.{extension}")
+            }
+            _ => format!("{path}.{hash}"),
+        };
+
+        let gzip = compressed(&out_dir, &hashed_path, "gz", &body, gzip(&body));
+        let brotli = compressed(&out_dir, &hashed_path, "br", &body, brotli(&body));
+        writeln!(
+            table,
+            "    Asset {{ path: {path:?}, hashed_path: {hashed_path:?}, hash: {hash:?}, \
+             body: include_bytes!({source:?}), gzip: {gzip}, brotli: {brotli} }},",
+            source = fs::canonicalize(&file).unwrap(),
+        )
+        .unwrap();
+    }
+    table.push_str("];\n");
+    fs::write(out_dir.join("assets.rs"), table).unwrap();
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+/// Writes a compressed variant, returning the expression embedding it. Not
+/// worth it, e.g. for images, when it is not smaller.
+fn compressed(
+    out_dir: &Path,
+    hashed_path: &str,
+    extension: &str,
+    body: &[u8],
+    variant: Vec<u8>,
+) -> String {
+    if variant.len() >= body.len() {
+        return "None".into();
+    }
+    let destination = out_dir
+        .join(STATIC_DIR)
+        .join(format!("{hashed_path}.{extension}"));
+    fs::create_dir_all(destination.parent().unwrap()).unwrap();
+    fs::write(&destination, variant).unwrap();
+    format!("Some(include_bytes!({destination:?}))")
+}
+
+fn gzip(body: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::best());
+    encoder.write_all(body).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn brotli(body: &[u8]) -> Vec<u8> {
+    let mut variant = vec![];
+    {
+        let mut encoder = brotli::CompressorWriter::new(&mut variant, 4096, 11, 22);
+        encoder.write_all(body).unwrap();
+    }
+    variant
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{byte:02x}").unwrap();
+        hex
+    })
+}
@@ -0,0 +1,159 @@
+## Layout
+
+language = Language
+messages = Messages
+level-debug = debug
+level-info = info
+level-success = success
+level-warning = warning
+level-error = error
+back-to-call-requests = Back to the call requests
+logout = Logout
+login = Login
+
+## Home page
+
+home-title = Actions
+home-request-call = Request Call
+home-call-requests = Call Requests
+
+## Call request form
+
+call-request-title = Call Request
+call-request-phone-number = Enter your phone number:
+call-request-contact-name = Enter your name:
+call-request-submit = Submit
+call-request-registered = Call request registered. You will be called soon!
+call-request-merged = You already requested a call, your request is still queued. You will be called soon!
+call-request-rate-limited = You sent too many call requests, please try again later.
+
+phone-number-empty = The phone number is empty.
+phone-number-forbidden-characters = The phone number can only contain digits, spaces, dashes, dots, brackets and a leading +.
+phone-number-unparsable = The phone number could not be understood.
+phone-number-invalid = The phone number does not exist.
+
+contact-name-empty = The contact name is empty.
+contact-name-too-short = The contact name is too short.
+contact-name-too-long = The contact name is too long.
+contact-name-forbidden-characters = The contact name contains characters that are not allowed.
+
+call-request-callback-slots = When can we call you back?
+call-request-callback-slots-hint = Leave everything unchecked if any time during office hours is fine.
+callback-slot-unknown = The callback time slot could not be understood.
+callback-slot-outside-office-hours = The office does not call back in the chosen time slot.
+call-request-topic = What is it about?
+call-request-topic-other = Something else
+topic-unknown = The chosen topic is not available.
+
+## Callback slots
+
+weekday-mon = Monday
+weekday-tue = Tuesday
+weekday-wed = Wednesday
+weekday-thu = Thursday
+weekday-fri = Friday
+weekday-sat = Saturday
+weekday-sun = Sunday
+period-morning = Morning
+period-afternoon = Afternoon
+callback-slot-morning = { $weekday } morning
+callback-slot-afternoon = { $weekday } afternoon
+
+## Call request status
+
+confirmation-title = Call Request Received
+confirmation-reference = Your reference code is
+confirmation-keep-reference = Keep it: together with your phone number, it lets you check how your request is going.
+confirmation-check-status = Check the status of your request
+status-lookup-title = Call Request Status
+status-lookup-reference = Reference code:
+status-lookup-phone-number = Phone number:
+status-lookup-submit = Check
+status-lookup-status = Status:
+status-lookup-requested-at = Requested at:
+status-lookup-last-requested-at = Last requested at:
+status-lookup-not-found = No call request matches this reference code and phone number.
+status-lookup-rate-limited = You checked too many times, please try again later.
+
+## Login
+
+login-title = Login
+login-username = Username:
+login-password = Password:
+login-failed = Authentication failed.
+login-required = You need to log in first.
+access-denied = You are not allowed to access that page.
+welcome-back = Welcome back!
+logged-out = You have successfully logged out.
+form-expired = Your form expired, please submit it again.
+unexpected-error = Something went wrong.
+database-error = Database error!
+
+## Call request dashboard
+
+dashboard-title = Call Requests
+dashboard-heading = Open Call Requests
+dashboard-empty = There are no open call requests.
+dashboard-requested-at = Requested at
+dashboard-last-requested-at = Last requested at
+dashboard-name = Name
+dashboard-phone-number = Phone number
+dashboard-status = Status
+dashboard-manage-users = Manage users
+dashboard-callback-slots = Call back
+dashboard-any-time = Any time
+dashboard-callable-now = Callable now
+dashboard-sort = Sort
+dashboard-sort-oldest = Oldest first
+dashboard-sort-callable-now = Callable now first
+dashboard-topic = Topic
+dashboard-team = Team
+dashboard-all-topics = All topics
+dashboard-manage-topics = Manage topics
+call-request-moved = Call request moved to { $status }.
+call-request-not-found = The call request does not exist.
+call-request-invalid-transition = A call request cannot go from { $from } to { $to }.
+
+status-pending = Pending
+status-assigned = Assigned
+status-in_progress = In progress
+status-unreachable = Unreachable
+status-completed = Completed
+status-cancelled = Cancelled
+
+## API tokens
+
+api-token-title = API Token
+api-token-generate = Generate API token
+api-token-usage = Use this token in the <code>Authorization: Bearer</code> header of your API requests. It will not be shown again.
+
+## User management
+
+users-title = Users
+users-username = Username
+users-role = Role
+users-change-role = Change role
+user-role-changed = { $username } is now { $role }.
+user-not-found = The user does not exist.
+user-own-role = You cannot change your own role.
+
+role-office_worker = Office worker
+role-supervisor = Supervisor
+role-administrator = Administrator
+
+## Topic catalog
+
+topics-title = Topics
+topics-empty = No topics yet, citizens cannot say what their call is about.
+topics-name = Name
+topics-team = Team
+topics-active = Offered
+topics-save = Save
+topics-add = Add topic
+topic-created = Topic { $name } added.
+topic-updated = Topic { $name } saved.
+topic-name-empty = The name is empty.
+topic-name-too-long = The name is too long.
+topic-name-forbidden-characters = The name contains characters that are not allowed.
+topic-name-taken = A topic with that name already exists.
+topic-not-found = The topic does not exist.
@@ -0,0 +1,159 @@
+## Layout
+
+language = Lingua
+messages = Messaggi
+level-debug = debug
+level-info = info
+level-success = successo
+level-warning = avviso
+level-error = errore
+back-to-call-requests = Torna alle richieste di chiamata
+logout = Esci
+login = Accedi
+
+## Home page
+
+home-title = Azioni
+home-request-call = Richiedi una chiamata
+home-call-requests = Richieste di chiamata
+
+## Call request form
+
+call-request-title = Richiesta di chiamata
+call-request-phone-number = Inserisci il tuo numero di telefono:
+call-request-contact-name = Inserisci il tuo nome:
+call-request-submit = Invia
+call-request-registered = Richiesta di chiamata registrata. Sarai richiamato presto!
+call-request-merged = Hai già richiesto una chiamata, la tua richiesta è ancora in coda. Sarai richiamato presto!
+call-request-rate-limited = Hai inviato troppe richieste di chiamata, riprova più tardi.
+
+phone-number-empty = Il numero di telefono è vuoto.
+phone-number-forbidden-characters = Il numero di telefono può contenere solo cifre, spazi, trattini, punti, parentesi e un + iniziale.
+phone-number-unparsable = Il numero di telefono non è comprensibile.
+phone-number-invalid = Il numero di telefono non esiste.
+
+contact-name-empty = Il nome è vuoto.
+contact-name-too-short = Il nome è troppo corto.
+contact-name-too-long = Il nome è troppo lungo.
+contact-name-forbidden-characters = Il nome contiene caratteri non ammessi.
+
+call-request-callback-slots = Quando possiamo richiamarti?
+call-request-callback-slots-hint = Non selezionare nulla se va bene qualsiasi momento durante l'orario d'ufficio.
+callback-slot-unknown = La fascia oraria per la richiamata non è comprensibile.
+callback-slot-outside-office-hours = L'ufficio non richiama nella fascia oraria scelta.
+call-request-topic = Di cosa si tratta?
+call-request-topic-other = Altro
+topic-unknown = L'argomento scelto non è disponibile.
+
+## Callback slots
+
+weekday-mon = Lunedì
+weekday-tue = Martedì
+weekday-wed = Mercoledì
+weekday-thu = Giovedì
+weekday-fri = Venerdì
+weekday-sat = Sabato
+weekday-sun = Domenica
+period-morning = Mattina
+period-afternoon = Pomeriggio
+callback-slot-morning = { $weekday } mattina
+callback-slot-afternoon = { $weekday } pomeriggio
+
+## Call request status
+
+confirmation-title = Richiesta di chiamata ricevuta
+confirmation-reference = Il tuo codice di riferimento è
+confirmation-keep-reference = Conservalo: insieme al tuo numero di telefono, ti permette di controllare a che punto è la tua richiesta.
+confirmation-check-status = Controlla lo stato della tua richiesta
+status-lookup-title = Stato della richiesta di chiamata
+status-lookup-reference = Codice di riferimento:
+status-lookup-phone-number = Numero di telefono:
+status-lookup-submit = Controlla
+status-lookup-status = Stato:
+status-lookup-requested-at = Richiesta il:
+status-lookup-last-requested-at = Ultima richiesta il:
+status-lookup-not-found = Nessuna richiesta di chiamata corrisponde a questo codice di riferimento e numero di telefono.
+status-lookup-rate-limited = Hai controllato troppe volte, riprova più tardi.
+
+## Login
+
+login-title = Accesso
+login-username = Nome utente:
+login-password = Password:
+login-failed = Autenticazione non riuscita.
+login-required = Devi prima accedere.
+access-denied = Non hai i permessi per accedere a quella pagina.
+welcome-back = Bentornato!
+logged-out = Sei uscito correttamente.
+form-expired = Il modulo è scaduto, invialo di nuovo.
+unexpected-error = Qualcosa è andato storto.
+database-error = Errore del database!
+
+## Call request dashboard
+
+dashboard-title = Richieste di chiamata
+dashboard-heading = Richieste di chiamata aperte
+dashboard-empty = Non ci sono richieste di chiamata aperte.
+dashboard-requested-at = Richiesta il
+dashboard-last-requested-at = Ultima richiesta il
+dashboard-name = Nome
+dashboard-phone-number = Numero di telefono
+dashboard-status = Stato
+dashboard-manage-users = Gestisci utenti
+dashboard-callback-slots = Richiamare
+dashboard-any-time = In qualsiasi momento
+dashboard-callable-now = Richiamabile ora
+dashboard-sort = Ordina
+dashboard-sort-oldest = Prima le più vecchie
+dashboard-sort-callable-now = Prima le richiamabili ora
+dashboard-topic = Argomento
+dashboard-team = Ufficio
+dashboard-all-topics = Tutti gli argomenti
+dashboard-manage-topics = Gestisci argomenti
+call-request-moved = Richiesta di chiamata spostata in { $status }.
+call-request-not-found = La richiesta di chiamata non esiste.
+call-request-invalid-transition = Una richiesta di chiamata non può passare da { $from } a { $to }.
+
+status-pending = In attesa
+status-assigned = Assegnata
+status-in_progress = In corso
+status-unreachable = Irraggiungibile
+status-completed = Completata
+status-cancelled = Annullata
+
+## API tokens
+
+api-token-title = Token API
+api-token-generate = Genera token API
+api-token-usage = Usa questo token nell'header <code>Authorization: Bearer</code> delle tue richieste API. Non verrà mostrato di nuovo.
+
+## User management
+
+users-title = Utenti
+users-username = Nome utente
+users-role = Ruolo
+users-change-role = Cambia ruolo
+user-role-changed = { $username } ora è { $role }.
+user-not-found = L'utente non esiste.
+user-own-role = Non puoi cambiare il tuo ruolo.
+
+role-office_worker = Impiegato
+role-supervisor = Supervisore
+role-administrator = Amministratore
+
+## Topic catalog
+
+topics-title = Argomenti
+topics-empty = Ancora nessun argomento, i cittadini non possono indicare il motivo della chiamata.
+topics-name = Nome
+topics-team = Ufficio
+topics-active = Proposto
+topics-save = Salva
+topics-add = Aggiungi argomento
+topic-created = Argomento { $name } aggiunto.
+topic-updated = Argomento { $name } salvato.
+topic-name-empty = Il nome è vuoto.
+topic-name-too-long = Il nome è troppo lungo.
+topic-name-forbidden-characters = Il nome contiene caratteri non ammessi.
+topic-name-taken = Esiste già un argomento con questo nome.
+topic-not-found = L'argomento non esiste.
@@ -0,0 +1,7 @@
+-- Office workers that can authenticate into the service.
+CREATE TABLE users(
+    user_id UUID NOT NULL,
+    PRIMARY KEY(user_id),
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL
+);
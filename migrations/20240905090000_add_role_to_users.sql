@@ -0,0 +1,3 @@
+-- Every user has a role that determines what they are allowed to do.
+CREATE TYPE user_role AS ENUM ('office_worker');
+ALTER TABLE users ADD COLUMN role user_role NOT NULL DEFAULT 'office_worker';
@@ -0,0 +1,4 @@
+-- Call requests are pending until an office worker marks them as done.
+ALTER TABLE call_requests ADD COLUMN status TEXT NOT NULL DEFAULT 'pending';
+ALTER TABLE call_requests ADD COLUMN completed_at TIMESTAMPTZ;
+ALTER TABLE call_requests ADD COLUMN completed_by UUID REFERENCES users(user_id);
@@ -0,0 +1,38 @@
+-- Call requests follow an explicit lifecycle, every status change is recorded.
+CREATE TYPE call_request_status AS ENUM (
+    'pending',
+    'assigned',
+    'in_progress',
+    'unreachable',
+    'completed',
+    'cancelled'
+);
+
+ALTER TABLE call_requests ALTER COLUMN status DROP DEFAULT;
+ALTER TABLE call_requests ALTER COLUMN status TYPE call_request_status USING (
+    CASE status WHEN 'done' THEN 'completed' ELSE status END
+)::call_request_status;
+ALTER TABLE call_requests ALTER COLUMN status SET DEFAULT 'pending';
+ALTER TABLE call_requests ADD COLUMN assigned_to UUID REFERENCES users(user_id);
+
+CREATE TABLE call_request_transitions(
+    id UUID NOT NULL,
+    PRIMARY KEY(id),
+    call_request_id UUID NOT NULL REFERENCES call_requests(id),
+    from_status call_request_status NOT NULL,
+    to_status call_request_status NOT NULL,
+    changed_by UUID NOT NULL REFERENCES users(user_id),
+    changed_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX call_request_transitions_call_request_id_idx
+    ON call_request_transitions(call_request_id);
+
+-- Who closed a call request and when is now part of the history.
+INSERT INTO call_request_transitions
+    (id, call_request_id, from_status, to_status, changed_by, changed_at)
+SELECT gen_random_uuid(), id, 'pending', 'completed', completed_by, completed_at
+FROM call_requests
+WHERE status = 'completed' AND completed_by IS NOT NULL AND completed_at IS NOT NULL;
+
+ALTER TABLE call_requests DROP COLUMN completed_at;
+ALTER TABLE call_requests DROP COLUMN completed_by;
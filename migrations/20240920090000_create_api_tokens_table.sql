@@ -0,0 +1,9 @@
+-- Bearer tokens used by staff to access the JSON API.
+-- Only the SHA-256 digest of a token is stored.
+CREATE TABLE api_tokens(
+    token_id UUID NOT NULL,
+    PRIMARY KEY(token_id),
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL
+);
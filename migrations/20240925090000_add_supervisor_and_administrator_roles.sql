@@ -0,0 +1,3 @@
+-- Supervisors and administrators have every permission of an office worker.
+ALTER TYPE user_role ADD VALUE 'supervisor';
+ALTER TYPE user_role ADD VALUE 'administrator';
@@ -0,0 +1,2 @@
+-- Disabled users can no longer log in nor use their API tokens.
+ALTER TABLE users ADD COLUMN disabled BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,6 @@
+-- Submissions for a phone number with an open call request update it instead
+-- of queueing a new one.
+ALTER TABLE call_requests ADD COLUMN last_requested_at TIMESTAMPTZ;
+UPDATE call_requests SET last_requested_at = created_at;
+ALTER TABLE call_requests ALTER COLUMN last_requested_at SET NOT NULL;
+CREATE INDEX call_requests_phone_number_idx ON call_requests(phone_number);
@@ -0,0 +1,11 @@
+-- Parts of the week a citizen prefers to be called back in, none meaning any
+-- time during office hours.
+CREATE TYPE day_period AS ENUM ('morning', 'afternoon');
+
+CREATE TABLE call_request_callback_slots(
+    call_request_id UUID NOT NULL REFERENCES call_requests(id),
+    -- ISO day of the week, 1 being Monday.
+    weekday SMALLINT NOT NULL CHECK (weekday BETWEEN 1 AND 7),
+    period day_period NOT NULL,
+    PRIMARY KEY(call_request_id, weekday, period)
+);
@@ -0,0 +1,15 @@
+-- Reasons citizens ask to be called for, managed by supervisors from the
+-- dashboard. Topics are retired instead of deleted, call requests keep them.
+CREATE TABLE topics(
+    id UUID NOT NULL,
+    PRIMARY KEY(id),
+    name TEXT NOT NULL,
+    -- Team handling the call requests about the topic.
+    team TEXT,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL
+);
+CREATE UNIQUE INDEX topics_name_idx ON topics(lower(name));
+
+ALTER TABLE call_requests ADD COLUMN topic_id UUID REFERENCES topics(id);
+CREATE INDEX call_requests_topic_id_idx ON call_requests(topic_id);
@@ -0,0 +1,4 @@
+-- Code given to citizens to look up their call request. Call requests
+-- registered before get one when submitted again.
+ALTER TABLE call_requests ADD COLUMN reference TEXT;
+CREATE UNIQUE INDEX call_requests_reference_idx ON call_requests(reference);
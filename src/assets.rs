@@ -0,0 +1,57 @@
+//! # Static assets
+//! The files of `static/` are embedded in the binary by `build.rs`, so the
+//! pages work on networks without internet access and visits are not
+//! disclosed to a CDN.
+//!
+//! Each file is also served under a fingerprinted name, e.g.
+//! `/static/css/bubble.3f2a9c0d1e2b4a5c.css`, that changes with its content:
+//! browsers can cache it forever. Templates link assets through [`url`]:
+//!
+//! ```html
+//! <link href="{{ crate::assets::url("css/bubble.css")|safe }}" rel="stylesheet" />
+//! ```
+
+/// A file of `static/`.
+pub struct Asset {
+    /// Path relative to `static/`.
+    pub path: &'static str,
+    /// `path` with the content hash before the extension.
+    pub hashed_path: &'static str,
+    pub hash: &'static str,
+    pub body: &'static [u8],
+    pub gzip: Option<&'static [u8]>,
+    pub brotli: Option<&'static [u8]>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+impl Asset {
+    /// Finds an asset by its path, fingerprinted or not. The flag tells
+    /// whether the fingerprinted one was requested.
+    pub fn find(path: &str) -> Option<(&'static Asset, bool)> {
+        ASSETS.iter().find_map(|asset| {
+            if asset.hashed_path == path {
+                Some((asset, true))
+            } else if asset.path == path {
+                Some((asset, false))
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn content_type(&self) -> mime_guess::Mime {
+        mime_guess::from_path(self.path).first_or_octet_stream()
+    }
+}
+
+/// URL of the fingerprinted asset at `path`, relative to `static/`.
+pub fn url(path: &str) -> String {
+    match ASSETS.iter().find(|asset| asset.path == path) {
+        Some(asset) => format!("/static/{}", asset.hashed_path),
+        None => {
+            tracing::error!(path, "Template links a missing static asset");
+            format!("/static/{}", path)
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use actix_web::{dev::Payload, http::header::AUTHORIZATION, web, FromRequest, HttpRequest};
+use chrono::Utc;
+use futures_util::future::LocalBoxFuture;
+use rand::{distributions::Alphanumeric, Rng};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::{types::Uuid, PgPool};
+
+use crate::{domain::user::UserRole, routes::api::ApiError};
+
+use super::UserId;
+
+/// Creates a new API token for the user. The token is returned only once,
+/// afterwards only its digest is known.
+#[tracing::instrument(name = "Issue API token", skip(pool))]
+pub async fn issue_api_token(pool: &PgPool, user_id: Uuid) -> Result<Secret<String>, sqlx::Error> {
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO api_tokens (token_id, user_id, token_hash, created_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        hash_token(&token),
+        Utc::now(),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(Secret::new(token))
+}
+
+/// Tokens are random and long, a fast digest is enough to store them.
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[tracing::instrument(name = "Validate API token", skip(token, pool))]
+async fn validate_api_token(
+    token: Secret<String>,
+    pool: &PgPool,
+) -> Result<Option<ApiUser>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT users.user_id, users.role AS "role: UserRole"
+        FROM api_tokens
+        JOIN users ON users.user_id = api_tokens.user_id
+        WHERE api_tokens.token_hash = $1 AND NOT users.disabled
+        "#,
+        hash_token(token.expose_secret()),
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|row| ApiUser {
+        user_id: UserId::from(row.user_id),
+        role: row.role,
+    }))
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<Secret<String>> {
+    let value = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    // Authentication schemes are case-insensitive (RFC 7235).
+    let (scheme, token) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("Bearer") {
+        return None;
+    }
+    let token = token.trim();
+    (!token.is_empty()).then(|| Secret::new(token.to_owned()))
+}
+
+/// Staff user authenticated through an `Authorization: Bearer <token>` header.
+///
+/// Handlers taking it as argument reject requests without a valid token.
+#[derive(Debug, Clone, Copy)]
+pub struct ApiUser {
+    pub user_id: UserId,
+    pub role: UserRole,
+}
+
+impl ApiUser {
+    /// Rejects users whose role does not grant `required`.
+    pub fn require(self, required: UserRole) -> Result<Self, ApiError> {
+        if self.role.grants(required) {
+            Ok(self)
+        } else {
+            Err(ApiError::Forbidden)
+        }
+    }
+}
+
+impl FromRequest for ApiUser {
+    type Error = ApiError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let token = bearer_token(req);
+        let pool = req.app_data::<web::Data<PgPool>>().cloned();
+        Box::pin(async move {
+            let (Some(token), Some(pool)) = (token, pool) else {
+                return Err(ApiError::Unauthorized);
+            };
+            validate_api_token(token, &pool)
+                .await?
+                .ok_or(ApiError::Unauthorized)
+        })
+    }
+}
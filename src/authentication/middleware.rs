@@ -0,0 +1,121 @@
+use std::ops::Deref;
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::LOCATION,
+    middleware::Next,
+    web, FromRequest, HttpMessage, HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::{types::Uuid, PgPool};
+
+use crate::{domain::user::UserRole, i18n::Message, session_state::TypedSession};
+
+/// Id of the authenticated user, available to the handlers behind
+/// [`require_role`] through `web::ReqData<UserId>`.
+#[derive(Copy, Clone, Debug)]
+pub struct UserId(Uuid);
+
+impl std::fmt::Display for UserId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<Uuid> for UserId {
+    fn from(user_id: Uuid) -> Self {
+        Self(user_id)
+    }
+}
+
+impl Deref for UserId {
+    type Target = Uuid;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Redirects users without a session to the login page.
+pub async fn reject_anonymous_users(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    require_role(UserRole::OfficeWorker, req, next).await
+}
+
+/// Lets through only users whose role grants `required`, to be wrapped
+/// around a scope with
+/// `from_fn(|req, next| require_role(UserRole::Administrator, req, next))`.
+///
+/// The role is read from the database on every request, so that role changes
+/// and disabled accounts apply to open sessions too: the session only keeps
+/// the user id. Handlers get the [`UserId`] and the [`UserRole`] through
+/// `web::ReqData`.
+pub async fn require_role(
+    required: UserRole,
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let user = match req.extensions().get::<UserId>().copied() {
+        // An outer scope already authenticated the user.
+        Some(user_id) => req
+            .extensions()
+            .get::<UserRole>()
+            .map(|role| (user_id, *role)),
+        None => None,
+    };
+    let user = match user {
+        Some(user) => Some(user),
+        None => {
+            let session = {
+                let (http_request, payload) = req.parts_mut();
+                TypedSession::from_request(http_request, payload).await
+            }?;
+            let user_id = session
+                .get_user_id()
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            match (user_id, req.app_data::<web::Data<PgPool>>()) {
+                (Some(user_id), Some(pool)) => get_user_role(pool, user_id)
+                    .await
+                    .map_err(actix_web::error::ErrorInternalServerError)?
+                    .map(|role| (UserId(user_id), role)),
+                _ => None,
+            }
+        }
+    };
+
+    match user {
+        Some((user_id, role)) if role.grants(required) => {
+            req.extensions_mut().insert(user_id);
+            req.extensions_mut().insert(role);
+            next.call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        }
+        Some(_) => Ok(redirect_to_login(req, Message::new("access-denied"))),
+        None => Ok(redirect_to_login(req, Message::new("login-required"))),
+    }
+}
+
+/// Answers with a response instead of an error, so that outer middlewares
+/// (e.g. flash messages) still process it.
+fn redirect_to_login<B>(req: ServiceRequest, message: Message) -> ServiceResponse<EitherBody<B>> {
+    FlashMessage::error(message).send();
+    let response = HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/login"))
+        .finish();
+    req.into_response(response).map_into_right_body()
+}
+
+#[tracing::instrument(name = "Get user role", skip(pool))]
+async fn get_user_role(pool: &PgPool, user_id: Uuid) -> Result<Option<UserRole>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT role AS "role: UserRole" FROM users WHERE user_id = $1 AND NOT disabled"#,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|row| row.role))
+}
@@ -0,0 +1,11 @@
+//! # Authentication
+//! Office workers authenticate with a username and a password.
+//! Passwords are never stored, only their Argon2id PHC string is.
+
+mod api_token;
+mod middleware;
+mod password;
+
+pub use api_token::*;
+pub use middleware::*;
+pub use password::*;
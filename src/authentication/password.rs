@@ -0,0 +1,126 @@
+use anyhow::Context;
+use argon2::{
+    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
+    PasswordVerifier, Version,
+};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{types::Uuid, PgPool};
+
+use crate::{domain::user::UserRole, telemetry::spawn_blocking_with_tracing};
+
+/// Username and password pair submitted by someone that wants to authenticate.
+pub struct Credentials {
+    pub username: String,
+    pub password: Secret<String>,
+}
+
+/// A user whose credentials have been verified.
+#[derive(Debug)]
+pub struct AuthenticatedUser {
+    pub user_id: Uuid,
+    pub role: UserRole,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AuthError {
+    #[error("Invalid credentials.")]
+    InvalidCredentials(#[source] anyhow::Error),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+/// Hash used when the username is unknown, so that the response time does not
+/// reveal which usernames exist.
+const FALLBACK_PASSWORD_HASH: &str = "$argon2id$v=19$m=15000,t=2,p=1$\
+    j2QolmFTDtoOd8twFLCL4A$\
+    huhuX1ewEgPpJog8EZOGkyp27YsQyx/AWA3kKsxz+9A";
+
+/// Checks the credentials against the stored password hash, returning the
+/// authenticated user.
+#[tracing::instrument(name = "Validate credentials", skip(credentials, pool))]
+pub async fn validate_credentials(
+    credentials: Credentials,
+    pool: &PgPool,
+) -> Result<AuthenticatedUser, AuthError> {
+    let mut user = None;
+    let mut expected_password_hash = Secret::new(FALLBACK_PASSWORD_HASH.to_string());
+
+    if let Some((stored_user, stored_password_hash)) =
+        get_stored_credentials(&credentials.username, pool).await?
+    {
+        user = Some(stored_user);
+        expected_password_hash = stored_password_hash;
+    }
+
+    spawn_blocking_with_tracing(move || {
+        verify_password_hash(expected_password_hash, credentials.password)
+    })
+    .await
+    .context("Failed to spawn blocking task.")??;
+
+    user.ok_or_else(|| anyhow::anyhow!("Unknown username."))
+        .map_err(AuthError::InvalidCredentials)
+}
+
+#[tracing::instrument(name = "Get stored credentials", skip(username, pool))]
+async fn get_stored_credentials(
+    username: &str,
+    pool: &PgPool,
+) -> Result<Option<(AuthenticatedUser, Secret<String>)>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT user_id, password_hash, role AS "role: UserRole"
+        FROM users
+        WHERE username = $1 AND NOT disabled
+        "#,
+        username,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve stored credentials.")?
+    .map(|row| {
+        (
+            AuthenticatedUser {
+                user_id: row.user_id,
+                role: row.role,
+            },
+            Secret::new(row.password_hash),
+        )
+    });
+    Ok(row)
+}
+
+#[tracing::instrument(
+    name = "Verify password hash",
+    skip(expected_password_hash, password_candidate)
+)]
+fn verify_password_hash(
+    expected_password_hash: Secret<String>,
+    password_candidate: Secret<String>,
+) -> Result<(), AuthError> {
+    let expected_password_hash = PasswordHash::new(expected_password_hash.expose_secret())
+        .context("Failed to parse hash in PHC string format.")?;
+
+    Argon2::default()
+        .verify_password(
+            password_candidate.expose_secret().as_bytes(),
+            &expected_password_hash,
+        )
+        .context("Invalid password.")
+        .map_err(AuthError::InvalidCredentials)
+}
+
+/// Hashes a password with Argon2id, producing a PHC string.
+///
+/// This is CPU intensive: call it through [`spawn_blocking_with_tracing`].
+pub fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>, anyhow::Error> {
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let password_hash = Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::new(15000, 2, 1, None).unwrap(),
+    )
+    .hash_password(password.expose_secret().as_bytes(), &salt)?
+    .to_string();
+    Ok(Secret::new(password_hash))
+}
@@ -0,0 +1,98 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use serde::Serialize;
+use sqlx::{types::Uuid, PgPool};
+
+use crate::domain::call_request::CallRequestStatus;
+
+#[derive(Subcommand, Debug)]
+pub enum CallRequestsCommand {
+    /// Exports call requests as CSV, oldest first.
+    Export {
+        /// Only export call requests with this status.
+        #[arg(long)]
+        status: Option<CallRequestStatus>,
+        /// Writes to this file instead of the standard output.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// A CSV record of the export.
+#[derive(Serialize, Debug)]
+struct ExportedCallRequest {
+    id: Uuid,
+    contact_name: String,
+    phone_number: String,
+    status: CallRequestStatus,
+    assigned_to: Option<Uuid>,
+    created_at: DateTime<Utc>,
+}
+
+impl CallRequestsCommand {
+    pub async fn run(self, pool: &PgPool) -> Result<(), anyhow::Error> {
+        match self {
+            CallRequestsCommand::Export { status, output } => {
+                let writer: Box<dyn Write> = match output {
+                    Some(path) => Box::new(
+                        std::fs::File::create(&path)
+                            .with_context(|| format!("Failed to create {}.", path.display()))?,
+                    ),
+                    None => Box::new(std::io::stdout().lock()),
+                };
+                export(pool, status, writer).await
+            }
+        }
+    }
+}
+
+#[tracing::instrument(name = "Export call requests", skip(pool, writer))]
+async fn export(
+    pool: &PgPool,
+    status: Option<CallRequestStatus>,
+    writer: impl Write,
+) -> Result<(), anyhow::Error> {
+    let call_requests = sqlx::query_as!(
+        ExportedCallRequest,
+        r#"
+        SELECT
+            id,
+            user_name AS contact_name,
+            phone_number,
+            status AS "status: CallRequestStatus",
+            assigned_to,
+            created_at
+        FROM call_requests
+        WHERE $1::call_request_status IS NULL OR status = $1
+        ORDER BY created_at ASC
+        "#,
+        status as Option<CallRequestStatus>,
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the call requests.")?;
+
+    let mut writer = csv::Writer::from_writer(writer);
+    for mut call_request in call_requests {
+        call_request.contact_name = escape_formula(call_request.contact_name);
+        writer.serialize(call_request)?;
+    }
+    writer.flush().context("Failed to write the export.")?;
+    Ok(())
+}
+
+/// Characters that make spreadsheets read a cell as a formula.
+const FORMULA_TRIGGERS: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Prefixes with a quote the values submitted by citizens that spreadsheets
+/// would evaluate when opening the export.
+fn escape_formula(value: String) -> String {
+    if value.starts_with(FORMULA_TRIGGERS) {
+        format!("'{value}")
+    } else {
+        value
+    }
+}
@@ -0,0 +1,28 @@
+use anyhow::Context;
+use clap::Subcommand;
+
+use crate::{configuration::Configuration, startup::make_database_pool};
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Prints the configuration, secrets excluded, and checks that the
+    /// database is reachable with it.
+    Check,
+}
+
+impl ConfigCommand {
+    pub async fn run(self, configuration: Configuration) -> Result<(), anyhow::Error> {
+        match self {
+            ConfigCommand::Check => {
+                println!("{:#?}", configuration);
+                let pool = make_database_pool(&configuration.database);
+                sqlx::query("SELECT 1")
+                    .execute(&pool)
+                    .await
+                    .context("The database is not reachable.")?;
+                println!("The configuration is valid.");
+                Ok(())
+            }
+        }
+    }
+}
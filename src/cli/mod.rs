@@ -0,0 +1,88 @@
+//! # Command line interface
+//! Without a subcommand the binary serves the application, the other
+//! subcommands are meant for operators. Every subcommand reads the same
+//! configuration as the server.
+
+mod call_requests;
+mod config;
+mod user;
+
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+
+use crate::{
+    configuration::{get_configuration, Configuration},
+    migration::prepare_database,
+    startup::{make_database_pool, Application},
+};
+
+#[derive(Parser, Debug)]
+#[command(version, about = "A digital office for anagraphic services.")]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Serves the application, the default.
+    Serve,
+    /// Applies the pending database migrations.
+    Migrate,
+    /// Manages staff accounts.
+    #[command(subcommand)]
+    User(user::UserCommand),
+    /// Works with call requests.
+    #[command(subcommand)]
+    CallRequests(call_requests::CallRequestsCommand),
+    /// Inspects the configuration.
+    #[command(subcommand)]
+    Config(config::ConfigCommand),
+}
+
+impl Cli {
+    /// Whether the command runs the server. The other commands print their
+    /// output to stdout, so logs must go elsewhere.
+    pub fn serves(&self) -> bool {
+        matches!(self.command, None | Some(Command::Serve))
+    }
+
+    pub async fn run(self) -> Result<(), anyhow::Error> {
+        let configuration = get_configuration().context("Could not get configuration")?;
+        match self.command.unwrap_or(Command::Serve) {
+            Command::Serve => serve(configuration).await,
+            Command::Migrate => migrate(configuration).await,
+            Command::User(command) => {
+                command
+                    .run(&make_database_pool(&configuration.database))
+                    .await
+            }
+            Command::CallRequests(command) => {
+                command
+                    .run(&make_database_pool(&configuration.database))
+                    .await
+            }
+            Command::Config(command) => command.run(configuration).await,
+        }
+    }
+}
+
+async fn serve(configuration: Configuration) -> Result<(), anyhow::Error> {
+    let app = Application::build(configuration)
+        .await
+        .context("Could not build application")?;
+
+    app.run_until_stopped()
+        .await
+        .context("Could not run application")
+}
+
+#[tracing::instrument(name = "Migrate database", skip(configuration))]
+async fn migrate(configuration: Configuration) -> Result<(), anyhow::Error> {
+    let pool = make_database_pool(&configuration.database);
+    prepare_database(&pool, true)
+        .await
+        .context("Could not migrate the database")?;
+    println!("The database is up to date.");
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use anyhow::{bail, Context};
+use clap::Subcommand;
+use rand::{distributions::Alphanumeric, Rng};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{types::Uuid, PgPool};
+
+use crate::{
+    authentication::compute_password_hash, domain::user::UserRole,
+    telemetry::spawn_blocking_with_tracing,
+};
+
+#[derive(Subcommand, Debug)]
+pub enum UserCommand {
+    /// Creates a staff account. Without --password-stdin a random password
+    /// is generated and printed.
+    Create {
+        username: String,
+        /// One of office_worker, supervisor or administrator.
+        #[arg(long, default_value = "office_worker")]
+        role: UserRole,
+        /// Reads the password from the first line of the standard input.
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Prevents a staff account from logging in and using its API tokens.
+    Disable { username: String },
+    /// Replaces the password of a staff account. Without --password-stdin a
+    /// random password is generated and printed.
+    ResetPassword {
+        username: String,
+        /// Reads the password from the first line of the standard input.
+        #[arg(long)]
+        password_stdin: bool,
+    },
+}
+
+impl UserCommand {
+    pub async fn run(self, pool: &PgPool) -> Result<(), anyhow::Error> {
+        match self {
+            UserCommand::Create {
+                username,
+                role,
+                password_stdin,
+            } => {
+                let password = new_password(password_stdin)?;
+                create_user(pool, &username, role, password.clone()).await?;
+                println!("Created {} with role {}.", username, role.as_str());
+                if !password_stdin {
+                    println!("Password: {}", password.expose_secret());
+                }
+            }
+            UserCommand::Disable { username } => {
+                disable_user(pool, &username).await?;
+                println!("Disabled {}.", username);
+            }
+            UserCommand::ResetPassword {
+                username,
+                password_stdin,
+            } => {
+                let password = new_password(password_stdin)?;
+                reset_password(pool, &username, password.clone()).await?;
+                println!("Reset the password of {}.", username);
+                if !password_stdin {
+                    println!("Password: {}", password.expose_secret());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn new_password(from_stdin: bool) -> Result<Secret<String>, anyhow::Error> {
+    if !from_stdin {
+        let password: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(24)
+            .map(char::from)
+            .collect();
+        return Ok(Secret::new(password));
+    }
+
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .context("Failed to read the password from the standard input.")?;
+    let password = line.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        bail!("The password cannot be empty.");
+    }
+    Ok(Secret::new(password.to_owned()))
+}
+
+async fn hash(password: Secret<String>) -> Result<Secret<String>, anyhow::Error> {
+    spawn_blocking_with_tracing(move || compute_password_hash(password))
+        .await
+        .context("Failed to spawn blocking task.")?
+}
+
+#[tracing::instrument(name = "Create user", skip(pool, password))]
+async fn create_user(
+    pool: &PgPool,
+    username: &str,
+    role: UserRole,
+    password: Secret<String>,
+) -> Result<(), anyhow::Error> {
+    let password_hash = hash(password).await?;
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, password_hash, role)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        Uuid::new_v4(),
+        username,
+        password_hash.expose_secret(),
+        role as UserRole,
+    )
+    .execute(pool)
+    .await;
+
+    match result {
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            bail!("A user named {} already exists.", username)
+        }
+        result => result.map(|_| ()).context("Failed to store the new user."),
+    }
+}
+
+#[tracing::instrument(name = "Disable user", skip(pool))]
+async fn disable_user(pool: &PgPool, username: &str) -> Result<(), anyhow::Error> {
+    let updated = sqlx::query!(
+        "UPDATE users SET disabled = true WHERE username = $1",
+        username,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to disable the user.")?
+    .rows_affected();
+    if updated == 0 {
+        bail!("There is no user named {}.", username);
+    }
+    Ok(())
+}
+
+#[tracing::instrument(name = "Reset password", skip(pool, password))]
+async fn reset_password(
+    pool: &PgPool,
+    username: &str,
+    password: Secret<String>,
+) -> Result<(), anyhow::Error> {
+    let password_hash = hash(password).await?;
+    let updated = sqlx::query!(
+        "UPDATE users SET password_hash = $1 WHERE username = $2",
+        password_hash.expose_secret(),
+        username,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to reset the password.")?
+    .rows_affected();
+    if updated == 0 {
+        bail!("There is no user named {}.", username);
+    }
+    Ok(())
+}
@@ -1,10 +1,26 @@
 use secrecy::{ExposeSecret, Secret};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
+
+use crate::{domain::callback_slot::OfficeHours, i18n::Locale};
+
 #[derive(serde::Deserialize, Clone, Debug)]
 pub struct Configuration {
+    /// Set from `APP_ENVIRONMENT`, not from the configuration files.
+    #[serde(skip)]
+    pub environment: Environment,
     pub application: ApplicationConfiguration,
     pub database: DatabaseConfiguration,
+    pub redis_uri: Secret<String>,
+    #[serde(default)]
+    pub telemetry: TelemetryConfiguration,
+}
+
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+pub struct TelemetryConfiguration {
+    /// OTLP/HTTP traces endpoint of an OpenTelemetry collector, e.g.
+    /// `http://localhost:4318/v1/traces`. Spans are not exported without it.
+    pub otlp_endpoint: Option<String>,
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -14,6 +30,84 @@ pub struct ApplicationConfiguration {
     pub host: String,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    pub session_store: SessionStoreKind,
+    /// Applies the pending database migrations before serving requests.
+    #[serde(default)]
+    pub migrate_on_startup: bool,
+    /// Port of the admin server exposing `/metrics`, not to be reachable by
+    /// the public.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub metrics_port: u16,
+    /// Time each dependency has to answer the readiness probe.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub readiness_timeout_milliseconds: u64,
+    pub rate_limit: RateLimitConfiguration,
+    /// Submissions for a phone number with an open call request, requested
+    /// within this many minutes, update it instead of queueing a new one.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub duplicate_window_minutes: u32,
+    /// Language of the pages for browsers asking for neither Italian nor
+    /// English.
+    pub default_locale: Locale,
+    /// When citizens can ask to be called back, see [`OfficeHours`].
+    pub office_hours: OfficeHours,
+    #[serde(default)]
+    pub security_headers: SecurityHeadersConfiguration,
+}
+
+/// Headers added to every response, see [`crate::security_headers`].
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SecurityHeadersConfiguration {
+    /// Lifetime of the HSTS policy, only sent over TLS. HSTS is disabled
+    /// when unset.
+    pub hsts_max_age_seconds: Option<u64>,
+    /// `X-Frame-Options` value.
+    pub frame_options: String,
+    /// `Referrer-Policy` value.
+    pub referrer_policy: String,
+    /// Reports Content Security Policy violations without blocking them.
+    pub csp_report_only: bool,
+    /// Takes the scheme from `Forwarded`/`X-Forwarded-Proto` to decide on
+    /// HSTS, only enable it behind a reverse proxy that sets them.
+    pub trust_forwarded_proto: bool,
+}
+
+impl Default for SecurityHeadersConfiguration {
+    fn default() -> Self {
+        Self {
+            hsts_max_age_seconds: Some(31_536_000),
+            frame_options: "DENY".into(),
+            referrer_policy: "strict-origin-when-cross-origin".into(),
+            csp_report_only: false,
+            trust_forwarded_proto: false,
+        }
+    }
+}
+
+/// Limits on call request submissions, see [`crate::rate_limit`].
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct RateLimitConfiguration {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub window_seconds: u64,
+    /// Submissions allowed from a client IP within a window.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub per_client_ip: u32,
+    /// Submissions allowed for a phone number within a window.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub per_phone_number: u32,
+    /// Takes the client IP from `Forwarded`/`X-Forwarded-For`, only enable
+    /// it behind a reverse proxy that sets them.
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+}
+
+/// Backend used to keep the session state of authenticated users.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStoreKind {
+    Redis,
+    Memory,
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -48,7 +142,111 @@ impl DatabaseConfiguration {
     }
 }
 
-pub fn get_configuration() -> Result<Configuration, config::ConfigError> {
+/// Deployment environment, selected through the `APP_ENVIRONMENT` variable.
+///
+/// Each one has an overlay, `configuration/<environment>.toml`, applied on
+/// top of `configuration/base.toml`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Environment {
+    #[default]
+    Local,
+    Production,
+}
+
+impl Environment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Environment::Local => "local",
+            Environment::Production => "production",
+        }
+    }
+}
+
+impl TryFrom<String> for Environment {
+    type Error = ConfigurationError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.to_lowercase().as_str() {
+            "local" => Ok(Self::Local),
+            "production" => Ok(Self::Production),
+            _ => Err(ConfigurationError::UnknownEnvironment(s)),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigurationError {
+    #[error("`{0}` is not a supported environment, use either `local` or `production`.")]
+    UnknownEnvironment(String),
+    #[error(transparent)]
+    LoadError(#[from] config::ConfigError),
+    #[error("Invalid value for `{key}`: {reason}")]
+    InvalidValue {
+        key: &'static str,
+        reason: &'static str,
+    },
+}
+
+/// Secrets shipped in `configuration/base.toml`, for development only.
+const SAMPLE_DATABASE_PASSWORD: &str = "password";
+const SAMPLE_HMAC_SECRET: &str = "super-duper-hmac-secret-super-duper-hmac-secret-super-duper-hmac-secret-super-duper-hmac-secret";
+
+impl Configuration {
+    /// Rejects values that cannot work, and in production the ones that are
+    /// obviously insecure.
+    pub fn validate(&self, environment: Environment) -> Result<(), ConfigurationError> {
+        let hmac_secret = self.application.hmac_secret.expose_secret();
+        // Cookie signing keys are derived from the secret and need 64 bytes.
+        if hmac_secret.len() < 64 {
+            return Err(ConfigurationError::InvalidValue {
+                key: "application.hmac_secret",
+                reason: "it must be at least 64 bytes long.",
+            });
+        }
+        if self.application.rate_limit.window_seconds == 0 {
+            return Err(ConfigurationError::InvalidValue {
+                key: "application.rate_limit.window_seconds",
+                reason: "the window cannot be empty.",
+            });
+        }
+        if environment != Environment::Production {
+            return Ok(());
+        }
+
+        if hmac_secret == SAMPLE_HMAC_SECRET {
+            return Err(ConfigurationError::InvalidValue {
+                key: "application.hmac_secret",
+                reason: "the sample secret cannot be used in production.",
+            });
+        }
+        if self.database.password.expose_secret() == SAMPLE_DATABASE_PASSWORD {
+            return Err(ConfigurationError::InvalidValue {
+                key: "database.password",
+                reason: "the sample password cannot be used in production.",
+            });
+        }
+        if !self.database.require_ssl {
+            return Err(ConfigurationError::InvalidValue {
+                key: "database.require_ssl",
+                reason: "connections to the database must be encrypted in production.",
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Loads the configuration of the environment named by `APP_ENVIRONMENT`,
+/// `local` when unset.
+pub fn get_configuration() -> Result<Configuration, ConfigurationError> {
+    let environment: Environment = std::env::var("APP_ENVIRONMENT")
+        .unwrap_or_else(|_| Environment::Local.as_str().into())
+        .try_into()?;
+    load_configuration(environment)
+}
+
+/// Loads `base.toml`, the overlay of `environment` and the `BUBBLE_`
+/// environment variables, in this order.
+pub fn load_configuration(environment: Environment) -> Result<Configuration, ConfigurationError> {
     let base_path = std::env::current_dir()
         .expect("Failed to determine the current directory. (Doesn't exists or not permitted)");
     let configuration_directory = base_path.join("configuration");
@@ -57,11 +255,17 @@ pub fn get_configuration() -> Result<Configuration, config::ConfigError> {
         .add_source(config::File::from(
             configuration_directory.join("base.toml"),
         ))
+        .add_source(config::File::from(
+            configuration_directory.join(format!("{}.toml", environment.as_str())),
+        ))
         .add_source(
             config::Environment::with_prefix("BUBBLE")
                 .prefix_separator("_")
                 .separator("__"),
         )
         .build()?;
-    settings.try_deserialize::<Configuration>()
+    let mut configuration = settings.try_deserialize::<Configuration>()?;
+    configuration.environment = environment;
+    configuration.validate(environment)?;
+    Ok(configuration)
 }
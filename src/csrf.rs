@@ -0,0 +1,189 @@
+//! # CSRF protection
+//! Every browser gets a random token in a cookie signed with `hmac_secret`.
+//! Templates embed it in their forms (see `templates/csrf_field.html`) and
+//! state-changing requests must send it back, as the `csrf_token` form field
+//! or the `X-CSRF-Token` header. Another site can make a browser post to us,
+//! but it cannot read the token.
+//!
+//! The JSON API is exempt: it authenticates with bearer tokens, which browsers
+//! do not attach on their own.
+
+use std::{
+    future::{ready, Ready},
+    pin::Pin,
+};
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    cookie::{Cookie, CookieJar, Key, SameSite},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::{ErrorInternalServerError, PayloadError},
+    http::{
+        header::{CONTENT_TYPE, LOCATION, REFERER},
+        Method,
+    },
+    middleware::Next,
+    web, FromRequest, HttpMessage, HttpRequest, HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use futures_util::{stream, Stream};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Deserialize;
+
+use crate::i18n::Message;
+
+const COOKIE_NAME: &str = "csrf_token";
+const HEADER_NAME: &str = "X-CSRF-Token";
+
+/// Key signing the token cookie, derived from `hmac_secret`.
+#[derive(Clone)]
+pub struct CsrfKey(pub Key);
+
+/// Token of the current browser, to be embedded in the forms.
+#[derive(Clone, Debug)]
+pub struct CsrfToken(String);
+
+impl std::fmt::Display for CsrfToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for CsrfToken {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<CsrfToken>()
+                .cloned()
+                .ok_or_else(|| ErrorInternalServerError("CSRF protection is not enabled.")),
+        )
+    }
+}
+
+/// The token field of a submitted form, the other fields are ignored.
+#[derive(Deserialize)]
+struct CsrfField {
+    csrf_token: Option<String>,
+}
+
+/// Issues the token cookie and rejects state-changing requests that do not
+/// carry the token.
+pub async fn protect_from_csrf(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let key = req
+        .app_data::<web::Data<CsrfKey>>()
+        .expect("The CSRF key is not configured.")
+        .0
+        .clone();
+    let stored_token = req.cookie(COOKIE_NAME).and_then(|cookie| {
+        let mut jar = CookieJar::new();
+        jar.add_original(cookie);
+        jar.signed(&key).get(COOKIE_NAME)
+    });
+
+    if needs_token(&req) {
+        let submitted_token = submitted_token(&mut req).await?;
+        let valid = match (&stored_token, submitted_token) {
+            (Some(stored), Some(submitted)) => {
+                constant_time_eq(stored.value().as_bytes(), submitted.as_bytes())
+            }
+            _ => false,
+        };
+        if !valid {
+            tracing::warn!(path = req.path(), "Request rejected by CSRF protection");
+            FlashMessage::error(Message::new("form-expired")).send();
+            let location = same_origin_referer(req.request()).unwrap_or_else(|| "/".into());
+            let response = HttpResponse::SeeOther()
+                .insert_header((LOCATION, location))
+                .finish();
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+
+    let token = match &stored_token {
+        Some(cookie) => cookie.value().to_owned(),
+        None => rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect(),
+    };
+    req.extensions_mut().insert(CsrfToken(token.clone()));
+
+    // Static assets are cached publicly, they must not carry anyone's token.
+    let issue_cookie = stored_token.is_none() && !req.path().starts_with("/static/");
+    let mut response = next.call(req).await?;
+    if issue_cookie {
+        let mut jar = CookieJar::new();
+        jar.signed_mut(&key).add(
+            Cookie::build(COOKIE_NAME, token)
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Lax)
+                .finish(),
+        );
+        for cookie in jar.delta() {
+            response.response_mut().add_cookie(cookie)?;
+        }
+    }
+    Ok(response.map_into_left_body())
+}
+
+/// Safe methods and the JSON API do not need a token.
+fn needs_token(req: &ServiceRequest) -> bool {
+    let safe = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    !safe && !req.path().starts_with("/api/")
+}
+
+/// Reads the token from the header or, for forms, from the body. The body is
+/// put back for the handler.
+async fn submitted_token(req: &mut ServiceRequest) -> Result<Option<String>, actix_web::Error> {
+    if let Some(token) = req
+        .headers()
+        .get(HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+    {
+        return Ok(Some(token.to_owned()));
+    }
+    let is_form = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/x-www-form-urlencoded"));
+    if !is_form {
+        return Ok(None);
+    }
+
+    let body = req.extract::<web::Bytes>().await?;
+    let token = std::str::from_utf8(&body)
+        .ok()
+        .and_then(|body| web::Query::<CsrfField>::from_query(body).ok())
+        .and_then(|field| field.into_inner().csrf_token);
+    let stream: Pin<Box<dyn Stream<Item = Result<web::Bytes, PayloadError>>>> =
+        Box::pin(stream::once(ready(Ok(body))));
+    req.set_payload(Payload::from(stream));
+    Ok(token)
+}
+
+/// Path of the referring page, when it belongs to this site, to send the
+/// user back to the form.
+pub(crate) fn same_origin_referer(req: &HttpRequest) -> Option<String> {
+    let referer = reqwest::Url::parse(req.headers().get(REFERER)?.to_str().ok()?).ok()?;
+    if referer.authority() != req.connection_info().host() {
+        return None;
+    }
+    Some(match referer.query() {
+        Some(query) => format!("{}?{}", referer.path(), query),
+        None => referer.path().to_owned(),
+    })
+}
+
+/// Compares without leaking through timing how many bytes match.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -1,14 +1,77 @@
-use validator::ValidateLength;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
+use unicode_properties::{GeneralCategory, UnicodeGeneralCategory};
+use unicode_segmentation::UnicodeSegmentation;
+use utoipa::ToSchema;
+
+use super::{
+    callback_slot::{CallbackSlot, CallbackSlotError, OfficeHours},
+    topic::{Topic, TopicError},
+};
 
 /// An incoming call request that needs to be processed.
+#[derive(Debug)]
 pub struct NewCallRequest {
     pub phone_number: CallRequestPhoneNumber,
     pub contact_name: CallRequestContactName,
+    /// Preferred callback slots, none meaning any time during office hours.
+    pub callback_slots: Vec<CallbackSlot>,
+    /// Reason of the call, none when it is not among the offered topics.
+    pub topic_id: Option<Uuid>,
 }
 
+/// Validation failures of a new call request, keyed by field.
+#[derive(thiserror::Error, Debug, Default, Clone, PartialEq, Eq)]
+#[error("The call request is not valid.")]
+pub struct CallRequestValidationError {
+    pub phone_number: Option<PhoneNumberError>,
+    pub contact_name: Option<ContactNameError>,
+    pub callback_slots: Option<CallbackSlotError>,
+    pub topic: Option<TopicError>,
+}
+
+impl NewCallRequest {
+    /// Validates every field, reporting all the failures at once.
+    pub fn parse(
+        phone_number: String,
+        contact_name: String,
+        callback_slots: &[String],
+        office_hours: &OfficeHours,
+        topic: &str,
+        offered_topics: &[Topic],
+    ) -> Result<NewCallRequest, CallRequestValidationError> {
+        match (
+            CallRequestPhoneNumber::parse(phone_number),
+            CallRequestContactName::parse(contact_name),
+            office_hours.parse_preferences(callback_slots),
+            Topic::choose(topic, offered_topics),
+        ) {
+            (Ok(phone_number), Ok(contact_name), Ok(callback_slots), Ok(topic_id)) => {
+                Ok(NewCallRequest {
+                    phone_number,
+                    contact_name,
+                    callback_slots,
+                    topic_id,
+                })
+            }
+            (phone_number, contact_name, callback_slots, topic_id) => {
+                Err(CallRequestValidationError {
+                    phone_number: phone_number.err(),
+                    contact_name: contact_name.err(),
+                    callback_slots: callback_slots.err(),
+                    topic: topic_id.err(),
+                })
+            }
+        }
+    }
+}
+
+/// Trimmed contact name, free of control characters and markup.
 #[derive(Debug)]
 pub struct CallRequestContactName(String);
-#[derive(Debug)]
+/// Phone number normalized in E.164 format (e.g. `+393208946581`).
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CallRequestPhoneNumber(String);
 
 impl AsRef<str> for CallRequestPhoneNumber {
@@ -23,30 +86,276 @@ impl AsRef<str> for CallRequestContactName {
     }
 }
 
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactNameError {
+    #[error("The contact name is empty.")]
+    Empty,
+    #[error("The contact name is too short.")]
+    TooShort,
+    #[error("The contact name is too long.")]
+    TooLong,
+    #[error("The contact name contains characters that are not allowed.")]
+    ForbiddenCharacters,
+}
+
 impl CallRequestContactName {
-    pub fn parse(s: String) -> Result<CallRequestContactName, String> {
-        if s.validate_length(Some(2), Some(128), None) {
-            Ok(Self(s))
+    /// Lengths are measured in graphemes, what a user perceives as characters.
+    const MIN_LENGTH: usize = 2;
+    const MAX_LENGTH: usize = 128;
+    const FORBIDDEN_CHARACTERS: [char; 10] = ['/', '(', ')', '"', '<', '>', '\\', '{', '}', '`'];
+
+    pub fn parse(s: String) -> Result<CallRequestContactName, ContactNameError> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ContactNameError::Empty);
+        }
+        // Invisible format characters, e.g. bidi overrides and zero-width
+        // spaces, could make a name look like another one to staff.
+        if s.chars().any(|c| {
+            c.is_control()
+                || c.general_category() == GeneralCategory::Format
+                || Self::FORBIDDEN_CHARACTERS.contains(&c)
+        }) {
+            return Err(ContactNameError::ForbiddenCharacters);
+        }
+
+        let length = s.graphemes(true).count();
+        if length < Self::MIN_LENGTH {
+            Err(ContactNameError::TooShort)
+        } else if length > Self::MAX_LENGTH {
+            Err(ContactNameError::TooLong)
         } else {
-            Err(format!("Invalid contact name: {}", s))
+            Ok(Self(s.to_owned()))
         }
     }
 }
 
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhoneNumberError {
+    #[error("The phone number is empty.")]
+    Empty,
+    #[error(
+        "The phone number can only contain digits, spaces, dashes, dots, brackets and a leading +."
+    )]
+    ForbiddenCharacters,
+    #[error("The phone number could not be understood.")]
+    Unparsable,
+    #[error("The phone number does not exist.")]
+    Invalid,
+}
+
 impl CallRequestPhoneNumber {
-    pub fn parse(s: String) -> Result<CallRequestPhoneNumber, String> {
-        if s.validate_length(Some(10), Some(12), None) {
-            Ok(Self(s))
-        } else {
-            Err(format!("Invalid phone number: {}", s))
+    /// Numbers without an international prefix (`+` or `00`) are considered italian.
+    const DEFAULT_COUNTRY: phonenumber::country::Id = phonenumber::country::IT;
+
+    pub fn parse(s: String) -> Result<CallRequestPhoneNumber, PhoneNumberError> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(PhoneNumberError::Empty);
+        }
+        let allowed_characters = s.chars().enumerate().all(|(i, c)| {
+            c.is_ascii_digit() || matches!(c, ' ' | '-' | '.' | '(' | ')') || (i == 0 && c == '+')
+        });
+        if !allowed_characters {
+            return Err(PhoneNumberError::ForbiddenCharacters);
+        }
+
+        let number = phonenumber::parse(Some(Self::DEFAULT_COUNTRY), s)
+            .map_err(|_| PhoneNumberError::Unparsable)?;
+        if !phonenumber::is_valid(&number) {
+            return Err(PhoneNumberError::Invalid);
+        }
+
+        Ok(Self(
+            number.format().mode(phonenumber::Mode::E164).to_string(),
+        ))
+    }
+}
+
+/// Lifecycle of a call request.
+///
+/// - `Pending` can be assigned, taken in progress or cancelled.
+/// - `Assigned` can be released back to pending, taken in progress or cancelled.
+/// - `InProgress` ends as completed, unreachable or cancelled.
+/// - `Unreachable` can be retried (in progress) or cancelled.
+/// - `Completed` and `Cancelled` are terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "call_request_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum CallRequestStatus {
+    Pending,
+    Assigned,
+    InProgress,
+    Unreachable,
+    Completed,
+    Cancelled,
+}
+
+impl CallRequestStatus {
+    pub const ALL: [CallRequestStatus; 6] = [
+        CallRequestStatus::Pending,
+        CallRequestStatus::Assigned,
+        CallRequestStatus::InProgress,
+        CallRequestStatus::Unreachable,
+        CallRequestStatus::Completed,
+        CallRequestStatus::Cancelled,
+    ];
+
+    /// Statuses reachable from the current one.
+    pub fn next_statuses(self) -> &'static [CallRequestStatus] {
+        use CallRequestStatus::*;
+        match self {
+            Pending => &[Assigned, InProgress, Cancelled],
+            Assigned => &[Pending, InProgress, Cancelled],
+            InProgress => &[Completed, Unreachable, Cancelled],
+            Unreachable => &[InProgress, Cancelled],
+            Completed | Cancelled => &[],
+        }
+    }
+
+    pub fn can_transition_to(self, next: CallRequestStatus) -> bool {
+        self.next_statuses().contains(&next)
+    }
+
+    pub fn is_terminal(self) -> bool {
+        self.next_statuses().is_empty()
+    }
+
+    /// Value used in the database and in forms.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CallRequestStatus::Pending => "pending",
+            CallRequestStatus::Assigned => "assigned",
+            CallRequestStatus::InProgress => "in_progress",
+            CallRequestStatus::Unreachable => "unreachable",
+            CallRequestStatus::Completed => "completed",
+            CallRequestStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("Unknown call request status.")]
+pub struct UnknownCallRequestStatus;
+
+impl std::str::FromStr for CallRequestStatus {
+    type Err = UnknownCallRequestStatus;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CallRequestStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == s)
+            .ok_or(UnknownCallRequestStatus)
+    }
+}
+
+impl std::fmt::Display for CallRequestStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            CallRequestStatus::Pending => "Pending",
+            CallRequestStatus::Assigned => "Assigned",
+            CallRequestStatus::InProgress => "In progress",
+            CallRequestStatus::Unreachable => "Unreachable",
+            CallRequestStatus::Completed => "Completed",
+            CallRequestStatus::Cancelled => "Cancelled",
+        };
+        f.write_str(label)
+    }
+}
+
+/// A registered call request, the only way to change its status is through
+/// [`CallRequest::transition`].
+#[derive(Debug)]
+pub struct CallRequest {
+    id: Uuid,
+    status: CallRequestStatus,
+    assigned_to: Option<Uuid>,
+}
+
+/// A status change performed by a user, to be recorded in the history.
+#[derive(Debug)]
+pub struct CallRequestTransition {
+    pub call_request_id: Uuid,
+    pub from: CallRequestStatus,
+    pub to: CallRequestStatus,
+    pub changed_by: Uuid,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("A call request cannot go from {from} to {to}.")]
+pub struct InvalidStatusTransition {
+    pub from: CallRequestStatus,
+    pub to: CallRequestStatus,
+}
+
+impl CallRequest {
+    /// Rebuilds a call request from its stored state.
+    pub fn restore(id: Uuid, status: CallRequestStatus, assigned_to: Option<Uuid>) -> Self {
+        Self {
+            id,
+            status,
+            assigned_to,
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn status(&self) -> CallRequestStatus {
+        self.status
+    }
+
+    pub fn assigned_to(&self) -> Option<Uuid> {
+        self.assigned_to
+    }
+
+    /// Moves the call request to `to` on behalf of `actor`.
+    ///
+    /// Assigning a call request gives it to the actor, moving it back to
+    /// pending releases it.
+    pub fn transition(
+        &mut self,
+        to: CallRequestStatus,
+        actor: Uuid,
+    ) -> Result<CallRequestTransition, InvalidStatusTransition> {
+        let from = self.status;
+        if !from.can_transition_to(to) {
+            return Err(InvalidStatusTransition { from, to });
         }
+
+        match to {
+            CallRequestStatus::Assigned => self.assigned_to = Some(actor),
+            CallRequestStatus::Pending => self.assigned_to = None,
+            _ => {}
+        }
+        self.status = to;
+
+        Ok(CallRequestTransition {
+            call_request_id: self.id,
+            from,
+            to,
+            changed_by: actor,
+            changed_at: Utc::now(),
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::CallRequestPhoneNumber;
+    use super::{
+        CallRequest, CallRequestContactName, CallRequestPhoneNumber, CallRequestStatus,
+        CallRequestValidationError, ContactNameError, NewCallRequest, PhoneNumberError,
+    };
+    use crate::domain::{
+        callback_slot::{CallbackSlot, CallbackSlotError, DayPeriod, OfficeHours, TimeRange},
+        topic::TopicError,
+    };
+    use chrono::{NaiveTime, Weekday};
     use claims::{assert_err, assert_ok};
+    use proptest::prelude::*;
+    use sqlx::types::Uuid;
 
     #[test]
     fn empty_phone_number_is_rejected() {
@@ -59,4 +368,271 @@ mod tests {
         let phone_number = "3208946581".to_string();
         assert_ok!(CallRequestPhoneNumber::parse(phone_number));
     }
+
+    #[test]
+    fn phone_numbers_are_normalized_in_e164_format() {
+        for phone_number in [
+            "3208946581",
+            "320 894 6581",
+            "320-894-6581",
+            "+39 320 894 6581",
+            "0039 320 894 6581",
+            "+393208946581",
+        ] {
+            let parsed = assert_ok!(CallRequestPhoneNumber::parse(phone_number.to_string()));
+            assert_eq!(parsed.as_ref(), "+393208946581", "input: {}", phone_number);
+        }
+    }
+
+    #[test]
+    fn italian_landlines_and_international_numbers_are_accepted() {
+        let cases = [
+            ("06 6988 4231", "+390669884231"),
+            ("+44 20 7946 0958", "+442079460958"),
+            ("0044 20 7946 0958", "+442079460958"),
+            ("+1 (415) 555-2671", "+14155552671"),
+        ];
+        for (phone_number, expected) in cases {
+            let parsed = assert_ok!(CallRequestPhoneNumber::parse(phone_number.to_string()));
+            assert_eq!(parsed.as_ref(), expected);
+        }
+    }
+
+    #[test]
+    fn malformed_phone_numbers_are_rejected_with_a_reason() {
+        let cases = [
+            ("   ", PhoneNumberError::Empty),
+            ("abcdefghij", PhoneNumberError::ForbiddenCharacters),
+            ("320 894 658a", PhoneNumberError::ForbiddenCharacters),
+            ("320+8946581", PhoneNumberError::ForbiddenCharacters),
+            ("12345678901", PhoneNumberError::Invalid),
+        ];
+        for (phone_number, expected) in cases {
+            assert_eq!(
+                CallRequestPhoneNumber::parse(phone_number.to_string()),
+                Err(expected),
+                "input: {}",
+                phone_number
+            );
+        }
+    }
+
+    /// Italian mobile numbers written with an optional international prefix
+    /// and arbitrary separators, together with their E.164 form.
+    fn italian_mobile_number() -> impl Strategy<Value = (String, String)> {
+        (
+            "3[2-4][0-9]",
+            "[0-9]{3}",
+            "[0-9]{4}",
+            prop::sample::select(vec!["", "+39 ", "0039 ", "00 39 "]),
+            prop::sample::select(vec!["", " ", "-", "."]),
+        )
+            .prop_map(|(operator, first, second, prefix, separator)| {
+                (
+                    format!("{prefix}{operator}{separator}{first}{separator}{second}"),
+                    format!("+39{operator}{first}{second}"),
+                )
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn valid_italian_mobile_numbers_are_normalized((input, expected) in italian_mobile_number()) {
+            let parsed = CallRequestPhoneNumber::parse(input);
+            prop_assert_eq!(parsed.as_ref().map(AsRef::as_ref), Ok(expected.as_str()));
+        }
+
+        #[test]
+        fn strings_with_letters_are_rejected(s in "[0-9 ]{0,6}[a-zA-Z]{1,6}[0-9 ]{0,6}") {
+            prop_assert!(CallRequestPhoneNumber::parse(s).is_err());
+        }
+
+        #[test]
+        fn too_short_numbers_are_rejected(s in "[0-9]{1,4}") {
+            prop_assert!(CallRequestPhoneNumber::parse(s).is_err());
+        }
+
+        #[test]
+        fn names_with_control_characters_are_rejected(
+            prefix in "[a-zA-Z]{1,10}",
+            control in "[\\x00-\\x1f\\x7f]",
+            suffix in "[a-zA-Z]{1,10}",
+        ) {
+            let name = format!("{prefix}{control}{suffix}");
+            prop_assert!(CallRequestContactName::parse(name).is_err());
+        }
+
+        #[test]
+        fn reasonable_names_are_accepted(name in "[A-Za-zÀ-ÿ][A-Za-zÀ-ÿ' .-]{0,100}[A-Za-zÀ-ÿ]") {
+            prop_assert!(CallRequestContactName::parse(name).is_ok());
+        }
+    }
+
+    #[test]
+    fn contact_names_are_trimmed() {
+        let name = assert_ok!(CallRequestContactName::parse("  Rino Pape \n".to_string()));
+        assert_eq!(name.as_ref(), "Rino Pape");
+    }
+
+    #[test]
+    fn contact_name_length_is_measured_in_graphemes() {
+        // Two graphemes, but more than two bytes and code points.
+        assert_ok!(CallRequestContactName::parse("Zoë".to_string()));
+        assert_ok!(CallRequestContactName::parse(
+            "e\u{301}e\u{301}".to_string()
+        ));
+        assert_ok!(CallRequestContactName::parse("ё".repeat(128)));
+
+        assert_eq!(
+            CallRequestContactName::parse("e\u{301}".to_string()).map(|_| ()),
+            Err(ContactNameError::TooShort)
+        );
+        assert_eq!(
+            CallRequestContactName::parse("ё".repeat(129)).map(|_| ()),
+            Err(ContactNameError::TooLong)
+        );
+    }
+
+    #[test]
+    fn malformed_contact_names_are_rejected_with_a_reason() {
+        let cases = [
+            ("", ContactNameError::Empty),
+            (" \t\n ", ContactNameError::Empty),
+            ("a", ContactNameError::TooShort),
+            ("Rino\0Pape", ContactNameError::ForbiddenCharacters),
+            ("Rino\u{1b}[31mPape", ContactNameError::ForbiddenCharacters),
+            ("Rino\u{202e}epaP", ContactNameError::ForbiddenCharacters),
+            ("Rino\u{200b}Pape", ContactNameError::ForbiddenCharacters),
+            (
+                "Rino \u{2066}Pape\u{2069}",
+                ContactNameError::ForbiddenCharacters,
+            ),
+            ("\u{feff}Rino Pape", ContactNameError::ForbiddenCharacters),
+            (
+                "<script>alert(1)</script>",
+                ContactNameError::ForbiddenCharacters,
+            ),
+            ("{{ name }}", ContactNameError::ForbiddenCharacters),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(
+                CallRequestContactName::parse(name.to_string()).map(|_| ()),
+                Err(expected),
+                "input: {:?}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn common_names_are_accepted() {
+        for name in [
+            "Gregory Sech",
+            "Anna-Maria D'Amico",
+            "Dr. Ugo Rossi",
+            "José Ñúñez",
+        ] {
+            assert_ok!(CallRequestContactName::parse(name.to_string()));
+        }
+    }
+
+    /// Open on Monday morning only.
+    fn office_hours() -> OfficeHours {
+        let time = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+        OfficeHours::new(
+            chrono_tz::Europe::Rome,
+            TimeRange {
+                start: time(9),
+                end: time(13),
+            },
+            TimeRange {
+                start: time(14),
+                end: time(17),
+            },
+            [CallbackSlot::new(Weekday::Mon, DayPeriod::Morning)].into(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn every_invalid_field_is_reported() {
+        let error = assert_err!(NewCallRequest::parse(
+            "3".to_string(),
+            "a".to_string(),
+            &["mon-afternoon".to_string()],
+            &office_hours(),
+            "ID card",
+            &[]
+        ));
+        assert_eq!(
+            error,
+            CallRequestValidationError {
+                phone_number: Some(PhoneNumberError::Unparsable),
+                contact_name: Some(ContactNameError::TooShort),
+                callback_slots: Some(CallbackSlotError::OutsideOfficeHours),
+                topic: Some(TopicError::Unknown),
+            }
+        );
+
+        let error = assert_err!(NewCallRequest::parse(
+            "3208946581".to_string(),
+            "".to_string(),
+            &[],
+            &office_hours(),
+            "",
+            &[]
+        ));
+        assert_eq!(error.phone_number, None);
+        assert_eq!(error.contact_name, Some(ContactNameError::Empty));
+        assert_eq!(error.callback_slots, None);
+        assert_eq!(error.topic, None);
+    }
+
+    #[test]
+    fn terminal_statuses_cannot_be_left() {
+        use CallRequestStatus::*;
+        for terminal in [Completed, Cancelled] {
+            assert!(terminal.is_terminal());
+            for next in CallRequestStatus::ALL {
+                assert!(!terminal.can_transition_to(next));
+            }
+        }
+    }
+
+    #[test]
+    fn statuses_are_parsed_from_their_stored_value() {
+        for status in CallRequestStatus::ALL {
+            assert_eq!(status.as_str().parse::<CallRequestStatus>(), Ok(status));
+        }
+        assert_err!("done".parse::<CallRequestStatus>());
+    }
+
+    #[test]
+    fn pending_call_request_cannot_be_completed_directly() {
+        let mut call_request =
+            CallRequest::restore(Uuid::new_v4(), CallRequestStatus::Pending, None);
+        assert_err!(call_request.transition(CallRequestStatus::Completed, Uuid::new_v4()));
+        assert_eq!(call_request.status(), CallRequestStatus::Pending);
+    }
+
+    #[test]
+    fn assignment_follows_the_lifecycle() {
+        let actor = Uuid::new_v4();
+        let mut call_request =
+            CallRequest::restore(Uuid::new_v4(), CallRequestStatus::Pending, None);
+
+        let transition = assert_ok!(call_request.transition(CallRequestStatus::Assigned, actor));
+        assert_eq!(transition.from, CallRequestStatus::Pending);
+        assert_eq!(transition.to, CallRequestStatus::Assigned);
+        assert_eq!(call_request.assigned_to(), Some(actor));
+
+        assert_ok!(call_request.transition(CallRequestStatus::Pending, actor));
+        assert_eq!(call_request.assigned_to(), None);
+
+        assert_ok!(call_request.transition(CallRequestStatus::InProgress, actor));
+        assert_ok!(call_request.transition(CallRequestStatus::Unreachable, actor));
+        assert_ok!(call_request.transition(CallRequestStatus::InProgress, actor));
+        assert_ok!(call_request.transition(CallRequestStatus::Completed, actor));
+        assert_eq!(call_request.status(), CallRequestStatus::Completed);
+    }
 }
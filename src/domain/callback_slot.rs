@@ -0,0 +1,412 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// Part of the day in which the office calls back.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, sqlx::Type,
+)]
+#[sqlx(type_name = "day_period", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DayPeriod {
+    Morning,
+    Afternoon,
+}
+
+impl DayPeriod {
+    pub const ALL: [DayPeriod; 2] = [DayPeriod::Morning, DayPeriod::Afternoon];
+
+    /// Value used in the database and in forms.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DayPeriod::Morning => "morning",
+            DayPeriod::Afternoon => "afternoon",
+        }
+    }
+}
+
+/// A part of a weekday in which a citizen prefers to be called back, written
+/// as `mon-morning` in forms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallbackSlot {
+    pub weekday: Weekday,
+    pub period: DayPeriod,
+}
+
+impl CallbackSlot {
+    pub fn new(weekday: Weekday, period: DayPeriod) -> Self {
+        Self { weekday, period }
+    }
+
+    /// ISO day of the week as stored in the database, 1 being Monday.
+    pub fn iso_weekday(&self) -> i16 {
+        self.weekday.number_from_monday() as i16
+    }
+
+    /// Rebuilds a slot from its stored columns.
+    pub fn restore(iso_weekday: i16, period: DayPeriod) -> Option<Self> {
+        let days_from_monday = u8::try_from(iso_weekday.checked_sub(1)?).ok()?;
+        let weekday = Weekday::try_from(days_from_monday).ok()?;
+        Some(Self::new(weekday, period))
+    }
+}
+
+/// Monday morning first.
+impl Ord for CallbackSlot {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.weekday.num_days_from_monday(), self.period)
+            .cmp(&(other.weekday.num_days_from_monday(), other.period))
+    }
+}
+
+impl PartialOrd for CallbackSlot {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::fmt::Display for CallbackSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let weekday = self.weekday.to_string().to_lowercase();
+        write!(f, "{}-{}", weekday, self.period.as_str())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallbackSlotError {
+    #[error("The callback time slot could not be understood.")]
+    Unknown,
+    #[error("The office does not call back in the chosen time slot.")]
+    OutsideOfficeHours,
+}
+
+impl std::str::FromStr for CallbackSlot {
+    type Err = CallbackSlotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (weekday, period) = s.split_once('-').ok_or(CallbackSlotError::Unknown)?;
+        let weekday = weekday
+            .parse::<Weekday>()
+            .map_err(|_| CallbackSlotError::Unknown)?;
+        let period = DayPeriod::ALL
+            .into_iter()
+            .find(|p| p.as_str() == period)
+            .ok_or(CallbackSlotError::Unknown)?;
+        Ok(Self::new(weekday, period))
+    }
+}
+
+/// Time of the day from `start`, included, to `end`, excluded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct TimeRange {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeRange {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        self.start <= time && time < self.end
+    }
+}
+
+impl std::fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}–{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// When the office calls citizens back: the hours of each part of the day,
+/// and the parts of each weekday in which it is open.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "OfficeHoursSettings")]
+pub struct OfficeHours {
+    timezone: Tz,
+    morning: TimeRange,
+    afternoon: TimeRange,
+    slots: BTreeSet<CallbackSlot>,
+}
+
+/// Office hours as written in the configuration, e.g.
+/// `open = { mon = ["morning", "afternoon"], fri = ["morning"] }`.
+#[derive(Deserialize)]
+struct OfficeHoursSettings {
+    timezone: Tz,
+    morning: TimeRange,
+    afternoon: TimeRange,
+    open: std::collections::HashMap<Weekday, Vec<DayPeriod>>,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("The morning must end before the afternoon starts, and both must not be empty.")]
+pub struct InvalidOfficeHours;
+
+impl TryFrom<OfficeHoursSettings> for OfficeHours {
+    type Error = InvalidOfficeHours;
+
+    fn try_from(settings: OfficeHoursSettings) -> Result<Self, Self::Error> {
+        let slots = settings
+            .open
+            .into_iter()
+            .flat_map(|(weekday, periods)| {
+                periods
+                    .into_iter()
+                    .map(move |period| CallbackSlot::new(weekday, period))
+            })
+            .collect();
+        OfficeHours::new(
+            settings.timezone,
+            settings.morning,
+            settings.afternoon,
+            slots,
+        )
+    }
+}
+
+impl OfficeHours {
+    pub fn new(
+        timezone: Tz,
+        morning: TimeRange,
+        afternoon: TimeRange,
+        slots: BTreeSet<CallbackSlot>,
+    ) -> Result<Self, InvalidOfficeHours> {
+        if morning.start >= morning.end
+            || afternoon.start >= afternoon.end
+            || morning.end > afternoon.start
+        {
+            return Err(InvalidOfficeHours);
+        }
+        Ok(Self {
+            timezone,
+            morning,
+            afternoon,
+            slots,
+        })
+    }
+
+    /// Slots in which the office calls back, Monday morning first.
+    pub fn slots(&self) -> impl Iterator<Item = CallbackSlot> + '_ {
+        self.slots.iter().copied()
+    }
+
+    /// Weekdays with at least one open slot.
+    pub fn weekdays(&self) -> Vec<Weekday> {
+        let mut weekdays: Vec<Weekday> = self.slots().map(|slot| slot.weekday).collect();
+        weekdays.dedup();
+        weekdays
+    }
+
+    pub fn is_open(&self, slot: CallbackSlot) -> bool {
+        self.slots.contains(&slot)
+    }
+
+    pub fn hours(&self, period: DayPeriod) -> TimeRange {
+        match period {
+            DayPeriod::Morning => self.morning,
+            DayPeriod::Afternoon => self.afternoon,
+        }
+    }
+
+    /// Slot the office is in at `now`, none when it is closed.
+    pub fn current_slot(&self, now: DateTime<Utc>) -> Option<CallbackSlot> {
+        let local = now.with_timezone(&self.timezone);
+        let period = DayPeriod::ALL
+            .into_iter()
+            .find(|period| self.hours(*period).contains(local.time()))?;
+        let slot = CallbackSlot::new(local.weekday(), period);
+        self.is_open(slot).then_some(slot)
+    }
+
+    /// Whether a citizen with these preferences can be called at `now`. No
+    /// preferences mean any time during office hours.
+    pub fn is_callable(&self, preferences: &[CallbackSlot], now: DateTime<Utc>) -> bool {
+        self.current_slot(now)
+            .is_some_and(|slot| preferences.is_empty() || preferences.contains(&slot))
+    }
+
+    /// Validates the slots chosen in a form, dropping duplicates.
+    pub fn parse_preferences(
+        &self,
+        values: &[String],
+    ) -> Result<Vec<CallbackSlot>, CallbackSlotError> {
+        let slots = values
+            .iter()
+            .map(|value| value.trim().parse::<CallbackSlot>())
+            .collect::<Result<BTreeSet<_>, _>>()?;
+        if !slots.iter().all(|slot| self.is_open(*slot)) {
+            return Err(CallbackSlotError::OutsideOfficeHours);
+        }
+        Ok(slots.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveTime, TimeZone, Utc, Weekday};
+    use claims::{assert_err, assert_ok};
+
+    use super::{CallbackSlot, CallbackSlotError, DayPeriod, OfficeHours, TimeRange};
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    /// Weekday mornings and afternoons, except on Friday afternoon.
+    fn office_hours() -> OfficeHours {
+        let slots = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu]
+            .into_iter()
+            .flat_map(|weekday| DayPeriod::ALL.map(|period| CallbackSlot::new(weekday, period)))
+            .chain([CallbackSlot::new(Weekday::Fri, DayPeriod::Morning)])
+            .collect();
+        OfficeHours::new(
+            chrono_tz::Europe::Rome,
+            TimeRange {
+                start: time(9, 0),
+                end: time(13, 0),
+            },
+            TimeRange {
+                start: time(14, 0),
+                end: time(17, 30),
+            },
+            slots,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn slots_round_trip_through_their_form_value() {
+        for slot in office_hours().slots() {
+            assert_eq!(slot.to_string().parse::<CallbackSlot>(), Ok(slot));
+        }
+        assert_eq!(
+            CallbackSlot::new(Weekday::Wed, DayPeriod::Afternoon).to_string(),
+            "wed-afternoon"
+        );
+    }
+
+    #[test]
+    fn slots_round_trip_through_their_stored_columns() {
+        for slot in office_hours().slots() {
+            assert_eq!(
+                CallbackSlot::restore(slot.iso_weekday(), slot.period),
+                Some(slot)
+            );
+        }
+        assert_eq!(CallbackSlot::restore(0, DayPeriod::Morning), None);
+        assert_eq!(CallbackSlot::restore(8, DayPeriod::Morning), None);
+    }
+
+    #[test]
+    fn preferences_must_fall_within_office_hours() {
+        let office_hours = office_hours();
+
+        let slots = assert_ok!(office_hours.parse_preferences(&[
+            "tue-afternoon".to_string(),
+            "mon-morning".to_string(),
+            "tue-afternoon".to_string(),
+        ]));
+        assert_eq!(
+            slots,
+            [
+                CallbackSlot::new(Weekday::Mon, DayPeriod::Morning),
+                CallbackSlot::new(Weekday::Tue, DayPeriod::Afternoon),
+            ]
+        );
+        assert_eq!(office_hours.parse_preferences(&[]), Ok(vec![]));
+
+        for (values, expected) in [
+            (["fri-afternoon"], CallbackSlotError::OutsideOfficeHours),
+            (["sun-morning"], CallbackSlotError::OutsideOfficeHours),
+            (["mon-evening"], CallbackSlotError::Unknown),
+            (["someday"], CallbackSlotError::Unknown),
+        ] {
+            let values = values.map(String::from);
+            assert_eq!(
+                office_hours.parse_preferences(&values),
+                Err(expected),
+                "{:?}",
+                values
+            );
+        }
+    }
+
+    #[test]
+    fn current_slot_follows_the_office_timezone() {
+        let office_hours = office_hours();
+        // Tuesday 2024-10-08, Rome is two hours ahead of UTC in summer.
+        let cases = [
+            (
+                Utc.with_ymd_and_hms(2024, 10, 8, 7, 0, 0),
+                Some(DayPeriod::Morning),
+            ),
+            (Utc.with_ymd_and_hms(2024, 10, 8, 6, 59, 0), None),
+            (Utc.with_ymd_and_hms(2024, 10, 8, 11, 30, 0), None),
+            (
+                Utc.with_ymd_and_hms(2024, 10, 8, 12, 0, 0),
+                Some(DayPeriod::Afternoon),
+            ),
+            (Utc.with_ymd_and_hms(2024, 10, 8, 15, 30, 0), None),
+        ];
+        for (now, expected) in cases {
+            let now = now.unwrap();
+            assert_eq!(
+                office_hours.current_slot(now),
+                expected.map(|period| CallbackSlot::new(Weekday::Tue, period)),
+                "{}",
+                now
+            );
+        }
+        // One hour ahead in winter.
+        let now = Utc.with_ymd_and_hms(2024, 12, 3, 8, 0, 0).unwrap();
+        assert_eq!(
+            office_hours.current_slot(now),
+            Some(CallbackSlot::new(Weekday::Tue, DayPeriod::Morning))
+        );
+        // Closed on Friday afternoon.
+        let now = Utc.with_ymd_and_hms(2024, 10, 11, 13, 0, 0).unwrap();
+        assert_eq!(office_hours.current_slot(now), None);
+    }
+
+    #[test]
+    fn citizens_without_preferences_are_callable_during_office_hours() {
+        let office_hours = office_hours();
+        let tuesday_morning = Utc.with_ymd_and_hms(2024, 10, 8, 8, 0, 0).unwrap();
+        let sunday = Utc.with_ymd_and_hms(2024, 10, 13, 8, 0, 0).unwrap();
+
+        assert!(office_hours.is_callable(&[], tuesday_morning));
+        assert!(!office_hours.is_callable(&[], sunday));
+        assert!(office_hours.is_callable(
+            &[
+                CallbackSlot::new(Weekday::Mon, DayPeriod::Afternoon),
+                CallbackSlot::new(Weekday::Tue, DayPeriod::Morning),
+            ],
+            tuesday_morning
+        ));
+        assert!(!office_hours.is_callable(
+            &[CallbackSlot::new(Weekday::Tue, DayPeriod::Afternoon)],
+            tuesday_morning
+        ));
+    }
+
+    #[test]
+    fn the_morning_must_end_before_the_afternoon() {
+        let range = |start, end| TimeRange {
+            start: time(start, 0),
+            end: time(end, 0),
+        };
+        let new = |morning, afternoon| {
+            OfficeHours::new(chrono_tz::UTC, morning, afternoon, Default::default())
+        };
+
+        assert_ok!(new(range(9, 13), range(13, 17)));
+        assert_err!(new(range(9, 14), range(13, 17)));
+        assert_err!(new(range(13, 9), range(14, 17)));
+        assert_err!(new(range(9, 13), range(17, 17)));
+    }
+}
@@ -1 +1,5 @@
 pub mod call_request;
+pub mod callback_slot;
+pub mod reference_code;
+pub mod topic;
+pub mod user;
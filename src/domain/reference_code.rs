@@ -0,0 +1,210 @@
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+
+/// Crockford's base32: no I, L, O and U, easily mistaken when read aloud.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const PREFIX: &str = "BBL";
+/// 2^30 serials: with a million call requests, a new one is taken about once
+/// in a thousand.
+const SERIAL_LENGTH: usize = 6;
+/// Serials of the codes handed out before they were widened, still valid.
+const LEGACY_SERIAL_LENGTH: usize = 4;
+const CHECK_LENGTH: usize = 2;
+
+/// Key signing the reference codes, derived from `hmac_secret`.
+#[derive(Clone)]
+pub struct ReferenceCodeKey(Secret<String>);
+
+impl ReferenceCodeKey {
+    pub fn new(hmac_secret: Secret<String>) -> Self {
+        Self(hmac_secret)
+    }
+
+    /// Check characters of a serial, the first 10 bits of its HMAC.
+    fn check(&self, serial: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.0.expose_secret().as_bytes())
+            .expect("HMAC accepts keys of any length.");
+        mac.update(format!("reference-code:{}", serial).as_bytes());
+        let digest = mac.finalize().into_bytes();
+        let bits = u16::from_be_bytes([digest[0], digest[1]]) >> 6;
+        [bits >> 5, bits & 0b11111]
+            .into_iter()
+            .map(|index| ALPHABET[index as usize] as char)
+            .collect()
+    }
+}
+
+/// Code given to citizens to look up their call request, e.g. `BBL-7K3Q9F-M2`:
+/// a random serial followed by a check signed with a server secret, so that
+/// valid codes cannot be made up and typos are caught.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceCode(String);
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceCodeError {
+    #[error("The reference code is not valid.")]
+    Invalid,
+}
+
+impl ReferenceCode {
+    /// A new code, unique only with high probability: callers must check it
+    /// is not taken yet.
+    pub fn generate(key: &ReferenceCodeKey) -> Self {
+        let mut rng = rand::thread_rng();
+        let serial: String = (0..SERIAL_LENGTH)
+            .map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char)
+            .collect();
+        Self::assemble(&serial, key)
+    }
+
+    /// Parses a code typed by a citizen, regardless of case, spacing, dashes
+    /// and the prefix. As in Crockford's base32, O is read as 0, I and L as 1.
+    pub fn parse(s: &str, key: &ReferenceCodeKey) -> Result<Self, ReferenceCodeError> {
+        let characters: String = s
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        // Serials can start with the letters of the prefix too.
+        let characters: String = match characters.strip_prefix(PREFIX) {
+            Some(rest) if serial_length(rest).is_some() => rest,
+            _ => &characters,
+        }
+        .chars()
+        .map(|c| match c {
+            'O' => '0',
+            'I' | 'L' => '1',
+            c => c,
+        })
+        .collect();
+        let serial_length = serial_length(&characters).ok_or(ReferenceCodeError::Invalid)?;
+        if !characters.bytes().all(|c| ALPHABET.contains(&c)) {
+            return Err(ReferenceCodeError::Invalid);
+        }
+        let (serial, check) = characters.split_at(serial_length);
+        if key.check(serial) != check {
+            return Err(ReferenceCodeError::Invalid);
+        }
+        Ok(Self::assemble(serial, key))
+    }
+
+    /// Wraps a code read from the database, which was generated here.
+    pub fn restore(code: String) -> Self {
+        Self(code)
+    }
+
+    fn assemble(serial: &str, key: &ReferenceCodeKey) -> Self {
+        Self(format!("{}-{}-{}", PREFIX, serial, key.check(serial)))
+    }
+}
+
+/// Length of the serial of a code made of `characters`, when it has a valid
+/// length.
+fn serial_length(characters: &str) -> Option<usize> {
+    [SERIAL_LENGTH, LEGACY_SERIAL_LENGTH]
+        .into_iter()
+        .find(|length| characters.len() == length + CHECK_LENGTH)
+}
+
+impl AsRef<str> for ReferenceCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ReferenceCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use secrecy::Secret;
+
+    use super::{ReferenceCode, ReferenceCodeError, ReferenceCodeKey};
+
+    fn key(secret: &str) -> ReferenceCodeKey {
+        ReferenceCodeKey::new(Secret::new(secret.to_owned()))
+    }
+
+    #[test]
+    fn generated_codes_are_valid() {
+        let key = key("secret");
+        for _ in 0..100 {
+            let code = ReferenceCode::generate(&key);
+            assert_eq!(code.as_ref().len(), "BBL-7K3Q9F-M2".len());
+            assert!(code.as_ref().starts_with("BBL-"));
+            assert_eq!(assert_ok!(ReferenceCode::parse(code.as_ref(), &key)), code);
+        }
+    }
+
+    #[test]
+    fn codes_are_read_leniently() {
+        let key = key("secret");
+        let code = ReferenceCode::generate(&key);
+        let serial_and_check = code.as_ref().trim_start_matches("BBL-").replace('-', "");
+
+        for typed in [
+            code.as_ref().to_lowercase(),
+            format!(" {} ", serial_and_check),
+            format!(
+                "bbl {}",
+                serial_and_check.replace('0', "o").replace('1', "l")
+            ),
+        ] {
+            assert_eq!(
+                ReferenceCode::parse(&typed, &key),
+                Ok(code.clone()),
+                "{}",
+                typed
+            );
+        }
+    }
+
+    #[test]
+    fn codes_with_legacy_serials_are_still_valid() {
+        let key = key("secret");
+        let code = ReferenceCode::assemble("7K3Q", &key);
+
+        assert_eq!(assert_ok!(ReferenceCode::parse(code.as_ref(), &key)), code);
+        let typed = code.as_ref().trim_start_matches("BBL-").to_lowercase();
+        assert_eq!(assert_ok!(ReferenceCode::parse(&typed, &key)), code);
+    }
+
+    #[test]
+    fn made_up_codes_are_rejected() {
+        let key = key("secret");
+        let code = ReferenceCode::generate(&key);
+        let mut forged = code.as_ref().to_owned();
+        let last = forged.pop().unwrap();
+        forged.push(if last == 'Z' { 'Y' } else { 'Z' });
+
+        assert_eq!(
+            ReferenceCode::parse(&forged, &key),
+            Err(ReferenceCodeError::Invalid)
+        );
+        // Checks depend on the secret.
+        let other_key = self::key("another secret");
+        let accepted = (0..100)
+            .map(|_| ReferenceCode::generate(&other_key))
+            .filter(|code| ReferenceCode::parse(code.as_ref(), &key).is_ok())
+            .count();
+        assert!(accepted < 5, "{}", accepted);
+        for typed in [
+            "",
+            "BBL-",
+            "BBL-7K3Q",
+            "BBL-7K3Q-M2X",
+            "BBL-7K3Q9-M2",
+            "BBL-7K3Q9F2-M2",
+            "BBL-7K3U9F-M2",
+            "BBL-7K3Q9F-M!",
+        ] {
+            assert_err!(ReferenceCode::parse(typed, &key), "{}", typed);
+        }
+    }
+}
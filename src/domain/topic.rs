@@ -0,0 +1,151 @@
+use sqlx::types::Uuid;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A reason citizens ask to be called for, e.g. an ID card renewal, with the
+/// team handling it. Retired topics are no longer offered.
+#[derive(Debug, Clone)]
+pub struct Topic {
+    pub id: Uuid,
+    pub name: String,
+    pub team: Option<String>,
+    pub active: bool,
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopicError {
+    #[error("The chosen topic is not available.")]
+    Unknown,
+}
+
+impl Topic {
+    /// Validates the topic chosen in a form among the offered ones, an empty
+    /// choice meaning none of them.
+    pub fn choose(value: &str, offered: &[Topic]) -> Result<Option<Uuid>, TopicError> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(None);
+        }
+        let id = Uuid::parse_str(value).map_err(|_| TopicError::Unknown)?;
+        offered
+            .iter()
+            .find(|topic| topic.id == id && topic.active)
+            .map(|topic| Some(topic.id))
+            .ok_or(TopicError::Unknown)
+    }
+}
+
+/// Trimmed name of a topic or of a team, free of control characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicName(String);
+
+impl AsRef<str> for TopicName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopicNameError {
+    #[error("The name is empty.")]
+    Empty,
+    #[error("The name is too long.")]
+    TooLong,
+    #[error("The name contains characters that are not allowed.")]
+    ForbiddenCharacters,
+}
+
+impl TopicName {
+    /// Measured in graphemes, as contact names.
+    const MAX_LENGTH: usize = 64;
+
+    pub fn parse(s: &str) -> Result<TopicName, TopicNameError> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(TopicNameError::Empty);
+        }
+        if s.chars().any(char::is_control) {
+            return Err(TopicNameError::ForbiddenCharacters);
+        }
+        if s.graphemes(true).count() > Self::MAX_LENGTH {
+            return Err(TopicNameError::TooLong);
+        }
+        Ok(Self(s.to_owned()))
+    }
+
+    /// Parses an optional name, e.g. a team, empty meaning none.
+    pub fn parse_optional(s: &str) -> Result<Option<TopicName>, TopicNameError> {
+        match Self::parse(s) {
+            Ok(name) => Ok(Some(name)),
+            Err(TopicNameError::Empty) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use sqlx::types::Uuid;
+
+    use super::{Topic, TopicError, TopicName, TopicNameError};
+
+    fn topic(name: &str, active: bool) -> Topic {
+        Topic {
+            id: Uuid::new_v4(),
+            name: name.to_owned(),
+            team: None,
+            active,
+        }
+    }
+
+    #[test]
+    fn only_offered_topics_can_be_chosen() {
+        let id_card = topic("ID card", true);
+        let retired = topic("Dog licence", false);
+        let offered = [id_card.clone(), retired.clone()];
+
+        assert_eq!(Topic::choose("", &offered), Ok(None));
+        assert_eq!(Topic::choose("  ", &offered), Ok(None));
+        assert_eq!(
+            Topic::choose(&id_card.id.to_string(), &offered),
+            Ok(Some(id_card.id))
+        );
+        for value in [
+            retired.id.to_string(),
+            Uuid::new_v4().to_string(),
+            "ID card".to_string(),
+        ] {
+            assert_eq!(
+                Topic::choose(&value, &offered),
+                Err(TopicError::Unknown),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn names_are_trimmed_and_bounded() {
+        let name = assert_ok!(TopicName::parse("  Residency change \n"));
+        assert_eq!(name.as_ref(), "Residency change");
+        assert_ok!(TopicName::parse(&"é".repeat(64)));
+
+        assert_eq!(TopicName::parse(" "), Err(TopicNameError::Empty));
+        assert_eq!(
+            TopicName::parse(&"é".repeat(65)),
+            Err(TopicNameError::TooLong)
+        );
+        assert_eq!(
+            TopicName::parse("ID\u{0}card"),
+            Err(TopicNameError::ForbiddenCharacters)
+        );
+    }
+
+    #[test]
+    fn teams_are_optional() {
+        assert_eq!(TopicName::parse_optional(""), Ok(None));
+        let team = assert_ok!(TopicName::parse_optional("Registry office"));
+        assert_eq!(team.unwrap().as_ref(), "Registry office");
+        assert_err!(TopicName::parse_optional("Registry\u{7}office"));
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Role of an authenticated user.
+///
+/// Citizens use the service anonymously and have no role. Roles are ordered
+/// by privilege, each one grants everything the previous ones do.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type, ToSchema,
+)]
+#[sqlx(type_name = "user_role", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    OfficeWorker,
+    Supervisor,
+    Administrator,
+}
+
+impl UserRole {
+    pub const ALL: [UserRole; 3] = [
+        UserRole::OfficeWorker,
+        UserRole::Supervisor,
+        UserRole::Administrator,
+    ];
+
+    /// Whether a user with this role may access what `required` may.
+    pub fn grants(self, required: UserRole) -> bool {
+        self >= required
+    }
+
+    /// Value used in the database and in forms.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UserRole::OfficeWorker => "office_worker",
+            UserRole::Supervisor => "supervisor",
+            UserRole::Administrator => "administrator",
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("Unknown role, expected one of: office_worker, supervisor, administrator.")]
+pub struct UnknownUserRole;
+
+impl std::str::FromStr for UserRole {
+    type Err = UnknownUserRole;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        UserRole::ALL
+            .into_iter()
+            .find(|role| role.as_str() == s)
+            .ok_or(UnknownUserRole)
+    }
+}
+
+impl std::fmt::Display for UserRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            UserRole::OfficeWorker => "Office worker",
+            UserRole::Supervisor => "Supervisor",
+            UserRole::Administrator => "Administrator",
+        };
+        f.write_str(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UserRole;
+
+    #[test]
+    fn every_role_grants_itself() {
+        for role in UserRole::ALL {
+            assert!(role.grants(role));
+        }
+    }
+
+    #[test]
+    fn higher_roles_grant_lower_ones() {
+        assert!(UserRole::Administrator.grants(UserRole::Supervisor));
+        assert!(UserRole::Administrator.grants(UserRole::OfficeWorker));
+        assert!(UserRole::Supervisor.grants(UserRole::OfficeWorker));
+    }
+
+    #[test]
+    fn roles_are_parsed_from_their_stored_value() {
+        for role in UserRole::ALL {
+            assert_eq!(role.as_str().parse::<UserRole>(), Ok(role));
+        }
+        assert!("admin".parse::<UserRole>().is_err());
+    }
+
+    #[test]
+    fn lower_roles_do_not_grant_higher_ones() {
+        assert!(!UserRole::OfficeWorker.grants(UserRole::Supervisor));
+        assert!(!UserRole::OfficeWorker.grants(UserRole::Administrator));
+        assert!(!UserRole::Supervisor.grants(UserRole::Administrator));
+    }
+}
@@ -0,0 +1,429 @@
+//! # Internationalization
+//! User-facing text is translated through the Fluent catalogs of `locales/`,
+//! in Italian and English. The language is the one picked with the switcher
+//! (see [`crate::routes::switch_language`]), kept in the `lang` cookie, or
+//! else the first supported one in `Accept-Language`, or else the configured
+//! default.
+//!
+//! Flash messages are sent as [`Message`]s, an id with its arguments, and
+//! translated by the page showing them.
+//!
+//! The JSON API and the CLI speak English only.
+
+use std::{
+    collections::BTreeMap,
+    future::{ready, Ready},
+    sync::LazyLock,
+};
+
+use actix_web::{dev::Payload, http::header::ACCEPT_LANGUAGE, web, FromRequest, HttpRequest};
+use actix_web_flash_messages::{FlashMessage, Level};
+use chrono::Weekday;
+use fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{
+    call_request::{CallRequestStatus, ContactNameError, PhoneNumberError},
+    callback_slot::{CallbackSlot, CallbackSlotError, DayPeriod},
+    topic::TopicError,
+    user::UserRole,
+};
+
+/// Cookie keeping the language picked with the switcher.
+pub const LANGUAGE_COOKIE: &str = "lang";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Locale {
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "it")]
+    Italian,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::Italian, Locale::English];
+
+    /// Language tag, as used in the `lang` attribute and the cookie.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::Italian => "it",
+        }
+    }
+
+    /// Name of the language in the language itself, for the switcher.
+    pub fn name(self) -> &'static str {
+        match self {
+            Locale::English => "English",
+            Locale::Italian => "Italiano",
+        }
+    }
+
+    /// Supported locale of a language tag, e.g. `it-IT`.
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.split(['-', '_']).next()?;
+        Locale::ALL
+            .into_iter()
+            .find(|locale| locale.as_str().eq_ignore_ascii_case(language))
+    }
+
+    /// First supported locale of an `Accept-Language` header, by quality.
+    pub fn negotiate(accept_language: &str) -> Option<Locale> {
+        let mut languages: Vec<(&str, f32)> = accept_language
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';').map(str::trim);
+                let tag = parts.next().filter(|tag| !tag.is_empty())?;
+                let quality = parts
+                    .find_map(|parameter| parameter.strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse().ok())?;
+                Some((tag, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+        // Stable, so that the order is kept between equal qualities.
+        languages.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        languages
+            .into_iter()
+            .find_map(|(tag, _)| Locale::from_tag(tag))
+    }
+}
+
+/// Locale of the requests that ask for no supported one.
+#[derive(Debug, Clone, Copy)]
+pub struct DefaultLocale(pub Locale);
+
+struct Catalog {
+    english: FluentBundle<FluentResource>,
+    italian: FluentBundle<FluentResource>,
+}
+
+static CATALOG: LazyLock<Catalog> = LazyLock::new(|| Catalog {
+    english: bundle(Locale::English, include_str!("../locales/en.ftl")),
+    italian: bundle(Locale::Italian, include_str!("../locales/it.ftl")),
+});
+
+fn bundle(locale: Locale, source: &str) -> FluentBundle<FluentResource> {
+    let resource = FluentResource::try_new(source.to_owned())
+        .unwrap_or_else(|_| panic!("The {} catalog is not valid Fluent.", locale.as_str()));
+    let mut bundle = FluentBundle::new_concurrent(vec![locale.as_str().parse().unwrap()]);
+    // Isolation marks would end up in the plain text of flash messages.
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(resource)
+        .unwrap_or_else(|_| panic!("The {} catalog has duplicate ids.", locale.as_str()));
+    bundle
+}
+
+/// A text to translate, with its arguments.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    id: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    args: BTreeMap<String, Argument>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Argument {
+    Text(String),
+    /// Translated as well, e.g. a status.
+    Message(Message),
+}
+
+impl Message {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_owned(),
+            args: BTreeMap::new(),
+        }
+    }
+
+    /// Adds an argument shown as it is, e.g. a username.
+    pub fn arg(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.args
+            .insert(name.to_owned(), Argument::Text(value.into()));
+        self
+    }
+
+    /// Adds an argument translated in the same language.
+    pub fn localized_arg(mut self, name: &str, value: &impl Localized) -> Self {
+        self.args
+            .insert(name.to_owned(), Argument::Message(value.message()));
+        self
+    }
+}
+
+/// Content of a flash message, to be translated by [`I18n::flash`].
+impl From<Message> for String {
+    fn from(message: Message) -> Self {
+        serde_json::to_string(&message).expect("Messages are always serializable.")
+    }
+}
+
+/// Values with a translated description.
+pub trait Localized {
+    fn message(&self) -> Message;
+}
+
+impl<T: Localized> Localized for &T {
+    fn message(&self) -> Message {
+        (*self).message()
+    }
+}
+
+impl Localized for Message {
+    fn message(&self) -> Message {
+        self.clone()
+    }
+}
+
+impl Localized for Level {
+    fn message(&self) -> Message {
+        Message::new(&format!("level-{}", self))
+    }
+}
+
+impl Localized for CallRequestStatus {
+    fn message(&self) -> Message {
+        Message::new(&format!("status-{}", self.as_str()))
+    }
+}
+
+impl Localized for UserRole {
+    fn message(&self) -> Message {
+        Message::new(&format!("role-{}", self.as_str()))
+    }
+}
+
+impl Localized for PhoneNumberError {
+    fn message(&self) -> Message {
+        Message::new(match self {
+            PhoneNumberError::Empty => "phone-number-empty",
+            PhoneNumberError::ForbiddenCharacters => "phone-number-forbidden-characters",
+            PhoneNumberError::Unparsable => "phone-number-unparsable",
+            PhoneNumberError::Invalid => "phone-number-invalid",
+        })
+    }
+}
+
+impl Localized for ContactNameError {
+    fn message(&self) -> Message {
+        Message::new(match self {
+            ContactNameError::Empty => "contact-name-empty",
+            ContactNameError::TooShort => "contact-name-too-short",
+            ContactNameError::TooLong => "contact-name-too-long",
+            ContactNameError::ForbiddenCharacters => "contact-name-forbidden-characters",
+        })
+    }
+}
+
+impl Localized for CallbackSlotError {
+    fn message(&self) -> Message {
+        Message::new(match self {
+            CallbackSlotError::Unknown => "callback-slot-unknown",
+            CallbackSlotError::OutsideOfficeHours => "callback-slot-outside-office-hours",
+        })
+    }
+}
+
+impl Localized for TopicError {
+    fn message(&self) -> Message {
+        Message::new(match self {
+            TopicError::Unknown => "topic-unknown",
+        })
+    }
+}
+
+impl Localized for Weekday {
+    fn message(&self) -> Message {
+        Message::new(&format!("weekday-{}", self.to_string().to_lowercase()))
+    }
+}
+
+impl Localized for DayPeriod {
+    fn message(&self) -> Message {
+        Message::new(&format!("period-{}", self.as_str()))
+    }
+}
+
+impl Localized for CallbackSlot {
+    fn message(&self) -> Message {
+        Message::new(&format!("callback-slot-{}", self.period.as_str()))
+            .localized_arg("weekday", &self.weekday)
+    }
+}
+
+/// Translator in the language of the current request.
+#[derive(Debug, Clone, Copy)]
+pub struct I18n {
+    locale: Locale,
+}
+
+impl I18n {
+    pub fn new(locale: Locale) -> Self {
+        Self { locale }
+    }
+
+    pub fn locale(&self) -> Locale {
+        self.locale
+    }
+
+    /// Text of the message with `id`, which takes no arguments.
+    pub fn tr(&self, id: &str) -> String {
+        self.localize(Message::new(id))
+    }
+
+    pub fn localize(&self, item: impl Localized) -> String {
+        let message = item.message();
+        let bundle = match self.locale {
+            Locale::English => &CATALOG.english,
+            Locale::Italian => &CATALOG.italian,
+        };
+        let Some(pattern) = bundle.get_message(&message.id).and_then(|m| m.value()) else {
+            tracing::error!(
+                id = message.id,
+                locale = self.locale.as_str(),
+                "Missing translation"
+            );
+            return message.id;
+        };
+        let mut args = FluentArgs::new();
+        for (name, value) in &message.args {
+            let value = match value {
+                Argument::Text(text) => text.clone(),
+                Argument::Message(message) => self.localize(message),
+            };
+            args.set(name.clone(), value);
+        }
+        let mut errors = vec![];
+        let text = bundle.format_pattern(pattern, Some(&args), &mut errors);
+        if !errors.is_empty() {
+            tracing::error!(id = message.id, ?errors, "Invalid translation arguments");
+        }
+        text.into_owned()
+    }
+
+    /// Text of a flash message, sent as a [`Message`].
+    pub fn flash(&self, message: &FlashMessage) -> String {
+        match serde_json::from_str::<Message>(message.content()) {
+            Ok(message) => self.localize(message),
+            // Sent by an older version, shown untranslated.
+            Err(_) => message.content().to_owned(),
+        }
+    }
+}
+
+impl FromRequest for I18n {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let picked = req
+            .cookie(LANGUAGE_COOKIE)
+            .and_then(|cookie| Locale::from_tag(cookie.value()));
+        let accepted = || {
+            req.headers()
+                .get(ACCEPT_LANGUAGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(Locale::negotiate)
+        };
+        let default = || {
+            req.app_data::<web::Data<DefaultLocale>>()
+                .map_or(Locale::Italian, |default| default.0)
+        };
+        ready(Ok(I18n::new(
+            picked.or_else(accepted).unwrap_or_else(default),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{I18n, Locale, Message};
+    use crate::domain::{call_request::CallRequestStatus, user::UserRole};
+
+    /// Message ids, at the start of their definition line.
+    fn ids(source: &str) -> BTreeSet<&str> {
+        source
+            .lines()
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_lowercase()))
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    #[test]
+    fn both_catalogs_translate_the_same_messages() {
+        let english = ids(include_str!("../locales/en.ftl"));
+        let italian = ids(include_str!("../locales/it.ftl"));
+
+        assert_eq!(
+            english.difference(&italian).collect::<Vec<_>>(),
+            Vec::<&&str>::new(),
+            "missing in Italian"
+        );
+        assert_eq!(
+            italian.difference(&english).collect::<Vec<_>>(),
+            Vec::<&&str>::new(),
+            "missing in English"
+        );
+    }
+
+    #[test]
+    fn accept_language_is_negotiated_by_quality() {
+        for (header, expected) in [
+            ("it-IT,it;q=0.9,en;q=0.8", Some(Locale::Italian)),
+            ("en-US,en;q=0.9", Some(Locale::English)),
+            ("de-DE, en;q=0.5, it;q=0.7", Some(Locale::Italian)),
+            ("fr;q=0.9, EN-gb;q=0.3", Some(Locale::English)),
+            ("it;q=0, en;q=0.1", Some(Locale::English)),
+            ("de, fr", None),
+            ("", None),
+            ("*", None),
+        ] {
+            assert_eq!(Locale::negotiate(header), expected, "{:?}", header);
+        }
+    }
+
+    #[test]
+    fn arguments_are_translated_in_the_same_language() {
+        let message = Message::new("call-request-invalid-transition")
+            .localized_arg("from", &CallRequestStatus::Pending)
+            .localized_arg("to", &CallRequestStatus::Completed);
+
+        assert_eq!(
+            I18n::new(Locale::English).localize(&message),
+            "A call request cannot go from Pending to Completed."
+        );
+        assert_eq!(
+            I18n::new(Locale::Italian).localize(&message),
+            "Una richiesta di chiamata non può passare da In attesa a Completata."
+        );
+    }
+
+    #[test]
+    fn flash_messages_survive_the_round_trip() {
+        let message = Message::new("user-role-changed")
+            .arg("username", "rino")
+            .localized_arg("role", &UserRole::Supervisor);
+        let flash = actix_web_flash_messages::FlashMessage::info(message);
+
+        assert_eq!(
+            I18n::new(Locale::Italian).flash(&flash),
+            "rino ora è Supervisore."
+        );
+        let plain = actix_web_flash_messages::FlashMessage::info("Plain text.");
+        assert_eq!(I18n::new(Locale::English).flash(&plain), "Plain text.");
+    }
+
+    #[test]
+    fn missing_translations_show_the_id() {
+        assert_eq!(
+            I18n::new(Locale::English).tr("no-such-message"),
+            "no-such-message"
+        );
+    }
+}
@@ -1,7 +1,18 @@
 #![doc = include_str!("../README.md")]
 
+pub mod assets;
+pub mod authentication;
+pub mod cli;
 pub mod configuration;
+pub mod csrf;
 pub mod domain;
+pub mod i18n;
+pub mod metrics;
+pub mod migration;
+pub mod rate_limit;
 pub mod routes;
+pub mod security_headers;
+pub mod session_state;
+pub mod session_store;
 pub mod startup;
 pub mod telemetry;
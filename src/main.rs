@@ -2,22 +2,42 @@
 
 use anyhow::Context;
 use bubble_services::{
+    cli::Cli,
     configuration::get_configuration,
-    startup::Application,
-    telemetry::{get_subscriber, init_subscriber},
+    telemetry::{get_subscriber, init_subscriber, init_tracer_provider},
 };
+use clap::Parser;
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
-    let subscriber = get_subscriber("bubble_services".into(), "info".into(), std::io::stdout);
-    init_subscriber(subscriber);
+    let cli = Cli::parse();
+    let tracer_provider = if cli.serves() {
+        let configuration = get_configuration().context("Could not get configuration")?;
+        let tracer_provider = init_tracer_provider("bubble_services", &configuration.telemetry)?;
+        let subscriber = get_subscriber(
+            "bubble_services".into(),
+            "info".into(),
+            std::io::stdout,
+            tracer_provider.as_ref(),
+        );
+        init_subscriber(subscriber);
+        tracer_provider
+    } else {
+        let subscriber = get_subscriber(
+            "bubble_services".into(),
+            "warn".into(),
+            std::io::stderr,
+            None,
+        );
+        init_subscriber(subscriber);
+        None
+    };
 
-    let config = get_configuration().context("Could not get configuration")?;
-    let app = Application::build(config)
-        .await
-        .context("Could not build application")?;
-
-    app.run_until_stopped()
-        .await
-        .context("Could not run application")
+    let result = cli.run().await;
+    if let Some(tracer_provider) = tracer_provider {
+        tracer_provider
+            .shutdown()
+            .context("Could not flush the pending spans")?;
+    }
+    result
 }
@@ -0,0 +1,199 @@
+//! # Metrics
+//! Prometheus metrics exposed at `/metrics` by a separate admin server
+//! listening on `metrics_port`, so that they are not public.
+//!
+//! Every [`Application`](crate::startup::Application) has its own registry.
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, HttpResponse,
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use sqlx::{types::Uuid, PgPool};
+use tokio::time::Instant;
+
+use crate::domain::call_request::CallRequestValidationError;
+
+/// Where a call request comes from.
+#[derive(Debug, Clone, Copy)]
+pub enum Channel {
+    Form,
+    Api,
+}
+
+impl Channel {
+    fn as_str(self) -> &'static str {
+        match self {
+            Channel::Form => "form",
+            Channel::Api => "api",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_pool_connections: IntGaugeVec,
+    db_pool_max_connections: IntGauge,
+    call_requests_created: IntCounterVec,
+    validation_failures: IntCounterVec,
+    call_requests_completed: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new();
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests served."),
+            &["method", "route", "status"],
+        )?;
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time spent serving HTTP requests.",
+            ),
+            &["method", "route"],
+        )?;
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Connections in the database pool."),
+            &["state"],
+        )?;
+        let db_pool_max_connections = IntGauge::new(
+            "db_pool_max_connections",
+            "Maximum number of connections in the database pool.",
+        )?;
+        let call_requests_created = IntCounterVec::new(
+            Opts::new("call_requests_created_total", "Call requests registered."),
+            &["channel"],
+        )?;
+        let validation_failures = IntCounterVec::new(
+            Opts::new(
+                "call_request_validation_failures_total",
+                "Call request submissions rejected, by invalid field.",
+            ),
+            &["channel", "field"],
+        )?;
+        let call_requests_completed = IntCounterVec::new(
+            Opts::new(
+                "call_requests_completed_total",
+                "Call requests completed, by office worker.",
+            ),
+            &["worker"],
+        )?;
+
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_request_duration.clone()))?;
+        registry.register(Box::new(db_pool_connections.clone()))?;
+        registry.register(Box::new(db_pool_max_connections.clone()))?;
+        registry.register(Box::new(call_requests_created.clone()))?;
+        registry.register(Box::new(validation_failures.clone()))?;
+        registry.register(Box::new(call_requests_completed.clone()))?;
+
+        Ok(Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_pool_connections,
+            db_pool_max_connections,
+            call_requests_created,
+            validation_failures,
+            call_requests_completed,
+        })
+    }
+
+    pub fn call_request_created(&self, channel: Channel) {
+        self.call_requests_created
+            .with_label_values(&[channel.as_str()])
+            .inc();
+    }
+
+    pub fn call_request_rejected(&self, channel: Channel, errors: &CallRequestValidationError) {
+        let fields = [
+            ("phone_number", errors.phone_number.is_some()),
+            ("contact_name", errors.contact_name.is_some()),
+            ("callback_slots", errors.callback_slots.is_some()),
+            ("topic", errors.topic.is_some()),
+        ];
+        for (field, _) in fields.iter().filter(|(_, invalid)| *invalid) {
+            self.validation_failures
+                .with_label_values(&[channel.as_str(), field])
+                .inc();
+        }
+    }
+
+    pub fn call_request_completed(&self, worker: Uuid) {
+        self.call_requests_completed
+            .with_label_values(&[&worker.to_string()])
+            .inc();
+    }
+
+    /// Renders the metrics in the Prometheus text format.
+    fn render(&self, pool: &PgPool) -> Result<String, prometheus::Error> {
+        let size = i64::from(pool.size());
+        let idle = pool.num_idle() as i64;
+        self.db_pool_connections
+            .with_label_values(&["idle"])
+            .set(idle);
+        self.db_pool_connections
+            .with_label_values(&["in_use"])
+            .set(size - idle);
+        self.db_pool_max_connections
+            .set(i64::from(pool.options().get_max_connections()));
+
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+/// Counts and times every request, labelled by its route pattern so that
+/// path parameters do not multiply the series. Requests failing with an error
+/// are counted with the status of the error.
+pub async fn record_http_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let metrics = req.app_data::<web::Data<Metrics>>().cloned();
+    let method = req.method().to_string();
+    // Known before routing, the pattern is found from the path alone.
+    let route = req.match_pattern().unwrap_or_else(|| "unmatched".into());
+    let start = Instant::now();
+
+    let result = next.call(req).await;
+
+    if let Some(metrics) = metrics {
+        let status = match &result {
+            Ok(response) => response.status(),
+            Err(e) => e.as_response_error().status_code(),
+        };
+        metrics
+            .http_requests
+            .with_label_values(&[&method, &route, status.as_str()])
+            .inc();
+        metrics
+            .http_request_duration
+            .with_label_values(&[&method, &route])
+            .observe(start.elapsed().as_secs_f64());
+    }
+    result
+}
+
+#[tracing::instrument(name = "Metrics", skip(metrics, pool))]
+pub async fn metrics(
+    metrics: web::Data<Metrics>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let body = metrics
+        .render(&pool)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type(prometheus::TEXT_FORMAT)
+        .body(body))
+}
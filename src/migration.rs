@@ -0,0 +1,153 @@
+//! # Database migrations
+//! The migrations in `migrations/` are embedded in the binary. They can be
+//! applied on startup (see `migrate_on_startup`) or through the `migrate`
+//! subcommand. Either way replicas coordinate through a transaction-level
+//! Postgres advisory lock, so that only one of them migrates at a time and the
+//! lock goes away with the transaction, whatever happens to the migrating
+//! process.
+//!
+//! Some changes cannot be expressed in SQL: they run as data migrations
+//! after the schema ones, and must be safe to run again.
+
+use sqlx::{migrate::Migrator, PgConnection, PgPool};
+
+use crate::domain::call_request::CallRequestPhoneNumber;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Key of the advisory lock held while checking and applying migrations.
+const MIGRATION_LOCK_KEY: i64 = 0x6275_6262_6c65; // "bubble"
+
+#[derive(thiserror::Error, Debug)]
+pub enum MigrationError {
+    #[error(
+        "The database schema is newer than this binary: migration {0} was applied but is unknown."
+    )]
+    SchemaTooNew(i64),
+    #[error(transparent)]
+    MigrateError(#[from] sqlx::migrate::MigrateError),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+impl MigrationError {
+    /// Whether the database could not be reached at all, as opposed to
+    /// refusing the checks.
+    pub fn is_unreachable(&self) -> bool {
+        matches!(
+            self,
+            Self::DatabaseError(
+                sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::PoolTimedOut
+            )
+        )
+    }
+}
+
+/// Makes sure the binary can work with the database schema, applying the
+/// pending migrations when `migrate` is set.
+///
+/// Fails when the database has migrations unknown to the binary, e.g. after
+/// a rollback to a previous release.
+#[tracing::instrument(name = "Prepare database", skip(pool))]
+pub async fn prepare_database(pool: &PgPool, migrate: bool) -> Result<(), MigrationError> {
+    if !migrate {
+        // Only reading, the lock is not needed.
+        let mut connection = pool.acquire().await?;
+        return check_and_migrate(&mut connection, false).await;
+    }
+    let mut transaction = pool.begin().await?;
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *transaction)
+        .await?;
+    match check_and_migrate(&mut transaction, migrate).await {
+        Ok(()) => {
+            transaction.commit().await?;
+            Ok(())
+        }
+        Err(e) => {
+            // Releases the lock right away rather than when the connection is
+            // next used.
+            if let Err(rollback_error) = transaction.rollback().await {
+                tracing::warn!(error = %rollback_error, "Failed to roll back the migration.");
+            }
+            Err(e)
+        }
+    }
+}
+
+async fn check_and_migrate(
+    connection: &mut PgConnection,
+    migrate: bool,
+) -> Result<(), MigrationError> {
+    let applied = applied_versions(connection).await?;
+    if let Some(unknown) = applied
+        .iter()
+        .find(|version| !MIGRATOR.iter().any(|m| m.version == **version))
+    {
+        return Err(MigrationError::SchemaTooNew(*unknown));
+    }
+
+    let pending = MIGRATOR
+        .iter()
+        .filter(|m| !applied.contains(&m.version))
+        .count();
+    if !migrate {
+        if pending > 0 {
+            tracing::warn!(pending, "The database has pending migrations.");
+        }
+        return Ok(());
+    }
+    if pending > 0 {
+        tracing::info!(pending, "Applying database migrations.");
+        MIGRATOR.run_direct(connection).await?;
+    }
+    normalize_phone_numbers(connection).await?;
+    Ok(())
+}
+
+/// Rewrites in E.164 format the phone numbers stored before they were
+/// normalized on submission. Numbers that cannot be understood are left as
+/// they are, and only counted.
+async fn normalize_phone_numbers(connection: &mut PgConnection) -> Result<(), sqlx::Error> {
+    let rows = sqlx::query!(
+        r"SELECT id, phone_number FROM call_requests WHERE phone_number !~ '^\+[0-9]+$'"
+    )
+    .fetch_all(&mut *connection)
+    .await?;
+    let mut unparsable = 0;
+    for row in rows {
+        let Ok(normalized) = CallRequestPhoneNumber::parse(row.phone_number) else {
+            unparsable += 1;
+            continue;
+        };
+        sqlx::query!(
+            "UPDATE call_requests SET phone_number = $1 WHERE id = $2",
+            normalized.as_ref(),
+            row.id,
+        )
+        .execute(&mut *connection)
+        .await?;
+    }
+    if unparsable > 0 {
+        tracing::warn!(
+            unparsable,
+            "Some stored phone numbers could not be normalized."
+        );
+    }
+    Ok(())
+}
+
+/// Versions of the migrations applied to the database, empty when it has
+/// never been migrated.
+async fn applied_versions(connection: &mut PgConnection) -> Result<Vec<i64>, sqlx::Error> {
+    let migrated: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(&mut *connection)
+        .await?;
+    if !migrated {
+        return Ok(vec![]);
+    }
+    sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success ORDER BY version")
+        .fetch_all(&mut *connection)
+        .await
+}
@@ -0,0 +1,249 @@
+//! # Rate limiting
+//! Call requests can be submitted anonymously, so each client IP and each
+//! phone number may only submit a limited number of them within a fixed
+//! window. Status lookups are limited per client IP as well, so that
+//! reference codes cannot be guessed. Counters live in the same backend as
+//! the sessions: with Redis they are shared between replicas.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::HttpRequest;
+use redis::aio::ConnectionManager;
+use secrecy::{ExposeSecret, Secret};
+
+use crate::{
+    configuration::{RateLimitConfiguration, SessionStoreKind},
+    domain::call_request::CallRequestPhoneNumber,
+};
+
+/// What a submission is limited by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    ClientIp,
+    PhoneNumber,
+    /// Status lookups by client IP.
+    StatusLookup,
+}
+
+impl Limit {
+    fn as_str(self) -> &'static str {
+        match self {
+            Limit::ClientIp => "client_ip",
+            Limit::PhoneNumber => "phone_number",
+            Limit::StatusLookup => "status_lookup",
+        }
+    }
+}
+
+/// A submission over one of the limits.
+#[derive(thiserror::Error, Debug)]
+#[error("Too many call requests by {}, retry in {retry_after:?}.", limit.as_str())]
+pub struct RateLimitExceeded {
+    pub limit: Limit,
+    /// Time left before the window resets.
+    pub retry_after: Duration,
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    counters: Counters,
+    window: Duration,
+    per_client_ip: u32,
+    per_phone_number: u32,
+    trust_forwarded_for: bool,
+}
+
+#[derive(Clone)]
+enum Counters {
+    Redis(ConnectionManager),
+    /// Count and start of the window, by key.
+    Memory(Arc<Mutex<HashMap<String, (u32, Instant)>>>),
+}
+
+impl RateLimiter {
+    pub async fn build(
+        configuration: &RateLimitConfiguration,
+        kind: SessionStoreKind,
+        redis_uri: &Secret<String>,
+    ) -> Result<Self, anyhow::Error> {
+        let counters = match kind {
+            SessionStoreKind::Redis => {
+                let client = redis::Client::open(redis_uri.expose_secret().as_str())?;
+                Counters::Redis(ConnectionManager::new(client).await?)
+            }
+            SessionStoreKind::Memory => Counters::Memory(Default::default()),
+        };
+        Ok(Self {
+            counters,
+            window: Duration::from_secs(configuration.window_seconds),
+            per_client_ip: configuration.per_client_ip,
+            per_phone_number: configuration.per_phone_number,
+            trust_forwarded_for: configuration.trust_forwarded_for,
+        })
+    }
+
+    /// Counts a submission from the client sending `req`.
+    pub async fn check_client(&self, req: &HttpRequest) -> Result<(), RateLimitExceeded> {
+        // Without an address (e.g. in unit tests) only the phone number is limited.
+        let Some(client_ip) = self.client_ip(req) else {
+            return Ok(());
+        };
+        self.hit(Limit::ClientIp, &client_ip, self.per_client_ip)
+            .await
+    }
+
+    /// Counts a status lookup from the client sending `req`, allowed as many
+    /// times as submissions.
+    pub async fn check_status_lookup(&self, req: &HttpRequest) -> Result<(), RateLimitExceeded> {
+        let Some(client_ip) = self.client_ip(req) else {
+            return Ok(());
+        };
+        self.hit(Limit::StatusLookup, &client_ip, self.per_client_ip)
+            .await
+    }
+
+    fn client_ip(&self, req: &HttpRequest) -> Option<String> {
+        if self.trust_forwarded_for {
+            req.connection_info()
+                .realip_remote_addr()
+                .map(|address| address.to_owned())
+        } else {
+            req.peer_addr().map(|address| address.ip().to_string())
+        }
+    }
+
+    /// Counts a submission for `phone_number`.
+    pub async fn check_phone_number(
+        &self,
+        phone_number: &CallRequestPhoneNumber,
+    ) -> Result<(), RateLimitExceeded> {
+        self.hit(
+            Limit::PhoneNumber,
+            phone_number.as_ref(),
+            self.per_phone_number,
+        )
+        .await
+    }
+
+    #[tracing::instrument(name = "Rate limiting", skip(self, value))]
+    async fn hit(&self, limit: Limit, value: &str, max: u32) -> Result<(), RateLimitExceeded> {
+        let key = format!("rate_limit:{}:{}", limit.as_str(), value);
+        let (count, retry_after) = match self.increment(key).await {
+            Ok(counter) => counter,
+            Err(e) => {
+                // Better to let citizens through than to lock everyone out.
+                tracing::warn!(error.cause_chain = ?e, "Rate limiting is unavailable");
+                return Ok(());
+            }
+        };
+        if count > max {
+            match limit {
+                Limit::ClientIp => tracing::warn!(
+                    client_ip = value,
+                    count,
+                    "Call request submission blocked by rate limiting"
+                ),
+                Limit::StatusLookup => tracing::warn!(
+                    client_ip = value,
+                    count,
+                    "Call request status lookup blocked by rate limiting"
+                ),
+                Limit::PhoneNumber => {
+                    tracing::warn!(count, "Call request submission blocked by rate limiting")
+                }
+            }
+            return Err(RateLimitExceeded { limit, retry_after });
+        }
+        Ok(())
+    }
+
+    /// Increments the counter of `key`, returning its value and the time left
+    /// in its window.
+    async fn increment(&self, key: String) -> Result<(u32, Duration), redis::RedisError> {
+        match &self.counters {
+            Counters::Redis(connection) => {
+                let window = self.window.as_millis().max(1) as u64;
+                let (count, ttl): (u32, i64) = redis::pipe()
+                    .atomic()
+                    .cmd("SET")
+                    .arg(&key)
+                    .arg(0)
+                    .arg("PX")
+                    .arg(window)
+                    .arg("NX")
+                    .ignore()
+                    .incr(&key, 1)
+                    .pttl(&key)
+                    .query_async(&mut connection.clone())
+                    .await?;
+                Ok((count, Duration::from_millis(ttl.max(0) as u64)))
+            }
+            Counters::Memory(counters) => {
+                let now = Instant::now();
+                let mut counters = counters.lock().unwrap();
+                counters.retain(|_, (_, started)| now.duration_since(*started) < self.window);
+                let (count, started) = counters.entry(key).or_insert((0, now));
+                *count += 1;
+                Ok((*count, self.window - now.duration_since(*started)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use secrecy::Secret;
+
+    use super::{Limit, RateLimiter};
+    use crate::configuration::{RateLimitConfiguration, SessionStoreKind};
+
+    async fn limiter(window: Duration) -> RateLimiter {
+        let mut limiter = RateLimiter::build(
+            &RateLimitConfiguration {
+                window_seconds: 0,
+                per_client_ip: 2,
+                per_phone_number: 1,
+                trust_forwarded_for: false,
+            },
+            SessionStoreKind::Memory,
+            &Secret::new(String::new()),
+        )
+        .await
+        .unwrap();
+        limiter.window = window;
+        limiter
+    }
+
+    #[tokio::test]
+    async fn submissions_over_the_limit_are_blocked() {
+        let limiter = limiter(Duration::from_secs(60)).await;
+
+        assert!(limiter.hit(Limit::ClientIp, "10.0.0.1", 2).await.is_ok());
+        assert!(limiter.hit(Limit::ClientIp, "10.0.0.1", 2).await.is_ok());
+        let exceeded = limiter
+            .hit(Limit::ClientIp, "10.0.0.1", 2)
+            .await
+            .unwrap_err();
+        assert_eq!(exceeded.limit, Limit::ClientIp);
+        assert!(exceeded.retry_after <= Duration::from_secs(60));
+
+        // Other clients have their own counter.
+        assert!(limiter.hit(Limit::ClientIp, "10.0.0.2", 2).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn counters_reset_with_the_window() {
+        let limiter = limiter(Duration::from_millis(50)).await;
+
+        assert!(limiter.hit(Limit::PhoneNumber, "+39", 1).await.is_ok());
+        assert!(limiter.hit(Limit::PhoneNumber, "+39", 1).await.is_err());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(limiter.hit(Limit::PhoneNumber, "+39", 1).await.is_ok());
+    }
+}
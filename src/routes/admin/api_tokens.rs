@@ -0,0 +1,50 @@
+//! # API tokens
+//! Office workers generate bearer tokens to use the staff JSON API.
+
+use actix_web::{web, HttpResponse};
+use askama_actix::Template;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+use crate::{
+    authentication::{issue_api_token, UserId},
+    csrf::CsrfToken,
+    i18n::I18n,
+    security_headers::CspNonce,
+};
+
+#[derive(Template)]
+#[template(path = "admin/api_token.html")]
+struct ApiTokenTemplate {
+    token: String,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+}
+
+#[tracing::instrument(name = "Create API token", skip(csrf_token, csp_nonce, i18n, pool))]
+pub async fn create(
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = issue_api_token(&pool, *user_id.into_inner())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let page = ApiTokenTemplate {
+        token: token.expose_secret().to_owned(),
+        csrf_token,
+        csp_nonce,
+        i18n,
+    }
+    .render()
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    // The token is shown only once, it must not end up in any cache.
+    Ok(HttpResponse::Ok()
+        .content_type(ApiTokenTemplate::MIME_TYPE)
+        .insert_header(("Cache-Control", "no-store"))
+        .body(page))
+}
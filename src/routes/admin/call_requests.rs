@@ -0,0 +1,376 @@
+//! # Call request dashboard
+//! Office workers see open call requests, oldest first or the ones callable
+//! now first, possibly only the ones about a topic, and move them along their
+//! lifecycle (see [`CallRequestStatus`]) until they are completed or
+//! cancelled.
+
+use std::collections::HashMap;
+
+use actix_web::{
+    http::{header::LOCATION, StatusCode},
+    web, HttpResponse, Responder, ResponseError,
+};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use askama_actix::Template;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::{types::Uuid, PgPool, Postgres, Transaction};
+
+use crate::{
+    authentication::UserId,
+    csrf::CsrfToken,
+    domain::{
+        call_request::{
+            CallRequest, CallRequestStatus, CallRequestTransition, InvalidStatusTransition,
+        },
+        callback_slot::{CallbackSlot, DayPeriod, OfficeHours},
+        topic::Topic,
+        user::UserRole,
+    },
+    i18n::{I18n, Message},
+    metrics::Metrics,
+    routes::{error_chain_fmt, topics::get_topics},
+    security_headers::CspNonce,
+};
+
+/// A call request still to be processed, as shown in the dashboard.
+pub struct OpenCallRequest {
+    pub id: Uuid,
+    pub user_name: String,
+    pub phone_number: String,
+    pub status: CallRequestStatus,
+    pub created_at: DateTime<Utc>,
+    pub last_requested_at: DateTime<Utc>,
+    /// Preferred callback slots, none meaning any time during office hours.
+    pub callback_slots: Vec<CallbackSlot>,
+    /// Whether the citizen can be called at the time of the request.
+    pub callable_now: bool,
+    pub topic: Option<String>,
+    /// Team the topic is routed to.
+    pub team: Option<String>,
+}
+
+/// Order of the dashboard.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DashboardSort {
+    #[default]
+    Oldest,
+    /// The ones that can be called now, then the others, oldest first.
+    CallableNow,
+}
+
+impl DashboardSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DashboardSort::Oldest => "oldest",
+            DashboardSort::CallableNow => "callable_now",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub struct DashboardQuery {
+    #[serde(default)]
+    sort: DashboardSort,
+    /// Only show the call requests about this topic.
+    topic: Option<Uuid>,
+}
+
+impl DashboardQuery {
+    /// Link to the dashboard with the given filters.
+    fn link(&self) -> String {
+        let mut link = format!("/admin/call_requests?sort={}", self.sort.as_str());
+        if let Some(topic) = self.topic {
+            link.push_str(&format!("&topic={}", topic));
+        }
+        link
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin/call_requests.html")]
+struct DashboardTemplate {
+    messages: Vec<FlashMessage>,
+    call_requests: Vec<OpenCallRequest>,
+    query: DashboardQuery,
+    /// Retired ones included, they may still have open call requests.
+    topics: Vec<Topic>,
+    can_manage_users: bool,
+    can_manage_topics: bool,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+}
+
+impl DashboardTemplate {
+    fn sort_link(&self, sort: DashboardSort) -> String {
+        DashboardQuery { sort, ..self.query }.link()
+    }
+
+    fn is_filtered_by(&self, topic: &Topic) -> bool {
+        self.query.topic == Some(topic.id)
+    }
+
+    fn topic_link(&self, topic: Option<&Topic>) -> String {
+        DashboardQuery {
+            topic: topic.map(|topic| topic.id),
+            ..self.query
+        }
+        .link()
+    }
+}
+
+#[tracing::instrument(
+    name = "Call request dashboard",
+    skip(messages, page_tokens, pool, office_hours)
+)]
+pub async fn dashboard(
+    messages: IncomingFlashMessages,
+    query: web::Query<DashboardQuery>,
+    page_tokens: (CsrfToken, CspNonce, I18n),
+    role: web::ReqData<UserRole>,
+    pool: web::Data<PgPool>,
+    office_hours: web::Data<OfficeHours>,
+) -> Result<impl Responder, DashboardError> {
+    let (csrf_token, csp_nonce, i18n) = page_tokens;
+    let query = query.into_inner();
+    let mut call_requests =
+        get_open_call_requests(&pool, &office_hours, query.topic, Utc::now()).await?;
+    if query.sort == DashboardSort::CallableNow {
+        // Stable, the others stay oldest first.
+        call_requests.sort_by_key(|call_request| !call_request.callable_now);
+    }
+    Ok(DashboardTemplate {
+        messages: messages.iter().cloned().collect(),
+        call_requests,
+        query,
+        topics: get_topics(&pool).await?,
+        can_manage_users: role.grants(UserRole::Administrator),
+        can_manage_topics: role.grants(UserRole::Supervisor),
+        csrf_token,
+        csp_nonce,
+        i18n,
+    })
+}
+
+#[tracing::instrument(name = "Get open call requests", skip(pool, office_hours))]
+async fn get_open_call_requests(
+    pool: &PgPool,
+    office_hours: &OfficeHours,
+    topic: Option<Uuid>,
+    now: DateTime<Utc>,
+) -> Result<Vec<OpenCallRequest>, sqlx::Error> {
+    let mut callback_slots = get_open_callback_slots(pool).await?;
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            c.id,
+            c.user_name,
+            c.phone_number,
+            c.status AS "status: CallRequestStatus",
+            c.created_at,
+            c.last_requested_at,
+            t.name AS "topic?",
+            t.team
+        FROM call_requests c
+        LEFT JOIN topics t ON t.id = c.topic_id
+        WHERE c.status NOT IN ('completed', 'cancelled')
+            AND ($1::uuid IS NULL OR c.topic_id = $1)
+        ORDER BY c.created_at ASC
+        "#,
+        topic,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let callback_slots = callback_slots.remove(&row.id).unwrap_or_default();
+            OpenCallRequest {
+                id: row.id,
+                user_name: row.user_name,
+                phone_number: row.phone_number,
+                status: row.status,
+                created_at: row.created_at,
+                last_requested_at: row.last_requested_at,
+                callable_now: office_hours.is_callable(&callback_slots, now),
+                callback_slots,
+                topic: row.topic,
+                team: row.team,
+            }
+        })
+        .collect())
+}
+
+/// Preferred callback slots of the open call requests, Monday morning first.
+#[tracing::instrument(name = "Get open callback slots", skip(pool))]
+async fn get_open_callback_slots(
+    pool: &PgPool,
+) -> Result<HashMap<Uuid, Vec<CallbackSlot>>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT s.call_request_id, s.weekday, s.period AS "period: DayPeriod"
+        FROM call_request_callback_slots s
+        JOIN call_requests c ON c.id = s.call_request_id
+        WHERE c.status NOT IN ('completed', 'cancelled')
+        ORDER BY s.weekday, s.period
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut callback_slots: HashMap<Uuid, Vec<CallbackSlot>> = HashMap::new();
+    for row in rows {
+        // The table only accepts valid weekdays.
+        if let Some(slot) = CallbackSlot::restore(row.weekday, row.period) {
+            callback_slots
+                .entry(row.call_request_id)
+                .or_default()
+                .push(slot);
+        }
+    }
+    Ok(callback_slots)
+}
+
+/// Status change requested from the dashboard.
+#[derive(Deserialize)]
+pub struct TransitionForm {
+    status: CallRequestStatus,
+}
+
+#[tracing::instrument(name = "Transition call request", skip(form, pool, metrics), fields(to = ?form.status))]
+pub async fn transition(
+    call_request_id: web::Path<Uuid>,
+    form: web::Form<TransitionForm>,
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
+    metrics: web::Data<Metrics>,
+) -> Result<HttpResponse, DashboardError> {
+    let mut transaction = pool.begin().await?;
+    let mut call_request = get_call_request_for_update(&mut transaction, *call_request_id)
+        .await?
+        .ok_or(DashboardError::NotFound)?;
+
+    let transition = call_request.transition(form.status, *user_id.into_inner())?;
+    store_transition(&mut transaction, &call_request, &transition).await?;
+    transaction.commit().await?;
+    if transition.to == CallRequestStatus::Completed {
+        metrics.call_request_completed(transition.changed_by);
+    }
+
+    FlashMessage::info(Message::new("call-request-moved").localized_arg("status", &transition.to))
+        .send();
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/call_requests"))
+        .finish())
+}
+
+#[tracing::instrument(name = "Get call request for update", skip(transaction))]
+async fn get_call_request_for_update(
+    transaction: &mut Transaction<'_, Postgres>,
+    call_request_id: Uuid,
+) -> Result<Option<CallRequest>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id, status AS "status: CallRequestStatus", assigned_to
+        FROM call_requests
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        call_request_id,
+    )
+    .fetch_optional(&mut **transaction)
+    .await?;
+    Ok(row.map(|row| CallRequest::restore(row.id, row.status, row.assigned_to)))
+}
+
+#[tracing::instrument(
+    name = "Store call request transition",
+    skip(transaction, call_request)
+)]
+async fn store_transition(
+    transaction: &mut Transaction<'_, Postgres>,
+    call_request: &CallRequest,
+    transition: &CallRequestTransition,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE call_requests
+        SET status = $1, assigned_to = $2
+        WHERE id = $3
+        "#,
+        call_request.status() as CallRequestStatus,
+        call_request.assigned_to(),
+        call_request.id(),
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO call_request_transitions
+            (id, call_request_id, from_status, to_status, changed_by, changed_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        Uuid::new_v4(),
+        transition.call_request_id,
+        transition.from as CallRequestStatus,
+        transition.to as CallRequestStatus,
+        transition.changed_by,
+        transition.changed_at,
+    )
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
+}
+
+#[derive(thiserror::Error)]
+pub enum DashboardError {
+    #[error("The call request does not exist.")]
+    NotFound,
+    #[error(transparent)]
+    InvalidTransition(#[from] InvalidStatusTransition),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for DashboardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for DashboardError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            DashboardError::NotFound | DashboardError::InvalidTransition(_) => {
+                let message = match self {
+                    DashboardError::InvalidTransition(e) => {
+                        Message::new("call-request-invalid-transition")
+                            .localized_arg("from", &e.from)
+                            .localized_arg("to", &e.to)
+                    }
+                    _ => Message::new("call-request-not-found"),
+                };
+                FlashMessage::error(message).send();
+                HttpResponse::SeeOther()
+                    .insert_header((LOCATION, "/admin/call_requests"))
+                    .finish()
+            }
+            DashboardError::DatabaseError(_) => {
+                HttpResponse::build(self.status_code()).body("Database error!")
+            }
+        }
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            DashboardError::NotFound | DashboardError::InvalidTransition(_) => {
+                StatusCode::SEE_OTHER
+            }
+            DashboardError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//! # Staff area
+//! Routes reserved to authenticated office workers.
+
+pub mod api_tokens;
+pub mod call_requests;
+pub mod topics;
+pub mod users;
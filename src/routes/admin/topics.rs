@@ -0,0 +1,187 @@
+//! # Topic catalog
+//! Supervisors manage the topics citizens pick on the call request form, and
+//! the team each one is routed to. Topics are retired instead of deleted, as
+//! call requests keep referring to them.
+
+use actix_web::{
+    http::{header::LOCATION, StatusCode},
+    web, HttpResponse, Responder, ResponseError,
+};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use askama_actix::Template;
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::{types::Uuid, PgPool};
+
+use crate::{
+    csrf::CsrfToken,
+    domain::topic::{Topic, TopicName, TopicNameError},
+    i18n::{I18n, Message},
+    routes::{error_chain_fmt, topics::get_topics},
+    security_headers::CspNonce,
+};
+
+#[derive(Template)]
+#[template(path = "admin/topics.html")]
+struct TopicsTemplate {
+    messages: Vec<FlashMessage>,
+    topics: Vec<Topic>,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+}
+
+#[tracing::instrument(
+    name = "Topic catalog",
+    skip(messages, csrf_token, csp_nonce, i18n, pool)
+)]
+pub async fn list(
+    messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+    pool: web::Data<PgPool>,
+) -> Result<impl Responder, TopicsError> {
+    Ok(TopicsTemplate {
+        messages: messages.iter().cloned().collect(),
+        topics: get_topics(&pool).await?,
+        csrf_token,
+        csp_nonce,
+        i18n,
+    })
+}
+
+/// New topic, or changes to an existing one.
+#[derive(Deserialize, Debug)]
+pub struct TopicForm {
+    name: String,
+    /// Empty when no team handles the topic.
+    #[serde(default)]
+    team: String,
+    /// Checkbox, only sent when checked. New topics are always offered.
+    active: Option<String>,
+}
+
+impl TopicForm {
+    fn parse(&self) -> Result<(TopicName, Option<TopicName>), TopicsError> {
+        Ok((
+            TopicName::parse(&self.name)?,
+            TopicName::parse_optional(&self.team)?,
+        ))
+    }
+}
+
+#[tracing::instrument(name = "Create topic", skip(pool))]
+pub async fn create(
+    form: web::Form<TopicForm>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, TopicsError> {
+    let (name, team) = form.parse()?;
+    sqlx::query!(
+        r#"
+        INSERT INTO topics (id, name, team, created_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        Uuid::new_v4(),
+        name.as_ref(),
+        team.as_ref().map(AsRef::as_ref),
+        Utc::now(),
+    )
+    .execute(pool.get_ref())
+    .await
+    .map_err(TopicsError::from_insertion)?;
+
+    FlashMessage::info(Message::new("topic-created").arg("name", name.as_ref())).send();
+    Ok(see_topics())
+}
+
+#[tracing::instrument(name = "Update topic", skip(pool))]
+pub async fn update(
+    topic_id: web::Path<Uuid>,
+    form: web::Form<TopicForm>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, TopicsError> {
+    let (name, team) = form.parse()?;
+    let updated = sqlx::query!(
+        r#"
+        UPDATE topics
+        SET name = $1, team = $2, active = $3
+        WHERE id = $4
+        "#,
+        name.as_ref(),
+        team.as_ref().map(AsRef::as_ref),
+        form.active.is_some(),
+        topic_id.into_inner(),
+    )
+    .execute(pool.get_ref())
+    .await
+    .map_err(TopicsError::from_insertion)?;
+    if updated.rows_affected() == 0 {
+        return Err(TopicsError::NotFound);
+    }
+
+    FlashMessage::info(Message::new("topic-updated").arg("name", name.as_ref())).send();
+    Ok(see_topics())
+}
+
+fn see_topics() -> HttpResponse {
+    HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/topics"))
+        .finish()
+}
+
+#[derive(thiserror::Error)]
+pub enum TopicsError {
+    #[error(transparent)]
+    InvalidName(#[from] TopicNameError),
+    #[error("A topic with that name already exists.")]
+    NameTaken,
+    #[error("The topic does not exist.")]
+    NotFound,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+impl TopicsError {
+    /// Names are unique regardless of case.
+    fn from_insertion(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => TopicsError::NameTaken,
+            _ => TopicsError::DatabaseError(e),
+        }
+    }
+}
+
+impl std::fmt::Debug for TopicsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for TopicsError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        let message = match self {
+            TopicsError::InvalidName(e) => Message::new(match e {
+                TopicNameError::Empty => "topic-name-empty",
+                TopicNameError::TooLong => "topic-name-too-long",
+                TopicNameError::ForbiddenCharacters => "topic-name-forbidden-characters",
+            }),
+            TopicsError::NameTaken => Message::new("topic-name-taken"),
+            TopicsError::NotFound => Message::new("topic-not-found"),
+            TopicsError::DatabaseError(_) => {
+                return HttpResponse::build(self.status_code()).body("Database error!");
+            }
+        };
+        FlashMessage::error(message).send();
+        see_topics()
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TopicsError::InvalidName(_) | TopicsError::NameTaken | TopicsError::NotFound => {
+                StatusCode::SEE_OTHER
+            }
+            TopicsError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! # User management
+//! Administrators see every staff account and change its role.
+
+use actix_web::{
+    http::{header::LOCATION, StatusCode},
+    web, HttpResponse, Responder, ResponseError,
+};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use askama_actix::Template;
+use serde::Deserialize;
+use sqlx::{types::Uuid, PgPool};
+
+use crate::{
+    authentication::UserId,
+    csrf::CsrfToken,
+    domain::user::UserRole,
+    i18n::{I18n, Message},
+    routes::error_chain_fmt,
+    security_headers::CspNonce,
+};
+
+/// A staff account, as shown in the user management page.
+pub struct StaffUser {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: UserRole,
+}
+
+#[derive(Template)]
+#[template(path = "admin/users.html")]
+struct UsersTemplate {
+    messages: Vec<FlashMessage>,
+    users: Vec<StaffUser>,
+    current_user: Uuid,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+}
+
+#[tracing::instrument(
+    name = "User management",
+    skip(messages, csrf_token, csp_nonce, i18n, pool)
+)]
+pub async fn list(
+    messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
+) -> Result<impl Responder, UsersError> {
+    let users = sqlx::query_as!(
+        StaffUser,
+        r#"
+        SELECT user_id, username, role AS "role: UserRole"
+        FROM users
+        ORDER BY username
+        "#
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+    Ok(UsersTemplate {
+        messages: messages.iter().cloned().collect(),
+        users,
+        current_user: *user_id.into_inner(),
+        csrf_token,
+        csp_nonce,
+        i18n,
+    })
+}
+
+/// Role change requested from the user management page.
+#[derive(Deserialize)]
+pub struct RoleForm {
+    role: UserRole,
+}
+
+#[tracing::instrument(name = "Change user role", skip(form, pool), fields(role = ?form.role))]
+pub async fn update_role(
+    target: web::Path<Uuid>,
+    form: web::Form<RoleForm>,
+    user_id: web::ReqData<UserId>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, UsersError> {
+    let target = target.into_inner();
+    // Administrators cannot lock themselves out.
+    if target == **user_id {
+        return Err(UsersError::OwnRole);
+    }
+    let username = sqlx::query!(
+        r#"
+        UPDATE users
+        SET role = $1
+        WHERE user_id = $2
+        RETURNING username
+        "#,
+        form.role as UserRole,
+        target,
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or(UsersError::NotFound)?
+    .username;
+
+    FlashMessage::info(
+        Message::new("user-role-changed")
+            .arg("username", username)
+            .localized_arg("role", &form.role),
+    )
+    .send();
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/users"))
+        .finish())
+}
+
+#[derive(thiserror::Error)]
+pub enum UsersError {
+    #[error("The user does not exist.")]
+    NotFound,
+    #[error("You cannot change your own role.")]
+    OwnRole,
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for UsersError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for UsersError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            UsersError::NotFound | UsersError::OwnRole => {
+                let message = match self {
+                    UsersError::OwnRole => "user-own-role",
+                    _ => "user-not-found",
+                };
+                FlashMessage::error(Message::new(message)).send();
+                HttpResponse::SeeOther()
+                    .insert_header((LOCATION, "/admin/users"))
+                    .finish()
+            }
+            UsersError::DatabaseError(_) => {
+                HttpResponse::build(self.status_code()).body("Database error!")
+            }
+        }
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            UsersError::NotFound | UsersError::OwnRole => StatusCode::SEE_OTHER,
+            UsersError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! # Call requests resource
+//! Anyone can create a call request, listing and reading them is reserved to
+//! staff authenticated with an API token.
+
+use actix_web::{http::header::LOCATION, web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Uuid, PgPool};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    authentication::ApiUser,
+    domain::{
+        call_request::CallRequestStatus, callback_slot::OfficeHours,
+        reference_code::ReferenceCodeKey,
+    },
+    metrics::{Channel, Metrics},
+    rate_limit::RateLimiter,
+    routes::call_request::{register_call_request, CallRequestForm, DuplicateWindow, Registration},
+    routes::topics::get_active_topics,
+};
+
+use super::{ApiError, Problem, PROBLEM_JSON};
+
+/// Call request as exposed by the API.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct CallRequestResource {
+    pub id: Uuid,
+    pub contact_name: String,
+    pub phone_number: String,
+    pub status: CallRequestStatus,
+    pub assigned_to: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    /// Last submission for the phone number, later ones are merged into
+    /// the open call request.
+    pub last_requested_at: DateTime<Utc>,
+    /// Topic chosen by the citizen, if any.
+    pub topic_id: Option<Uuid>,
+    /// Code the citizen can look the call request up with, e.g. `BBL-7K3Q9F-M2`.
+    /// Missing on the ones registered before codes existed.
+    pub reference: Option<String>,
+}
+
+pub fn location(call_request_id: Uuid) -> String {
+    format!("/api/v1/call_requests/{}", call_request_id)
+}
+
+/// Creates a call request. When the phone number already has an open one, that
+/// is updated and returned instead.
+#[utoipa::path(
+    post,
+    path = "/api/v1/call_requests",
+    tag = "call requests",
+    request_body = CallRequestForm,
+    responses(
+        (status = 201, description = "Call request registered", body = CallRequestResource,
+            headers(("Location" = String, description = "URL of the new call request"))),
+        (status = 200, description = "Merged into the open call request of the same phone number", body = CallRequestResource,
+            headers(("Location" = String, description = "URL of the open call request"))),
+        (status = 400, description = "Malformed body", body = Problem, content_type = PROBLEM_JSON),
+        (status = 422, description = "Invalid fields", body = Problem, content_type = PROBLEM_JSON),
+        (status = 429, description = "Too many call requests from the client or for the phone number",
+            body = Problem, content_type = PROBLEM_JSON,
+            headers(("Retry-After" = u64, description = "Seconds before retrying"))),
+    )
+)]
+#[tracing::instrument(
+    name = "API call request submission",
+    skip(req, body, pool, metrics, rate_limiter, rules)
+)]
+pub async fn create(
+    req: HttpRequest,
+    body: web::Json<CallRequestForm>,
+    pool: web::Data<PgPool>,
+    metrics: web::Data<Metrics>,
+    rate_limiter: web::Data<RateLimiter>,
+    rules: (
+        web::Data<OfficeHours>,
+        web::Data<DuplicateWindow>,
+        web::Data<ReferenceCodeKey>,
+    ),
+) -> Result<HttpResponse, ApiError> {
+    let (office_hours, duplicate_window, reference_key) = rules;
+    rate_limiter.check_client(&req).await?;
+    let topics = get_active_topics(&pool).await?;
+    let call_request = body.parse(&office_hours, &topics).inspect_err(|errors| {
+        metrics.call_request_rejected(Channel::Api, errors);
+    })?;
+    rate_limiter
+        .check_phone_number(&call_request.phone_number)
+        .await?;
+    let (registration, _) =
+        register_call_request(&pool, &call_request, **duplicate_window, &reference_key).await?;
+    let call_request_id = registration.call_request_id();
+    let resource = get_call_request(&pool, call_request_id)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let mut response = match registration {
+        Registration::Created(_) => {
+            metrics.call_request_created(Channel::Api);
+            HttpResponse::Created()
+        }
+        Registration::Merged(_) => HttpResponse::Ok(),
+    };
+    Ok(response
+        .insert_header((LOCATION, location(call_request_id)))
+        .json(resource))
+}
+
+/// Filters of the call request listing.
+#[derive(Deserialize, Debug, IntoParams)]
+pub struct ListQuery {
+    /// Only return call requests with this status.
+    status: Option<CallRequestStatus>,
+}
+
+/// Lists call requests, oldest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/call_requests",
+    tag = "call requests",
+    params(ListQuery),
+    security(("api_token" = [])),
+    responses(
+        (status = 200, description = "Call requests", body = [CallRequestResource]),
+        (status = 400, description = "Malformed filters", body = Problem, content_type = PROBLEM_JSON),
+        (status = 401, description = "Missing or invalid token", body = Problem, content_type = PROBLEM_JSON),
+    )
+)]
+#[tracing::instrument(name = "API call request listing", skip(pool))]
+pub async fn list(
+    _user: ApiUser,
+    query: web::Query<ListQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let call_requests = sqlx::query_as!(
+        CallRequestResource,
+        r#"
+        SELECT
+            id,
+            user_name AS contact_name,
+            phone_number,
+            status AS "status: CallRequestStatus",
+            assigned_to,
+            created_at,
+            last_requested_at,
+            topic_id,
+            reference
+        FROM call_requests
+        WHERE $1::call_request_status IS NULL OR status = $1
+        ORDER BY created_at ASC
+        "#,
+        query.status as Option<CallRequestStatus>,
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(call_requests))
+}
+
+/// Reads a call request.
+#[utoipa::path(
+    get,
+    path = "/api/v1/call_requests/{call_request_id}",
+    tag = "call requests",
+    params(("call_request_id" = Uuid, Path, description = "Id of the call request")),
+    security(("api_token" = [])),
+    responses(
+        (status = 200, description = "The call request", body = CallRequestResource),
+        (status = 401, description = "Missing or invalid token", body = Problem, content_type = PROBLEM_JSON),
+        (status = 404, description = "Unknown call request", body = Problem, content_type = PROBLEM_JSON),
+    )
+)]
+#[tracing::instrument(name = "API call request detail", skip(pool))]
+pub async fn detail(
+    _user: ApiUser,
+    call_request_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let call_request = get_call_request(&pool, call_request_id.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    Ok(HttpResponse::Ok().json(call_request))
+}
+
+#[tracing::instrument(name = "Get call request", skip(pool))]
+async fn get_call_request(
+    pool: &PgPool,
+    call_request_id: Uuid,
+) -> Result<Option<CallRequestResource>, sqlx::Error> {
+    sqlx::query_as!(
+        CallRequestResource,
+        r#"
+        SELECT
+            id,
+            user_name AS contact_name,
+            phone_number,
+            status AS "status: CallRequestStatus",
+            assigned_to,
+            created_at,
+            last_requested_at,
+            topic_id,
+            reference
+        FROM call_requests
+        WHERE id = $1
+        "#,
+        call_request_id,
+    )
+    .fetch_optional(pool)
+    .await
+}
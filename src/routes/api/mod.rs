@@ -0,0 +1,234 @@
+//! # JSON API
+//! Versioned API for kiosks and integrations, mounted under `/api/v1`.
+//!
+//! Errors are reported as [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807)
+//! problem details with the `application/problem+json` content type.
+
+pub mod call_requests;
+mod openapi;
+pub mod users;
+
+pub use openapi::ApiDoc;
+
+use actix_web::{
+    error::{JsonPayloadError, PathError, QueryPayloadError},
+    http::{
+        header::{RETRY_AFTER, WWW_AUTHENTICATE},
+        Method, StatusCode,
+    },
+    web, HttpRequest, HttpResponse, ResponseError, Route, Scope,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{domain::call_request::CallRequestValidationError, rate_limit::RateLimitExceeded};
+
+use super::error_chain_fmt;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Mount point of the API.
+pub const PREFIX: &str = "/api/v1";
+
+/// An operation of the API, with its path relative to [`PREFIX`]. Every one
+/// must be documented in [`ApiDoc`].
+pub struct ApiRoute {
+    pub method: Method,
+    pub path: &'static str,
+    /// Attaches the handler to a route guarded by `method`.
+    handler: fn(Route) -> Route,
+}
+
+/// The operations of the API, the single list both the server and the
+/// documentation checks read.
+pub fn routes() -> Vec<ApiRoute> {
+    vec![
+        ApiRoute {
+            method: Method::POST,
+            path: "/call_requests",
+            handler: |route| route.to(call_requests::create),
+        },
+        ApiRoute {
+            method: Method::GET,
+            path: "/call_requests",
+            handler: |route| route.to(call_requests::list),
+        },
+        ApiRoute {
+            method: Method::GET,
+            path: "/call_requests/{call_request_id}",
+            handler: |route| route.to(call_requests::detail),
+        },
+        ApiRoute {
+            method: Method::GET,
+            path: "/users",
+            handler: |route| route.to(users::list),
+        },
+    ]
+}
+
+/// The API scope, reporting every extractor failure as problem details.
+pub fn scope() -> Scope {
+    routes().into_iter().fold(
+        web::scope(PREFIX)
+            .app_data(web::JsonConfig::default().error_handler(json_error_handler))
+            .app_data(web::QueryConfig::default().error_handler(query_error_handler))
+            .app_data(web::PathConfig::default().error_handler(path_error_handler)),
+        |scope, route| scope.route(route.path, (route.handler)(web::method(route.method))),
+    )
+}
+
+/// Problem details body (RFC 7807).
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: &'static str,
+    pub title: &'static str,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Extension member listing the fields that failed validation.
+    #[serde(rename = "invalid-params", skip_serializing_if = "Vec::is_empty")]
+    pub invalid_params: Vec<InvalidParam>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct InvalidParam {
+    pub name: &'static str,
+    pub reason: String,
+}
+
+#[derive(thiserror::Error)]
+pub enum ApiError {
+    #[error("The request body is malformed.")]
+    MalformedBody(#[source] JsonPayloadError),
+    #[error("The query string is malformed.")]
+    MalformedQuery(#[source] QueryPayloadError),
+    #[error(transparent)]
+    ValidationError(#[from] CallRequestValidationError),
+    #[error("A valid bearer token is required.")]
+    Unauthorized,
+    #[error("The token does not grant access to this resource.")]
+    Forbidden,
+    #[error("The resource does not exist.")]
+    NotFound,
+    #[error("Too many call requests were submitted, retry later.")]
+    TooManyRequests(#[from] RateLimitExceeded),
+    #[error("Something went wrong.")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+impl std::fmt::Debug for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ApiError {
+    fn problem_type_and_title(&self) -> (&'static str, &'static str) {
+        match self {
+            ApiError::MalformedBody(_) => ("/problems/malformed-body", "Malformed request body"),
+            ApiError::MalformedQuery(_) => ("/problems/malformed-query", "Malformed query string"),
+            ApiError::ValidationError(_) => ("/problems/validation-error", "Validation failed"),
+            ApiError::Unauthorized => ("/problems/unauthorized", "Unauthorized"),
+            ApiError::Forbidden => ("/problems/forbidden", "Forbidden"),
+            ApiError::NotFound => ("about:blank", "Not Found"),
+            ApiError::TooManyRequests(_) => ("/problems/too-many-requests", "Too Many Requests"),
+            ApiError::DatabaseError(_) => ("about:blank", "Internal Server Error"),
+        }
+    }
+
+    fn invalid_params(&self) -> Vec<InvalidParam> {
+        let mut params = vec![];
+        if let ApiError::ValidationError(errors) = self {
+            if let Some(e) = errors.phone_number {
+                params.push(InvalidParam {
+                    name: "phone_number",
+                    reason: e.to_string(),
+                });
+            }
+            if let Some(e) = errors.contact_name {
+                params.push(InvalidParam {
+                    name: "contact_name",
+                    reason: e.to_string(),
+                });
+            }
+            if let Some(e) = errors.callback_slots {
+                params.push(InvalidParam {
+                    name: "callback_slots",
+                    reason: e.to_string(),
+                });
+            }
+            if let Some(e) = errors.topic {
+                params.push(InvalidParam {
+                    name: "topic",
+                    reason: e.to_string(),
+                });
+            }
+        }
+        params
+    }
+
+    pub fn problem(&self) -> Problem {
+        let (problem_type, title) = self.problem_type_and_title();
+        let detail = match self {
+            ApiError::MalformedBody(e) => Some(e.to_string()),
+            ApiError::MalformedQuery(e) => Some(e.to_string()),
+            ApiError::DatabaseError(_) => None,
+            _ => Some(self.to_string()),
+        };
+        Problem {
+            problem_type,
+            title,
+            status: self.status_code().as_u16(),
+            detail,
+            invalid_params: self.invalid_params(),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        let mut response = HttpResponse::build(self.status_code());
+        match self {
+            ApiError::Unauthorized => {
+                response.insert_header((WWW_AUTHENTICATE, "Bearer"));
+            }
+            ApiError::TooManyRequests(e) => {
+                // Whole seconds, rounded up.
+                let seconds = e.retry_after.as_millis().div_ceil(1000);
+                response.insert_header((RETRY_AFTER, seconds.to_string()));
+            }
+            _ => {}
+        }
+        response.content_type(PROBLEM_JSON).json(self.problem())
+    }
+
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::MalformedBody(_) | ApiError::MalformedQuery(_) => StatusCode::BAD_REQUEST,
+            ApiError::ValidationError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden => StatusCode::FORBIDDEN,
+            ApiError::NotFound => StatusCode::NOT_FOUND,
+            ApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Reports JSON deserialization failures as problem details.
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    ApiError::MalformedBody(err).into()
+}
+
+/// Reports query string deserialization failures as problem details.
+pub fn query_error_handler(err: QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    ApiError::MalformedQuery(err).into()
+}
+
+/// Reports path segments that do not deserialize, e.g. ids that are not
+/// UUIDs, as unknown resources.
+pub fn path_error_handler(err: PathError, _req: &HttpRequest) -> actix_web::Error {
+    tracing::debug!(error = %err, "Malformed path");
+    ApiError::NotFound.into()
+}
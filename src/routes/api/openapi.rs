@@ -0,0 +1,52 @@
+use utoipa::{
+    openapi::security::{Http, HttpAuthScheme, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use crate::{
+    domain::{call_request::CallRequestStatus, user::UserRole},
+    routes::{call_request, healthcheck},
+};
+
+use super::{call_requests, users, InvalidParam, Problem};
+
+/// OpenAPI document of the service, served at `/api/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Bubble Services", description = "A digital office for anagraphic services."),
+    paths(
+        healthcheck::healthcheck,
+        healthcheck::readiness,
+        call_request::post,
+        call_requests::create,
+        call_requests::list,
+        call_requests::detail,
+        users::list,
+    ),
+    components(schemas(
+        call_request::CallRequestForm,
+        call_requests::CallRequestResource,
+        CallRequestStatus,
+        users::UserResource,
+        UserRole,
+        Problem,
+        InvalidParam,
+        healthcheck::Readiness,
+        healthcheck::DependencyCheck,
+        healthcheck::ProbeStatus,
+    )),
+    modifiers(&ApiTokenSecurity)
+)]
+pub struct ApiDoc;
+
+struct ApiTokenSecurity;
+
+impl Modify for ApiTokenSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_token",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! # Users resource
+//! Staff accounts, readable by administrators only.
+
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use sqlx::{types::Uuid, PgPool};
+use utoipa::ToSchema;
+
+use crate::{authentication::ApiUser, domain::user::UserRole};
+
+use super::{ApiError, Problem, PROBLEM_JSON};
+
+/// Staff account as exposed by the API.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct UserResource {
+    pub id: Uuid,
+    pub username: String,
+    pub role: UserRole,
+}
+
+/// Lists staff accounts by username.
+#[utoipa::path(
+    get,
+    path = "/api/v1/users",
+    tag = "users",
+    security(("api_token" = [])),
+    responses(
+        (status = 200, description = "Staff accounts", body = [UserResource]),
+        (status = 401, description = "Missing or invalid token", body = Problem, content_type = PROBLEM_JSON),
+        (status = 403, description = "The token does not belong to an administrator", body = Problem, content_type = PROBLEM_JSON),
+    )
+)]
+#[tracing::instrument(name = "API user listing", skip(pool))]
+pub async fn list(user: ApiUser, pool: web::Data<PgPool>) -> Result<HttpResponse, ApiError> {
+    user.require(UserRole::Administrator)?;
+    let users = sqlx::query_as!(
+        UserResource,
+        r#"
+        SELECT user_id AS id, username, role AS "role: UserRole"
+        FROM users
+        ORDER BY username
+        "#
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+    Ok(HttpResponse::Ok().json(users))
+}
@@ -0,0 +1,80 @@
+//! # Static assets
+//! Serves the embedded files of [`crate::assets`]. Fingerprinted names never
+//! change content, so they are cached for a year; plain names must be
+//! revalidated. Compressed variants are sent to the browsers accepting them.
+
+use actix_web::{
+    http::header::{
+        HeaderMap, ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, ETAG, IF_NONE_MATCH, VARY,
+    },
+    web, HttpRequest, HttpResponse,
+};
+
+use crate::assets::Asset;
+
+#[tracing::instrument(name = "Static asset", skip(req))]
+pub async fn static_asset(req: HttpRequest, path: web::Path<String>) -> HttpResponse {
+    let Some((asset, fingerprinted)) = Asset::find(&path) else {
+        return HttpResponse::NotFound().finish();
+    };
+    // Weak, as the compressed variants are not byte-for-byte the same.
+    let etag = format!("W/\"{}\"", asset.hash);
+    let cache_control = if fingerprinted {
+        "public, max-age=31536000, immutable"
+    } else {
+        "public, no-cache"
+    };
+
+    let not_modified = matches_etag(req.headers(), asset.hash);
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response
+        .insert_header((ETAG, etag))
+        .insert_header((CACHE_CONTROL, cache_control))
+        .insert_header((VARY, "Accept-Encoding"));
+    if not_modified {
+        return response.finish();
+    }
+
+    let (encoding, body) = match (asset.brotli, asset.gzip) {
+        (Some(brotli), _) if accepts(req.headers(), "br") => (Some("br"), brotli),
+        (_, Some(gzip)) if accepts(req.headers(), "gzip") => (Some("gzip"), gzip),
+        _ => (None, asset.body),
+    };
+    if let Some(encoding) = encoding {
+        response.insert_header((CONTENT_ENCODING, encoding));
+    }
+    response.content_type(asset.content_type()).body(body)
+}
+
+/// Whether the browser already has the asset with `hash`.
+fn matches_etag(headers: &HeaderMap, hash: &str) -> bool {
+    let Some(if_none_match) = headers.get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    if_none_match.split(',').any(|tag| {
+        let tag = tag.trim();
+        tag == "*" || tag.trim_start_matches("W/").trim_matches('"') == hash
+    })
+}
+
+/// Whether `Accept-Encoding` allows `encoding`, i.e. lists it without `q=0`.
+fn accepts(headers: &HeaderMap, encoding: &str) -> bool {
+    let Some(accept_encoding) = headers.get(ACCEPT_ENCODING).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    accept_encoding.split(',').any(|item| {
+        let mut parts = item.split(';').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        let refused = parts.any(|parameter| {
+            parameter
+                .strip_prefix("q=")
+                .and_then(|q| q.parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+        name.eq_ignore_ascii_case(encoding) && !refused
+    })
+}
@@ -1,73 +1,491 @@
 use actix_web::{
     http::{header::LOCATION, StatusCode},
-    web, HttpResponse, Responder, ResponseError,
+    web, HttpRequest, HttpResponse, Responder, ResponseError,
 };
 use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use actix_web_lab::extract::UrlEncodedForm;
 use askama_actix::Template;
-use chrono::Utc;
+use chrono::{Utc, Weekday};
 use serde::Deserialize;
-use sqlx::{types::Uuid, PgPool};
+use sqlx::{types::Uuid, Acquire, PgPool, Postgres, Transaction};
 use tracing::instrument;
+use utoipa::ToSchema;
 
-use crate::domain::call_request::{CallRequestContactName, CallRequestPhoneNumber, NewCallRequest};
+use crate::{
+    csrf::CsrfToken,
+    domain::{
+        call_request::{CallRequestValidationError, NewCallRequest},
+        callback_slot::{CallbackSlot, DayPeriod, OfficeHours, TimeRange},
+        reference_code::{ReferenceCode, ReferenceCodeKey},
+        topic::Topic,
+    },
+    i18n::{I18n, Message},
+    metrics::{Channel, Metrics},
+    rate_limit::{RateLimitExceeded, RateLimiter},
+    security_headers::CspNonce,
+    session_state::TypedSession,
+};
 
-use super::error_chain_fmt;
+use super::{error_chain_fmt, topics::get_active_topics};
 
 #[derive(Template)]
 #[template(path = "call_request.html")]
 struct CallRequestTemplate {
     messages: Vec<FlashMessage>,
+    form: CallRequestForm,
+    errors: CallRequestValidationError,
+    office_hours: web::Data<OfficeHours>,
+    topics: Vec<Topic>,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
 }
 
-#[instrument(name = "Call Request page", skip(messages), fields(num_messages))]
-pub async fn get(messages: IncomingFlashMessages) -> impl Responder {
+impl CallRequestTemplate {
+    /// Columns of the callback slot grid, with their hours.
+    fn periods(&self) -> Vec<(DayPeriod, TimeRange)> {
+        DayPeriod::ALL
+            .into_iter()
+            .map(|period| (period, self.office_hours.hours(period)))
+            .collect()
+    }
+
+    /// Rows of the callback slot grid, one per weekday the office is open,
+    /// without the slots it is closed in.
+    fn slot_grid(&self) -> Vec<(Weekday, Vec<Option<CallbackSlot>>)> {
+        self.office_hours
+            .weekdays()
+            .into_iter()
+            .map(|weekday| {
+                let slots = DayPeriod::ALL
+                    .into_iter()
+                    .map(|period| CallbackSlot::new(weekday, period))
+                    .map(|slot| self.office_hours.is_open(slot).then_some(slot))
+                    .collect();
+                (weekday, slots)
+            })
+            .collect()
+    }
+}
+
+#[instrument(
+    name = "Call Request page",
+    skip(messages, pool, office_hours, csrf_token, csp_nonce, i18n),
+    fields(num_messages)
+)]
+pub async fn get(
+    messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    office_hours: web::Data<OfficeHours>,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+) -> impl Responder {
     let messages: Vec<FlashMessage> = messages.iter().cloned().collect();
     tracing::Span::current().record("num_messages", messages.len());
-    CallRequestTemplate { messages }
+    // Database errors redirect here, the form is still shown without topics.
+    let topics = get_active_topics(&pool).await.unwrap_or_else(|e| {
+        tracing::error!(error = ?e, "Failed to get the topics");
+        vec![]
+    });
+    CallRequestTemplate {
+        messages,
+        form: CallRequestForm::default(),
+        errors: CallRequestValidationError::default(),
+        office_hours,
+        topics,
+        csrf_token,
+        csp_nonce,
+        i18n,
+    }
 }
 
 /// Raw call request input that needs to be parsed.
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, Default, ToSchema)]
 pub struct CallRequestForm {
+    /// Italian or international phone number, e.g. `+39 320 894 6581`.
+    #[schema(example = "+39 320 894 6581")]
     phone_number: String,
+    /// Name of the person to call, 2 to 128 characters.
+    #[schema(example = "Rino Pape")]
     contact_name: String,
+    /// Preferred callback slots, as `<weekday>-<morning|afternoon>`, within
+    /// office hours. None means any time during office hours.
+    #[serde(default)]
+    #[schema(example = json!(["mon-morning", "thu-afternoon"]))]
+    callback_slots: Vec<String>,
+    /// Id of one of the topics offered on the form, empty when none fits.
+    #[serde(default)]
+    #[schema(example = "")]
+    topic: String,
+}
+
+impl CallRequestForm {
+    pub fn parse(
+        &self,
+        office_hours: &OfficeHours,
+        offered_topics: &[Topic],
+    ) -> Result<NewCallRequest, CallRequestValidationError> {
+        NewCallRequest::parse(
+            self.phone_number.clone(),
+            self.contact_name.clone(),
+            &self.callback_slots,
+            office_hours,
+            &self.topic,
+            offered_topics,
+        )
+    }
+
+    /// Whether the topic was chosen, to keep it selected when the form is
+    /// shown again.
+    fn has_topic(&self, topic: &Topic) -> bool {
+        self.topic.trim() == topic.id.to_string()
+    }
+
+    /// Whether the slot was chosen, to keep it checked when the form is shown
+    /// again.
+    fn has_callback_slot(&self, slot: &CallbackSlot) -> bool {
+        let slot = slot.to_string();
+        self.callback_slots.iter().any(|value| value.trim() == slot)
+    }
 }
 
-#[instrument(name = "Call Request submission", skip(form, pool))]
+/// Call request submission from the HTML form.
+#[utoipa::path(
+    post,
+    path = "/call_request",
+    tag = "call requests",
+    request_body(content = CallRequestForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Registered, or merged into the open call request of the same phone number, redirects to the confirmation page showing its reference code. Over the rate limits, redirects back to the form with an error"),
+        (status = 400, description = "Invalid input, the form is shown again with the errors", content_type = "text/html"),
+    )
+)]
+#[instrument(
+    name = "Call Request submission",
+    skip(req, form, session, page_tokens, pool, counters, rules)
+)]
 pub async fn post(
-    form: web::Form<CallRequestForm>,
+    req: HttpRequest,
+    // Checkboxes repeat the `callback_slots` key, which `web::Form` rejects.
+    form: UrlEncodedForm<CallRequestForm>,
+    session: TypedSession,
+    // Needed only to show the form again.
+    page_tokens: (CsrfToken, CspNonce, I18n),
     pool: web::Data<PgPool>,
+    counters: (web::Data<RateLimiter>, web::Data<Metrics>),
+    rules: (
+        web::Data<OfficeHours>,
+        web::Data<DuplicateWindow>,
+        web::Data<ReferenceCodeKey>,
+    ),
 ) -> Result<HttpResponse, CallRequestError> {
-    let call_request =
-        NewCallRequest::try_from(form.0).map_err(CallRequestError::ValidationError)?;
-    let call_id = Uuid::new_v4();
-    let created_at = Utc::now();
+    let (rate_limiter, metrics) = counters;
+    let (office_hours, duplicate_window, reference_key) = rules;
+    // Invalid submissions count too, scripts should not get free attempts.
+    rate_limiter.check_client(&req).await?;
+    let topics = get_active_topics(&pool).await?;
+    let call_request = match form.parse(&office_hours, &topics) {
+        Ok(call_request) => call_request,
+        Err(errors) => {
+            tracing::info!(?errors, "Invalid call request submitted");
+            metrics.call_request_rejected(Channel::Form, &errors);
+            // The form is shown again with the submitted values, so that
+            // the citizen only has to fix the wrong fields.
+            let (csrf_token, csp_nonce, i18n) = page_tokens;
+            let page = CallRequestTemplate {
+                messages: vec![],
+                form: form.0,
+                errors,
+                office_hours,
+                topics,
+                csrf_token,
+                csp_nonce,
+                i18n,
+            }
+            .render()?;
+            return Ok(HttpResponse::BadRequest()
+                .content_type(CallRequestTemplate::MIME_TYPE)
+                .body(page));
+        }
+    };
+    rate_limiter
+        .check_phone_number(&call_request.phone_number)
+        .await?;
+    let (registration, reference) =
+        register_call_request(&pool, &call_request, **duplicate_window, &reference_key).await?;
+    match registration {
+        Registration::Created(_) => {
+            metrics.call_request_created(Channel::Form);
+            FlashMessage::info(Message::new("call-request-registered")).send();
+        }
+        Registration::Merged(_) => {
+            FlashMessage::info(Message::new("call-request-merged")).send();
+        }
+    }
+    // Kept in the session, the reference code is shown until the next one.
+    session.insert_call_request_reference(&reference)?;
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/call_request/confirmation"))
+        .finish())
+}
 
-    sqlx::query!(
+/// How long an open call request absorbs new submissions for the same phone
+/// number, counting from its last submission.
+#[derive(Debug, Clone, Copy)]
+pub struct DuplicateWindow(pub chrono::Duration);
+
+/// Outcome of a call request submission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Registration {
+    /// A new call request was queued.
+    Created(Uuid),
+    /// The phone number already had an open call request, which was updated
+    /// and keeps its place in the queue.
+    Merged(Uuid),
+}
+
+impl Registration {
+    pub fn call_request_id(self) -> Uuid {
+        match self {
+            Registration::Created(id) | Registration::Merged(id) => id,
+        }
+    }
+}
+
+/// Contact names a merged call request keeps, later ones are dropped.
+const MAX_CONTACT_NAMES: i32 = 5;
+
+/// Stores a validated call request, unless an open one for the same phone
+/// number was submitted within `window`: that one is updated instead. Either
+/// way, returns the reference code of the call request.
+#[instrument(
+    name = "Registering call request",
+    skip(pool, call_request, reference_key)
+)]
+pub async fn register_call_request(
+    pool: &PgPool,
+    call_request: &NewCallRequest,
+    window: DuplicateWindow,
+    reference_key: &ReferenceCodeKey,
+) -> Result<(Registration, ReferenceCode), sqlx::Error> {
+    let now = Utc::now();
+    let mut transaction = pool.begin().await?;
+    // Concurrent submissions for the same number must not both insert.
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(call_request.phone_number.as_ref())
+        .execute(&mut *transaction)
+        .await?;
+
+    let open_call_request = sqlx::query_scalar!(
         r#"
-            INSERT INTO call_requests (id, user_name, phone_number, created_at)
-            VALUES ($1, $2, $3, $4)
-            "#,
-        call_id,
-        call_request.contact_name.as_ref(),
+        SELECT id
+        FROM call_requests
+        WHERE phone_number = $1
+            AND status NOT IN ('completed', 'cancelled')
+            AND last_requested_at > $2
+        ORDER BY created_at ASC
+        LIMIT 1
+        "#,
         call_request.phone_number.as_ref(),
-        created_at
+        now - window.0,
     )
-    .execute(pool.get_ref())
+    .fetch_optional(&mut *transaction)
     .await?;
 
-    FlashMessage::info("Call request registered. You will be called soon!").send();
-    Ok(HttpResponse::SeeOther()
-        .insert_header((LOCATION, "/"))
-        .finish())
+    let registration = match open_call_request {
+        Some(call_request_id) => {
+            // Names are separated by slashes, which names cannot contain.
+            sqlx::query!(
+                r#"
+                UPDATE call_requests
+                SET
+                    last_requested_at = $2,
+                    user_name = CASE
+                        WHEN lower($3) = ANY(string_to_array(lower(user_name), ' / '))
+                            OR cardinality(string_to_array(user_name, ' / ')) >= $5
+                        THEN user_name
+                        ELSE user_name || ' / ' || $3
+                    END,
+                    topic_id = COALESCE($4, topic_id)
+                WHERE id = $1
+                "#,
+                call_request_id,
+                now,
+                call_request.contact_name.as_ref(),
+                call_request.topic_id,
+                MAX_CONTACT_NAMES,
+            )
+            .execute(&mut *transaction)
+            .await?;
+            merge_callback_slots(
+                &mut transaction,
+                call_request_id,
+                &call_request.callback_slots,
+            )
+            .await?;
+            Registration::Merged(call_request_id)
+        }
+        None => {
+            let call_request_id = Uuid::new_v4();
+            sqlx::query!(
+                r#"
+                INSERT INTO call_requests
+                    (id, user_name, phone_number, created_at, last_requested_at, topic_id)
+                VALUES ($1, $2, $3, $4, $4, $5)
+                "#,
+                call_request_id,
+                call_request.contact_name.as_ref(),
+                call_request.phone_number.as_ref(),
+                now,
+                call_request.topic_id,
+            )
+            .execute(&mut *transaction)
+            .await?;
+            store_callback_slots(
+                &mut transaction,
+                call_request_id,
+                &call_request.callback_slots,
+            )
+            .await?;
+            Registration::Created(call_request_id)
+        }
+    };
+    let reference = assign_reference(
+        &mut transaction,
+        registration.call_request_id(),
+        reference_key,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok((registration, reference))
+}
+
+/// Codes tried before giving up, taken ones being rare (see
+/// [`ReferenceCode::generate`]).
+const MAX_REFERENCE_ATTEMPTS: u32 = 5;
+
+/// Gives the call request a reference code, unless it already has one. The
+/// ones registered before codes existed get theirs when submitted again.
+#[instrument(name = "Assigning reference code", skip(transaction, reference_key))]
+async fn assign_reference(
+    transaction: &mut Transaction<'_, Postgres>,
+    call_request_id: Uuid,
+    reference_key: &ReferenceCodeKey,
+) -> Result<ReferenceCode, sqlx::Error> {
+    let reference = sqlx::query_scalar!(
+        "SELECT reference FROM call_requests WHERE id = $1",
+        call_request_id,
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+    if let Some(reference) = reference {
+        return Ok(ReferenceCode::restore(reference));
+    }
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let reference = ReferenceCode::generate(reference_key);
+        // Serials are random, another call request may have this one: the
+        // unique index rejects it, within a savepoint not to abort the
+        // transaction.
+        let mut savepoint = transaction.begin().await?;
+        let assigned = sqlx::query!(
+            "UPDATE call_requests SET reference = $1 WHERE id = $2",
+            reference.as_ref(),
+            call_request_id,
+        )
+        .execute(&mut *savepoint)
+        .await;
+        match assigned {
+            Ok(_) => {
+                savepoint.commit().await?;
+                return Ok(reference);
+            }
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                savepoint.rollback().await?;
+                if attempts == MAX_REFERENCE_ATTEMPTS {
+                    tracing::error!(attempts, "Every reference code tried was taken.");
+                    return Err(sqlx::Error::Database(e));
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Adds the slots of a new submission to the ones of the call request. No
+/// slots means any time: when either side has none, the call request keeps
+/// none.
+#[instrument(name = "Merging callback slots", skip(transaction))]
+async fn merge_callback_slots(
+    transaction: &mut Transaction<'_, Postgres>,
+    call_request_id: Uuid,
+    callback_slots: &[CallbackSlot],
+) -> Result<(), sqlx::Error> {
+    if callback_slots.is_empty() {
+        sqlx::query!(
+            "DELETE FROM call_request_callback_slots WHERE call_request_id = $1",
+            call_request_id,
+        )
+        .execute(&mut **transaction)
+        .await?;
+        return Ok(());
+    }
+    let restricted = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM call_request_callback_slots WHERE call_request_id = $1
+        ) AS "restricted!"
+        "#,
+        call_request_id,
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+    if restricted {
+        store_callback_slots(transaction, call_request_id, callback_slots).await?;
+    }
+    Ok(())
+}
+
+#[instrument(name = "Storing callback slots", skip(transaction))]
+async fn store_callback_slots(
+    transaction: &mut Transaction<'_, Postgres>,
+    call_request_id: Uuid,
+    callback_slots: &[CallbackSlot],
+) -> Result<(), sqlx::Error> {
+    let weekdays: Vec<i16> = callback_slots
+        .iter()
+        .map(CallbackSlot::iso_weekday)
+        .collect();
+    let periods: Vec<DayPeriod> = callback_slots.iter().map(|slot| slot.period).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO call_request_callback_slots (call_request_id, weekday, period)
+        SELECT $1, weekday, period
+        FROM unnest($2::SMALLINT[], $3::day_period[]) AS slots(weekday, period)
+        ON CONFLICT DO NOTHING
+        "#,
+        call_request_id,
+        &weekdays,
+        &periods as &[DayPeriod],
+    )
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
 }
 
 #[derive(thiserror::Error)]
 pub enum CallRequestError {
-    #[error("{0}")]
-    ValidationError(String),
+    #[error("You sent too many call requests, please try again later.")]
+    RateLimited(#[from] RateLimitExceeded),
     #[error(transparent)]
     InsertionError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RenderError(#[from] askama::Error),
+    #[error("Failed to keep the reference code in the session.")]
+    SessionError(#[from] actix_session::SessionInsertError),
 }
 
 impl std::fmt::Debug for CallRequestError {
@@ -78,34 +496,21 @@ impl std::fmt::Debug for CallRequestError {
 
 impl ResponseError for CallRequestError {
     fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
-        let error_content = match self {
-            CallRequestError::ValidationError(s) => s,
-            CallRequestError::InsertionError(_) => "Database error!",
+        let message = match self {
+            CallRequestError::RateLimited(_) => "call-request-rate-limited",
+            CallRequestError::InsertionError(_) => "database-error",
+            CallRequestError::RenderError(_) | CallRequestError::SessionError(_) => {
+                "unexpected-error"
+            }
         };
-        FlashMessage::error(error_content).send();
+        FlashMessage::error(Message::new(message)).send();
         HttpResponse::SeeOther()
             .insert_header((LOCATION, "/call_request"))
             .finish()
     }
 
+    /// Every error is reported with a flash message on the form.
     fn status_code(&self) -> StatusCode {
-        match self {
-            CallRequestError::ValidationError(_) => StatusCode::BAD_REQUEST,
-            CallRequestError::InsertionError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-        }
-    }
-}
-
-impl TryFrom<CallRequestForm> for NewCallRequest {
-    type Error = String;
-
-    fn try_from(value: CallRequestForm) -> Result<Self, Self::Error> {
-        let contact_name = CallRequestContactName::parse(value.contact_name)?;
-        let phone_number = CallRequestPhoneNumber::parse(value.phone_number)?;
-
-        Ok(NewCallRequest {
-            phone_number,
-            contact_name,
-        })
+        StatusCode::SEE_OTHER
     }
 }
@@ -0,0 +1,243 @@
+//! # Call request status
+//! Once a call request is submitted, the citizen is shown its reference code.
+//! With the code and the phone number they can later look up how their call
+//! request is going, without an account. Lookups are rate limited, and a code
+//! with the wrong phone number is reported as not found.
+
+use actix_web::{
+    http::{header::LOCATION, StatusCode},
+    web, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use askama_actix::Template;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    csrf::CsrfToken,
+    domain::{
+        call_request::{CallRequestPhoneNumber, CallRequestStatus},
+        reference_code::{ReferenceCode, ReferenceCodeKey},
+    },
+    i18n::{I18n, Message},
+    rate_limit::{RateLimitExceeded, RateLimiter},
+    security_headers::CspNonce,
+    session_state::TypedSession,
+};
+
+use super::error_chain_fmt;
+
+#[derive(Template)]
+#[template(path = "call_request_confirmation.html")]
+struct ConfirmationTemplate {
+    messages: Vec<FlashMessage>,
+    reference: ReferenceCode,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+}
+
+/// Shows the reference code of the call request just submitted, after the
+/// outcome of the submission.
+#[tracing::instrument(
+    name = "Call request confirmation",
+    skip(messages, session, csrf_token, csp_nonce, i18n)
+)]
+pub async fn confirmation(
+    messages: IncomingFlashMessages,
+    session: TypedSession,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+) -> Result<HttpResponse, StatusLookupError> {
+    let Some(reference) = session.get_call_request_reference()? else {
+        // Nothing was submitted from this browser.
+        return Ok(HttpResponse::SeeOther()
+            .insert_header((LOCATION, "/call_request"))
+            .finish());
+    };
+    let page = ConfirmationTemplate {
+        messages: messages.iter().cloned().collect(),
+        reference,
+        csrf_token,
+        csp_nonce,
+        i18n,
+    }
+    .render()?;
+    Ok(HttpResponse::Ok()
+        .content_type(ConfirmationTemplate::MIME_TYPE)
+        .body(page))
+}
+
+/// A call request as its citizen sees it.
+pub struct CallRequestState {
+    pub reference: ReferenceCode,
+    pub status: CallRequestStatus,
+    pub created_at: DateTime<Utc>,
+    pub last_requested_at: DateTime<Utc>,
+}
+
+#[derive(Template)]
+#[template(path = "call_request_status.html")]
+struct StatusTemplate {
+    messages: Vec<FlashMessage>,
+    form: StatusLookupForm,
+    call_request: Option<CallRequestState>,
+    /// Whether a lookup found nothing.
+    not_found: bool,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+}
+
+/// Raw status lookup input.
+#[derive(Deserialize, Default)]
+pub struct StatusLookupForm {
+    reference: String,
+    phone_number: String,
+}
+
+#[tracing::instrument(
+    name = "Call request status page",
+    skip(messages, session, csrf_token, csp_nonce, i18n)
+)]
+pub async fn get(
+    messages: IncomingFlashMessages,
+    session: TypedSession,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+) -> impl Responder {
+    // The code of the last call request submitted from the browser, if any.
+    let reference = session
+        .get_call_request_reference()
+        .unwrap_or_else(|e| {
+            tracing::warn!(error = ?e, "Failed to read the reference code from the session");
+            None
+        })
+        .map(|reference| reference.to_string())
+        .unwrap_or_default();
+    StatusTemplate {
+        messages: messages.iter().cloned().collect(),
+        form: StatusLookupForm {
+            reference,
+            ..Default::default()
+        },
+        call_request: None,
+        not_found: false,
+        csrf_token,
+        csp_nonce,
+        i18n,
+    }
+}
+
+#[tracing::instrument(
+    name = "Call request status lookup",
+    skip(req, form, page_tokens, pool, rate_limiter, reference_key)
+)]
+pub async fn post(
+    req: HttpRequest,
+    form: web::Form<StatusLookupForm>,
+    page_tokens: (CsrfToken, CspNonce, I18n),
+    pool: web::Data<PgPool>,
+    rate_limiter: web::Data<RateLimiter>,
+    reference_key: web::Data<ReferenceCodeKey>,
+) -> Result<HttpResponse, StatusLookupError> {
+    rate_limiter.check_status_lookup(&req).await?;
+    let form = form.into_inner();
+    // Malformed input cannot match anything, it is reported the same way.
+    let call_request = match (
+        ReferenceCode::parse(&form.reference, &reference_key),
+        CallRequestPhoneNumber::parse(form.phone_number.clone()),
+    ) {
+        (Ok(reference), Ok(phone_number)) => {
+            get_call_request_state(&pool, &reference, &phone_number).await?
+        }
+        _ => None,
+    };
+
+    let (csrf_token, csp_nonce, i18n) = page_tokens;
+    let mut response = match call_request {
+        Some(_) => HttpResponse::Ok(),
+        None => HttpResponse::NotFound(),
+    };
+    let page = StatusTemplate {
+        messages: vec![],
+        form,
+        not_found: call_request.is_none(),
+        call_request,
+        csrf_token,
+        csp_nonce,
+        i18n,
+    }
+    .render()?;
+    Ok(response.content_type(StatusTemplate::MIME_TYPE).body(page))
+}
+
+#[tracing::instrument(name = "Get call request state", skip(pool, phone_number))]
+async fn get_call_request_state(
+    pool: &PgPool,
+    reference: &ReferenceCode,
+    phone_number: &CallRequestPhoneNumber,
+) -> Result<Option<CallRequestState>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            status AS "status: CallRequestStatus",
+            created_at,
+            last_requested_at
+        FROM call_requests
+        WHERE reference = $1 AND phone_number = $2
+        "#,
+        reference.as_ref(),
+        phone_number.as_ref(),
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|row| CallRequestState {
+        reference: reference.clone(),
+        status: row.status,
+        created_at: row.created_at,
+        last_requested_at: row.last_requested_at,
+    }))
+}
+
+#[derive(thiserror::Error)]
+pub enum StatusLookupError {
+    #[error("Too many status lookups, retry later.")]
+    RateLimited(#[from] RateLimitExceeded),
+    #[error(transparent)]
+    DatabaseError(#[from] sqlx::Error),
+    #[error(transparent)]
+    RenderError(#[from] askama::Error),
+    #[error("Failed to read the reference code from the session.")]
+    SessionError(#[from] actix_session::SessionGetError),
+}
+
+impl std::fmt::Debug for StatusLookupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for StatusLookupError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        let message = match self {
+            StatusLookupError::RateLimited(_) => "status-lookup-rate-limited",
+            StatusLookupError::DatabaseError(_) => "database-error",
+            StatusLookupError::RenderError(_) | StatusLookupError::SessionError(_) => {
+                "unexpected-error"
+            }
+        };
+        FlashMessage::error(Message::new(message)).send();
+        HttpResponse::SeeOther()
+            .insert_header((LOCATION, "/call_request/status"))
+            .finish()
+    }
+
+    /// Every error is reported with a flash message on the lookup form.
+    fn status_code(&self) -> StatusCode {
+        StatusCode::SEE_OTHER
+    }
+}
@@ -1,6 +1,137 @@
-use actix_web::Responder;
+//! # Probes
+//! `/healthcheck` tells whether the process is alive, `/ready` whether its
+//! dependencies are reachable so that it can serve traffic.
 
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use actix_web::{web, HttpResponse, Responder};
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+/// Liveness probe.
+#[utoipa::path(
+    get,
+    path = "/healthcheck",
+    tag = "operations",
+    responses((status = 200, description = "The service is running", body = String))
+)]
 #[tracing::instrument]
 pub async fn healthcheck() -> impl Responder {
     "OK"
 }
+
+/// Dependencies checked by the readiness probe, each with at most `timeout`
+/// to answer.
+#[derive(Clone, Debug)]
+pub struct DependencyProbes {
+    /// Only set when sessions are stored in Redis.
+    pub redis: Option<redis::Client>,
+    pub timeout: Duration,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeStatus {
+    Up,
+    Down,
+}
+
+/// Outcome of a dependency check.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct DependencyCheck {
+    pub status: ProbeStatus,
+    pub latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Readiness {
+    /// `up` when every dependency is.
+    pub status: ProbeStatus,
+    pub checks: BTreeMap<&'static str, DependencyCheck>,
+}
+
+/// Readiness probe.
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "operations",
+    responses(
+        (status = 200, description = "Every dependency is reachable", body = Readiness),
+        (status = 503, description = "Some dependency is not reachable", body = Readiness),
+    )
+)]
+#[tracing::instrument(skip(pool, probes))]
+pub async fn readiness(
+    pool: web::Data<PgPool>,
+    probes: web::Data<DependencyProbes>,
+) -> HttpResponse {
+    let mut checks = BTreeMap::new();
+    checks.insert(
+        "postgres",
+        check(probes.timeout, async {
+            sqlx::query("SELECT 1").execute(pool.get_ref()).await?;
+            Ok(())
+        })
+        .await,
+    );
+    if let Some(client) = &probes.redis {
+        checks.insert(
+            "redis",
+            check(probes.timeout, async {
+                let mut connection = client.get_multiplexed_tokio_connection().await?;
+                redis::cmd("PING")
+                    .query_async::<String>(&mut connection)
+                    .await?;
+                Ok(())
+            })
+            .await,
+        );
+    }
+
+    let status = if checks.values().all(|c| c.status == ProbeStatus::Up) {
+        ProbeStatus::Up
+    } else {
+        ProbeStatus::Down
+    };
+    let mut response = match status {
+        ProbeStatus::Up => HttpResponse::Ok(),
+        ProbeStatus::Down => HttpResponse::ServiceUnavailable(),
+    };
+    response
+        .insert_header(("Cache-Control", "no-store"))
+        .json(Readiness { status, checks })
+}
+
+async fn check(
+    timeout: Duration,
+    probe: impl Future<Output = Result<(), anyhow::Error>>,
+) -> DependencyCheck {
+    let start = Instant::now();
+    let outcome = tokio::time::timeout(timeout, probe).await;
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let error = match outcome {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("No answer within {}ms.", timeout.as_millis())),
+    };
+    if let Some(error) = &error {
+        tracing::warn!(error, "Dependency check failed.");
+    }
+    DependencyCheck {
+        status: if error.is_none() {
+            ProbeStatus::Up
+        } else {
+            ProbeStatus::Down
+        },
+        latency_ms,
+        error,
+    }
+}
@@ -5,20 +5,42 @@
 //! ## Unauthenticated actions
 //! Call requests can be submitted also by users that are not authenticated.
 //! Unauthenticated users can be authenticated using the login action.
+//!
+//! ## Authenticated actions
+//! Authenticated users can reach their call request dashboard and log out.
 
 use actix_web::Responder;
 use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
 use askama_actix::Template;
 
+use crate::{csrf::CsrfToken, i18n::I18n, security_headers::CspNonce, session_state::TypedSession};
+
 #[derive(Template)]
 #[template(path = "index.html")]
 struct HomeTemplate {
     messages: Vec<FlashMessage>,
+    logged_in: bool,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
 }
 
-#[tracing::instrument(name = "Home", skip(messages))]
-pub async fn home(messages: IncomingFlashMessages) -> impl Responder {
+#[tracing::instrument(name = "Home", skip(messages, session, csrf_token, csp_nonce, i18n))]
+pub async fn home(
+    messages: IncomingFlashMessages,
+    session: TypedSession,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+) -> impl Responder {
     let messages: Vec<FlashMessage> = messages.iter().cloned().collect();
+    let logged_in = matches!(session.get_user_id(), Ok(Some(_)));
 
-    HomeTemplate { messages }
+    HomeTemplate {
+        messages,
+        logged_in,
+        csrf_token,
+        csp_nonce,
+        i18n,
+    }
 }
@@ -0,0 +1,38 @@
+//! # Language switcher
+//! Every page lets the user pick Italian or English, overriding the language
+//! asked by the browser (see [`crate::i18n`]).
+
+use actix_web::{
+    cookie::{time::Duration, Cookie, SameSite},
+    http::header::LOCATION,
+    web, HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+
+use crate::{
+    csrf::same_origin_referer,
+    i18n::{Locale, LANGUAGE_COOKIE},
+};
+
+#[derive(Deserialize)]
+pub struct LanguageForm {
+    lang: Locale,
+}
+
+/// Remembers the picked language and goes back to the page of the switcher.
+#[tracing::instrument(name = "Switch language", skip(req, form), fields(lang = form.lang.as_str()))]
+pub async fn switch_language(req: HttpRequest, form: web::Form<LanguageForm>) -> HttpResponse {
+    let cookie = Cookie::build(LANGUAGE_COOKIE, form.lang.as_str())
+        .path("/")
+        .max_age(Duration::days(365))
+        .same_site(SameSite::Lax)
+        .http_only(true)
+        .finish();
+    HttpResponse::SeeOther()
+        .insert_header((
+            LOCATION,
+            same_origin_referer(&req).unwrap_or_else(|| "/".into()),
+        ))
+        .cookie(cookie)
+        .finish()
+}
@@ -1,16 +1,117 @@
-use actix_web::Responder;
+use actix_web::{
+    http::{header::LOCATION, StatusCode},
+    web, HttpResponse, Responder, ResponseError,
+};
 use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
 use askama::Template;
+use secrecy::Secret;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    authentication::{validate_credentials, AuthError, Credentials},
+    csrf::CsrfToken,
+    i18n::{I18n, Message},
+    security_headers::CspNonce,
+    session_state::TypedSession,
+};
+
+use super::error_chain_fmt;
 
 #[derive(Template)]
 #[template(path = "login.html")]
 struct LoginTemplate {
     messages: Vec<FlashMessage>,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
 }
 
-#[tracing::instrument(name = "Login Form", skip(messages))]
-pub async fn get(messages: IncomingFlashMessages) -> impl Responder {
+#[tracing::instrument(name = "Login Form", skip(messages, csrf_token, csp_nonce, i18n))]
+pub async fn get(
+    messages: IncomingFlashMessages,
+    csrf_token: CsrfToken,
+    csp_nonce: CspNonce,
+    i18n: I18n,
+) -> impl Responder {
     LoginTemplate {
         messages: messages.iter().cloned().collect(),
+        csrf_token,
+        csp_nonce,
+        i18n,
+    }
+}
+
+/// Raw login input.
+#[derive(Deserialize)]
+pub struct LoginForm {
+    username: String,
+    password: Secret<String>,
+}
+
+#[tracing::instrument(
+    name = "Login submission",
+    skip(form, pool, session),
+    fields(username = tracing::field::Empty, user_id = tracing::field::Empty)
+)]
+pub async fn post(
+    form: web::Form<LoginForm>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+) -> Result<HttpResponse, LoginError> {
+    let credentials = Credentials {
+        username: form.0.username,
+        password: form.0.password,
+    };
+    tracing::Span::current().record("username", tracing::field::display(&credentials.username));
+
+    let user = validate_credentials(credentials, &pool)
+        .await
+        .map_err(|e| match e {
+            AuthError::InvalidCredentials(_) => LoginError::AuthError(e.into()),
+            AuthError::UnexpectedError(_) => LoginError::UnexpectedError(e.into()),
+        })?;
+    tracing::Span::current().record("user_id", tracing::field::display(&user.user_id));
+
+    session.renew();
+    session
+        .insert_user_id(user.user_id)
+        .map_err(|e| LoginError::UnexpectedError(e.into()))?;
+
+    FlashMessage::info(Message::new("welcome-back")).send();
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/call_requests"))
+        .finish())
+}
+
+#[derive(thiserror::Error)]
+pub enum LoginError {
+    #[error("Authentication failed.")]
+    AuthError(#[source] anyhow::Error),
+    #[error("Something went wrong.")]
+    UnexpectedError(#[source] anyhow::Error),
+}
+
+impl std::fmt::Debug for LoginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for LoginError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        let message = match self {
+            LoginError::AuthError(_) => "login-failed",
+            LoginError::UnexpectedError(_) => "unexpected-error",
+        };
+        FlashMessage::error(Message::new(message)).send();
+        HttpResponse::SeeOther()
+            .insert_header((LOCATION, "/login"))
+            .finish()
+    }
+
+    /// Every error is reported with a flash message on the login page.
+    fn status_code(&self) -> StatusCode {
+        StatusCode::SEE_OTHER
     }
 }
@@ -0,0 +1,13 @@
+use actix_web::{http::header::LOCATION, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+
+use crate::{i18n::Message, session_state::TypedSession};
+
+#[tracing::instrument(name = "Logout", skip(session))]
+pub async fn logout(session: TypedSession) -> HttpResponse {
+    session.log_out();
+    FlashMessage::info(Message::new("logged-out")).send();
+    HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/login"))
+        .finish()
+}
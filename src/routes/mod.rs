@@ -1,11 +1,21 @@
+pub mod admin;
+pub mod api;
+mod assets;
 pub mod call_request;
+pub mod call_request_status;
 mod healthcheck;
 mod home;
+mod language;
 pub mod login;
+mod logout;
+pub mod topics;
 
+pub use assets::*;
 pub use call_request::*;
 pub use healthcheck::*;
 pub use home::*;
+pub use language::*;
+pub use logout::*;
 
 pub fn error_chain_fmt(
     e: &impl std::error::Error,
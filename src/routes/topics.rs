@@ -0,0 +1,29 @@
+//! # Topic queries
+//! Reads of the topic catalog shared by the citizen form, the API and the
+//! admin pages.
+
+use sqlx::PgPool;
+
+use crate::domain::topic::Topic;
+
+/// Every topic, retired ones included, by name.
+#[tracing::instrument(name = "Get topics", skip(pool))]
+pub async fn get_topics(pool: &PgPool) -> Result<Vec<Topic>, sqlx::Error> {
+    sqlx::query_as!(
+        Topic,
+        "SELECT id, name, team, active FROM topics ORDER BY lower(name)"
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Topics offered to citizens, by name.
+#[tracing::instrument(name = "Get active topics", skip(pool))]
+pub async fn get_active_topics(pool: &PgPool) -> Result<Vec<Topic>, sqlx::Error> {
+    sqlx::query_as!(
+        Topic,
+        "SELECT id, name, team, active FROM topics WHERE active ORDER BY lower(name)"
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,154 @@
+//! # Security headers
+//! Every response tells browsers to keep our pages out of frames, not to
+//! guess content types and to send only the origin as referrer. Over TLS it
+//! also pins HTTPS with HSTS. `X-Forwarded-Proto` only counts when
+//! `trust_forwarded_proto` is set, anyone could send it otherwise.
+//!
+//! The Content Security Policy only runs scripts served by us or carrying the
+//! nonce of the request, which templates put on their `<script>` tags (see
+//! `templates/common.html`). An injected script cannot know it.
+
+use std::future::{ready, Ready};
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::ErrorInternalServerError,
+    http::{
+        header::{
+            HeaderName, HeaderValue, CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY,
+            REFERRER_POLICY, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+        },
+        StatusCode,
+    },
+    middleware::Next,
+    web, FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError,
+};
+use rand::{distributions::Alphanumeric, Rng};
+
+use crate::configuration::SecurityHeadersConfiguration;
+
+/// Nonce allowing the scripts of the current response.
+#[derive(Clone, Debug)]
+pub struct CspNonce(String);
+
+impl std::fmt::Display for CspNonce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for CspNonce {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<CspNonce>()
+                .cloned()
+                .ok_or_else(|| ErrorInternalServerError("Security headers are not enabled.")),
+        )
+    }
+}
+
+/// Adds the security headers to every response, error ones included.
+pub async fn set_security_headers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let configuration = req
+        .app_data::<web::Data<SecurityHeadersConfiguration>>()
+        .expect("The security headers are not configured.")
+        .clone();
+    let over_tls = if configuration.trust_forwarded_proto {
+        req.connection_info().scheme() == "https"
+    } else {
+        req.app_config().secure()
+    };
+    let nonce: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(24)
+        .map(char::from)
+        .collect();
+    req.extensions_mut().insert(CspNonce(nonce.clone()));
+    let headers = security_headers(&configuration, &nonce, over_tls)?;
+
+    match next.call(req).await {
+        Ok(mut response) => {
+            for (name, value) in headers {
+                response.headers_mut().insert(name, value);
+            }
+            Ok(response)
+        }
+        // The request is gone, the headers go on the response of the error.
+        Err(error) => Err(WithSecurityHeaders { error, headers }.into()),
+    }
+}
+
+fn security_headers(
+    configuration: &SecurityHeadersConfiguration,
+    nonce: &str,
+    over_tls: bool,
+) -> Result<Vec<(HeaderName, HeaderValue)>, actix_web::Error> {
+    let mut headers: Vec<(HeaderName, String)> = vec![
+        (X_CONTENT_TYPE_OPTIONS, "nosniff".into()),
+        (X_FRAME_OPTIONS, configuration.frame_options.clone()),
+        (REFERRER_POLICY, configuration.referrer_policy.clone()),
+    ];
+    let csp_header = if configuration.csp_report_only {
+        CONTENT_SECURITY_POLICY_REPORT_ONLY
+    } else {
+        CONTENT_SECURITY_POLICY
+    };
+    headers.push((csp_header, content_security_policy(nonce)));
+    if let (true, Some(max_age)) = (over_tls, configuration.hsts_max_age_seconds) {
+        headers.push((
+            STRICT_TRANSPORT_SECURITY,
+            format!("max-age={max_age}; includeSubDomains"),
+        ));
+    }
+    headers
+        .into_iter()
+        .map(|(name, value)| {
+            let value = HeaderValue::try_from(value).map_err(ErrorInternalServerError)?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// Error of an inner service, whose response gets the security headers.
+#[derive(Debug)]
+struct WithSecurityHeaders {
+    error: actix_web::Error,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl std::fmt::Display for WithSecurityHeaders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl ResponseError for WithSecurityHeaders {
+    fn status_code(&self) -> StatusCode {
+        self.error.as_response_error().status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse<BoxBody> {
+        let mut response = self.error.error_response();
+        for (name, value) in &self.headers {
+            response.headers_mut().insert(name.clone(), value.clone());
+        }
+        response
+    }
+}
+
+/// Policy allowing only our own resources and the scripts carrying `nonce`.
+fn content_security_policy(nonce: &str) -> String {
+    format!(
+        "default-src 'self'; script-src 'self' 'nonce-{nonce}'; \
+         style-src 'self'; img-src 'self' data:; object-src 'none'; \
+         base-uri 'none'; form-action 'self'; frame-ancestors 'none'"
+    )
+}
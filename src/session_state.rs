@@ -0,0 +1,61 @@
+//! # Session state
+//! Typed access to the data kept in the session: the authenticated user, or
+//! the last call request of a citizen.
+
+use std::future::{ready, Ready};
+
+use actix_session::{Session, SessionExt, SessionGetError, SessionInsertError};
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use sqlx::types::Uuid;
+
+use crate::domain::reference_code::ReferenceCode;
+
+pub struct TypedSession(Session);
+
+impl TypedSession {
+    const USER_ID_KEY: &'static str = "user_id";
+    const CALL_REQUEST_REFERENCE_KEY: &'static str = "call_request_reference";
+
+    /// Rotates the session key, to be called when the privilege level changes.
+    pub fn renew(&self) {
+        self.0.renew();
+    }
+
+    pub fn insert_user_id(&self, user_id: Uuid) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::USER_ID_KEY, user_id)
+    }
+
+    pub fn get_user_id(&self) -> Result<Option<Uuid>, SessionGetError> {
+        self.0.get(Self::USER_ID_KEY)
+    }
+
+    pub fn insert_call_request_reference(
+        &self,
+        reference: &ReferenceCode,
+    ) -> Result<(), SessionInsertError> {
+        self.0
+            .insert(Self::CALL_REQUEST_REFERENCE_KEY, reference.as_ref())
+    }
+
+    /// Reference code of the last call request submitted from the browser.
+    pub fn get_call_request_reference(&self) -> Result<Option<ReferenceCode>, SessionGetError> {
+        Ok(self
+            .0
+            .get::<String>(Self::CALL_REQUEST_REFERENCE_KEY)?
+            .map(ReferenceCode::restore))
+    }
+
+    /// Removes the session state both from the client and the store.
+    pub fn log_out(self) {
+        self.0.purge()
+    }
+}
+
+impl FromRequest for TypedSession {
+    type Error = <Session as FromRequest>::Error;
+    type Future = Ready<Result<TypedSession, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(TypedSession(req.get_session())))
+    }
+}
@@ -0,0 +1,160 @@
+//! # Session store
+//! Session state is kept in Redis, or in memory when Redis is not available
+//! (e.g. while testing). The backend is chosen through the configuration.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Instant,
+};
+
+use actix_session::storage::{
+    generate_session_key, LoadError, RedisSessionStore, SaveError, SessionKey, SessionStore,
+    UpdateError,
+};
+use actix_web::cookie::time::Duration;
+use secrecy::{ExposeSecret, Secret};
+
+use crate::configuration::SessionStoreKind;
+
+type SessionState = HashMap<String, String>;
+
+/// Session store selected at startup.
+#[derive(Clone)]
+pub enum SessionStorage {
+    Redis(RedisSessionStore),
+    Memory(InMemorySessionStore),
+}
+
+impl SessionStorage {
+    pub async fn build(
+        kind: SessionStoreKind,
+        redis_uri: &Secret<String>,
+    ) -> Result<Self, anyhow::Error> {
+        match kind {
+            SessionStoreKind::Redis => Ok(Self::Redis(
+                RedisSessionStore::new(redis_uri.expose_secret()).await?,
+            )),
+            SessionStoreKind::Memory => Ok(Self::Memory(InMemorySessionStore::default())),
+        }
+    }
+}
+
+impl SessionStore for SessionStorage {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        match self {
+            Self::Redis(store) => store.load(session_key).await,
+            Self::Memory(store) => store.load(session_key).await,
+        }
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        match self {
+            Self::Redis(store) => store.save(session_state, ttl).await,
+            Self::Memory(store) => store.save(session_state, ttl).await,
+        }
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        match self {
+            Self::Redis(store) => store.update(session_key, session_state, ttl).await,
+            Self::Memory(store) => store.update(session_key, session_state, ttl).await,
+        }
+    }
+
+    async fn update_ttl(&self, session_key: &SessionKey, ttl: &Duration) -> anyhow::Result<()> {
+        match self {
+            Self::Redis(store) => store.update_ttl(session_key, ttl).await,
+            Self::Memory(store) => store.update_ttl(session_key, ttl).await,
+        }
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> anyhow::Result<()> {
+        match self {
+            Self::Redis(store) => store.delete(session_key).await,
+            Self::Memory(store) => store.delete(session_key).await,
+        }
+    }
+}
+
+/// Process-local session store, sessions are lost on restart and are not
+/// shared between replicas.
+#[derive(Clone, Default)]
+pub struct InMemorySessionStore {
+    sessions: Arc<RwLock<HashMap<String, (SessionState, Instant)>>>,
+}
+
+impl InMemorySessionStore {
+    fn expiration(ttl: &Duration) -> Instant {
+        Instant::now() + ttl.unsigned_abs()
+    }
+}
+
+impl SessionStore for InMemorySessionStore {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        let sessions = self.sessions.read().unwrap();
+        Ok(sessions
+            .get(session_key.as_ref())
+            .filter(|(_, expires_at)| *expires_at > Instant::now())
+            .map(|(state, _)| state.clone()))
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let session_key = generate_session_key();
+        self.sessions.write().unwrap().insert(
+            session_key.as_ref().to_owned(),
+            (session_state, Self::expiration(ttl)),
+        );
+        Ok(session_key)
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let mut sessions = self.sessions.write().unwrap();
+        match sessions.get_mut(session_key.as_ref()) {
+            Some(entry) => {
+                *entry = (session_state, Self::expiration(ttl));
+                Ok(session_key)
+            }
+            None => {
+                // The session expired in the meantime, a new one is created.
+                let new_key = generate_session_key();
+                sessions.insert(
+                    new_key.as_ref().to_owned(),
+                    (session_state, Self::expiration(ttl)),
+                );
+                Ok(new_key)
+            }
+        }
+    }
+
+    async fn update_ttl(&self, session_key: &SessionKey, ttl: &Duration) -> anyhow::Result<()> {
+        if let Some((_, expires_at)) = self.sessions.write().unwrap().get_mut(session_key.as_ref())
+        {
+            *expires_at = Self::expiration(ttl);
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> anyhow::Result<()> {
+        self.sessions.write().unwrap().remove(session_key.as_ref());
+        Ok(())
+    }
+}
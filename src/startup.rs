@@ -1,75 +1,239 @@
-use std::net::TcpListener;
+use std::{net::TcpListener, time::Duration};
 
-use actix_web::{cookie::Key, dev::Server, web, App, HttpServer};
+use actix_session::SessionMiddleware;
+use actix_web::{cookie::Key, dev::Server, middleware::from_fn, web, App, HttpServer};
 
 use actix_web_flash_messages::{storage::CookieMessageStore, FlashMessagesFramework};
-use secrecy::{ExposeSecret, Secret};
+use secrecy::ExposeSecret;
 use sqlx::{postgres::PgPoolOptions, PgPool, Pool, Postgres};
 use tracing_actix_web::TracingLogger;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
-    configuration::{Configuration, DatabaseConfiguration},
-    routes::{call_request, healthcheck, home, login},
+    authentication::{reject_anonymous_users, require_role},
+    configuration::{
+        ApplicationConfiguration, Configuration, DatabaseConfiguration, SessionStoreKind,
+    },
+    csrf::{protect_from_csrf, CsrfKey},
+    domain::{reference_code::ReferenceCodeKey, user::UserRole},
+    i18n::DefaultLocale,
+    metrics::{self, record_http_metrics, Metrics},
+    migration::{prepare_database, MigrationError},
+    rate_limit::RateLimiter,
+    routes::{
+        admin, api,
+        call_request::{self, DuplicateWindow},
+        call_request_status, healthcheck, home, login, logout, readiness, static_asset,
+        switch_language, DependencyProbes,
+    },
+    security_headers::set_security_headers,
+    session_store::SessionStorage,
 };
 
 pub struct Application {
     port: u16,
     server: Server,
+    /// Admin server exposing the metrics.
+    metrics_port: u16,
+    metrics_server: Server,
 }
 
 impl Application {
     #[tracing::instrument(name = "Building application from configuration")]
-    pub async fn build(configuration: Configuration) -> Result<Self, std::io::Error> {
+    pub async fn build(configuration: Configuration) -> Result<Self, anyhow::Error> {
         let address = format!(
             "{}:{}",
             configuration.application.host, configuration.application.port
         );
         let listener = TcpListener::bind(address)?;
         let port = listener.local_addr()?.port();
+        let db_pool = make_database_pool(&configuration.database);
+        if configuration.application.migrate_on_startup {
+            prepare_database(&db_pool, true).await?;
+        } else {
+            // The pool is lazy: the server starts without the database, whose
+            // schema is checked once reachable.
+            match check_schema(&db_pool).await {
+                Err(e) if e.is_unreachable() => {
+                    tracing::warn!(error = %e, "The database is unreachable, its schema will be checked later.");
+                    tokio::spawn(check_schema_until_reachable(db_pool.clone()));
+                }
+                result => result?,
+            }
+        }
+        let session_storage = SessionStorage::build(
+            configuration.application.session_store,
+            &configuration.redis_uri,
+        )
+        .await?;
+
+        let probes = DependencyProbes {
+            redis: match configuration.application.session_store {
+                SessionStoreKind::Redis => Some(redis::Client::open(
+                    configuration.redis_uri.expose_secret().as_str(),
+                )?),
+                SessionStoreKind::Memory => None,
+            },
+            timeout: Duration::from_millis(
+                configuration.application.readiness_timeout_milliseconds,
+            ),
+        };
+
+        let rate_limiter = RateLimiter::build(
+            &configuration.application.rate_limit,
+            configuration.application.session_store,
+            &configuration.redis_uri,
+        )
+        .await?;
+
+        let metrics = Metrics::new()?;
+        let metrics_listener = TcpListener::bind(format!(
+            "{}:{}",
+            configuration.application.host, configuration.application.metrics_port
+        ))?;
+        let metrics_port = metrics_listener.local_addr()?.port();
+        let metrics_server = run_metrics(metrics_listener, metrics.clone(), db_pool.clone())?;
 
         let server = run(
             listener,
-            configuration.application.base_url,
-            make_database_pool(&configuration.database),
-            configuration.application.hmac_secret,
+            configuration.application,
+            db_pool,
+            session_storage,
+            probes,
+            metrics,
+            rate_limiter,
         )
         .await?;
 
-        Ok(Self { port, server })
+        Ok(Self {
+            port,
+            server,
+            metrics_port,
+            metrics_server,
+        })
     }
 
     pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
-        self.server.await
+        tokio::try_join!(self.server, self.metrics_server)?;
+        Ok(())
     }
 
     pub fn port(&self) -> u16 {
         self.port
     }
+
+    /// Port of the admin server exposing the metrics.
+    pub fn metrics_port(&self) -> u16 {
+        self.metrics_port
+    }
 }
 
 pub struct ApplicationBaseUrl(pub String);
 
 async fn run(
     listener: TcpListener,
-    base_url: String,
+    configuration: ApplicationConfiguration,
     db_pool: PgPool,
-    hmac_secret: Secret<String>,
+    session_storage: SessionStorage,
+    probes: DependencyProbes,
+    metrics: Metrics,
+    rate_limiter: RateLimiter,
 ) -> Result<Server, std::io::Error> {
+    let base_url = configuration.base_url;
     let db_pool = web::Data::new(db_pool);
-    let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
+    let probes = web::Data::new(probes);
+    let metrics = web::Data::new(metrics);
+    let rate_limiter = web::Data::new(rate_limiter);
+    let duplicate_window = web::Data::new(DuplicateWindow(chrono::Duration::minutes(
+        configuration.duplicate_window_minutes.into(),
+    )));
+    let secret_key = Key::from(configuration.hmac_secret.expose_secret().as_bytes());
     let message_backend = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_backend).build();
+    let openapi = api::ApiDoc::openapi();
+    let csrf_key = web::Data::new(CsrfKey(secret_key.clone()));
+    let security_headers = web::Data::new(configuration.security_headers);
+    let default_locale = web::Data::new(DefaultLocale(configuration.default_locale));
+    let office_hours = web::Data::new(configuration.office_hours);
+    let reference_key = web::Data::new(ReferenceCodeKey::new(configuration.hmac_secret));
     let server = HttpServer::new(move || {
         App::new()
+            // Rejections are flashed, so the framework must wrap the check.
+            .wrap(from_fn(protect_from_csrf))
             .wrap(message_framework.clone())
+            .wrap(SessionMiddleware::new(
+                session_storage.clone(),
+                secret_key.clone(),
+            ))
             .wrap(TracingLogger::default())
+            .wrap(from_fn(set_security_headers))
+            .wrap(from_fn(record_http_metrics))
             .app_data(base_url.clone())
             .app_data(db_pool.clone())
+            .app_data(probes.clone())
+            .app_data(metrics.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(csrf_key.clone())
+            .app_data(security_headers.clone())
+            .app_data(default_locale.clone())
+            .app_data(duplicate_window.clone())
+            .app_data(office_hours.clone())
+            .app_data(reference_key.clone())
             .route("/", web::get().to(home))
+            .route("/static/{path:.*}", web::get().to(static_asset))
             .route("/healthcheck", web::get().to(healthcheck))
+            .route("/ready", web::get().to(readiness))
             .route("/call_request", web::get().to(call_request::get))
             .route("/call_request", web::post().to(call_request::post))
+            .route(
+                "/call_request/confirmation",
+                web::get().to(call_request_status::confirmation),
+            )
+            .route(
+                "/call_request/status",
+                web::get().to(call_request_status::get),
+            )
+            .route(
+                "/call_request/status",
+                web::post().to(call_request_status::post),
+            )
             .route("/login", web::get().to(login::get))
+            .route("/login", web::post().to(login::post))
+            .route("/logout", web::post().to(logout))
+            .route("/language", web::post().to(switch_language))
+            .service(
+                web::scope("/admin")
+                    .wrap(from_fn(reject_anonymous_users))
+                    .route(
+                        "/call_requests",
+                        web::get().to(admin::call_requests::dashboard),
+                    )
+                    .route(
+                        "/call_requests/{call_request_id}/transition",
+                        web::post().to(admin::call_requests::transition),
+                    )
+                    .route("/api_tokens", web::post().to(admin::api_tokens::create))
+                    .service(
+                        web::scope("/topics")
+                            .wrap(from_fn(|req, next| {
+                                require_role(UserRole::Supervisor, req, next)
+                            }))
+                            .route("", web::get().to(admin::topics::list))
+                            .route("", web::post().to(admin::topics::create))
+                            .route("/{topic_id}", web::post().to(admin::topics::update)),
+                    )
+                    .service(
+                        web::scope("/users")
+                            .wrap(from_fn(|req, next| {
+                                require_role(UserRole::Administrator, req, next)
+                            }))
+                            .route("", web::get().to(admin::users::list))
+                            .route("/{user_id}/role", web::post().to(admin::users::update_role)),
+                    ),
+            )
+            .service(SwaggerUi::new("/api/docs/{_:.*}").url("/api/openapi.json", openapi.clone()))
+            .service(api::scope())
     })
     .listen(listener)?
     .run();
@@ -77,6 +241,64 @@ async fn run(
     Ok(server)
 }
 
+/// Admin server, only exposing the metrics. It is kept off the application
+/// port, which is public.
+fn run_metrics(
+    listener: TcpListener,
+    metrics: Metrics,
+    db_pool: PgPool,
+) -> Result<Server, std::io::Error> {
+    let db_pool = web::Data::new(db_pool);
+    let metrics = web::Data::new(metrics);
+    let server = HttpServer::new(move || {
+        App::new()
+            .wrap(TracingLogger::default())
+            .app_data(db_pool.clone())
+            .app_data(metrics.clone())
+            .route("/metrics", web::get().to(metrics::metrics))
+    })
+    .listen(listener)?
+    .run();
+
+    Ok(server)
+}
+
+/// Time allowed to the schema check on startup, after which the database
+/// counts as unreachable.
+const SCHEMA_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest wait between two schema checks of an unreachable database.
+const SCHEMA_CHECK_MAX_INTERVAL: Duration = Duration::from_secs(60);
+
+async fn check_schema(db_pool: &PgPool) -> Result<(), MigrationError> {
+    tokio::time::timeout(SCHEMA_CHECK_TIMEOUT, prepare_database(db_pool, false))
+        .await
+        .unwrap_or(Err(MigrationError::DatabaseError(
+            sqlx::Error::PoolTimedOut,
+        )))
+}
+
+/// Checks the schema again, backing off, until the database answers.
+async fn check_schema_until_reachable(db_pool: PgPool) {
+    let mut interval = Duration::from_secs(1);
+    loop {
+        tokio::time::sleep(interval).await;
+        match check_schema(&db_pool).await {
+            Ok(()) => {
+                tracing::info!("The database is reachable, its schema is supported.");
+                return;
+            }
+            Err(e) if e.is_unreachable() => {
+                tracing::debug!(error = %e, "The database is still unreachable.");
+                interval = (interval * 2).min(SCHEMA_CHECK_MAX_INTERVAL);
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "The database schema is not supported.");
+                return;
+            }
+        }
+    }
+}
+
 pub fn make_database_pool(configuration: &DatabaseConfiguration) -> Pool<Postgres> {
     PgPoolOptions::new().connect_lazy_with(configuration.with_db())
 }
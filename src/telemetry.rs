@@ -1,29 +1,96 @@
+use opentelemetry::{global, propagation::Injector, trace::TracerProvider as _};
+use opentelemetry_otlp::{Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use tokio::task::JoinHandle;
 use tracing::subscriber::{set_global_default, Subscriber};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::{EnvFilter, Registry};
 
+use crate::configuration::TelemetryConfiguration;
+
+/// Composes the bunyan logs with, when a tracer provider is given, the export
+/// of the spans to OpenTelemetry.
 pub fn get_subscriber<Sink>(
     name: String,
     filter_log_level: String,
     sink: Sink,
+    tracer_provider: Option<&SdkTracerProvider>,
 ) -> impl Subscriber + Send + Sync
 where
     Sink: for<'a> MakeWriter<'a> + Send + Sync + 'static,
 {
     let env_filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(filter_log_level));
+    let otel_layer = tracer_provider
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer(name.clone())));
     let formatting_layer = BunyanFormattingLayer::new(name, sink);
 
     Registry::default()
         .with(env_filter)
+        .with(otel_layer)
         .with(JsonStorageLayer)
         .with(formatting_layer)
 }
 
+/// Builds the provider exporting spans to the configured OTLP collector, if
+/// any. W3C trace context propagation is enabled either way, so that
+/// `traceparent` headers are honoured and forwarded.
+///
+/// The provider must be shut down before exiting to flush pending spans.
+pub fn init_tracer_provider(
+    service_name: &str,
+    configuration: &TelemetryConfiguration,
+) -> Result<Option<SdkTracerProvider>, anyhow::Error> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let Some(endpoint) = &configuration.otlp_endpoint else {
+        return Ok(None);
+    };
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_protocol(Protocol::HttpJson)
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_owned())
+                .build(),
+        )
+        .build();
+    Ok(Some(provider))
+}
+
+/// Adds the trace context of the current span to an outgoing request, so that
+/// the service called takes part in the same trace.
+pub fn propagate_trace_context(request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    let context = tracing::Span::current().context();
+    let mut headers = HeaderMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(&mut headers))
+    });
+    request.headers(headers)
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
 pub fn init_subscriber(subscriber: impl Subscriber + Send + Sync) {
     LogTracer::init().expect("Failed to set logger");
     set_global_default(subscriber).expect("Failed to set subscriber");
@@ -0,0 +1,280 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+use bubble_services::domain::user::UserRole;
+
+use crate::helpers::{assert_is_redirect_to, TestApp};
+
+/// Runs the binary against the database of the test application.
+fn run_cli(app: &TestApp, args: &[&str], stdin: Option<&str>) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bubble-services"))
+        .args(args)
+        .env("BUBBLE_DATABASE__DATABASE_NAME", &app.database_name)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run the binary.");
+    if let Some(input) = stdin {
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+    }
+    child.wait_with_output().expect("Failed to run the binary.")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+/// Extracts the password printed by the user commands.
+fn printed_password(output: &Output) -> String {
+    stdout(output)
+        .lines()
+        .find_map(|line| line.strip_prefix("Password: "))
+        .expect("No password was printed.")
+        .to_owned()
+}
+
+async fn login_as(app: &TestApp, username: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": username,
+        "password": password,
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn created_users_can_log_in() {
+    let app = TestApp::spawn().await;
+
+    let output = run_cli(
+        &app,
+        &["user", "create", "first-admin", "--role", "administrator"],
+        None,
+    );
+    assert!(output.status.success(), "{:?}", output);
+
+    let password = printed_password(&output);
+    let response = login_as(&app, "first-admin", &password).await;
+    assert_is_redirect_to(&response, "/admin/call_requests");
+    let response = app.get_users_page().await;
+    assert!(response.status().is_success());
+}
+
+#[tokio::test]
+async fn passwords_can_be_read_from_stdin() {
+    let app = TestApp::spawn().await;
+
+    let output = run_cli(
+        &app,
+        &["user", "create", "operator", "--password-stdin"],
+        Some("a chosen password\n"),
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!stdout(&output).contains("Password:"));
+
+    let response = login_as(&app, "operator", "a chosen password").await;
+    assert_is_redirect_to(&response, "/admin/call_requests");
+    let role =
+        sqlx::query!(r#"SELECT role AS "role: UserRole" FROM users WHERE username = 'operator'"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .role;
+    assert_eq!(role, UserRole::OfficeWorker);
+}
+
+#[tokio::test]
+async fn usernames_are_unique() {
+    let app = TestApp::spawn().await;
+
+    let output = run_cli(&app, &["user", "create", &app.test_user.username], None);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+}
+
+#[tokio::test]
+async fn disabled_users_are_locked_out() {
+    let app = TestApp::spawn().await;
+    let token = app.api_token().await;
+    app.login().await;
+
+    let output = run_cli(&app, &["user", "disable", &app.test_user.username], None);
+    assert!(output.status.success(), "{:?}", output);
+
+    // The open session is not valid anymore.
+    let response = app.get_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+    // Nor are the credentials and the API tokens.
+    let response = app.login().await;
+    assert_is_redirect_to(&response, "/login");
+    let response = app.get_api("/api/v1/call_requests", Some(&token)).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn reset_passwords_replace_the_old_ones() {
+    let app = TestApp::spawn().await;
+
+    let output = run_cli(
+        &app,
+        &["user", "reset-password", &app.test_user.username],
+        None,
+    );
+    assert!(output.status.success(), "{:?}", output);
+
+    let response = app.login().await;
+    assert_is_redirect_to(&response, "/login");
+    let password = printed_password(&output);
+    let response = login_as(&app, &app.test_user.username, &password).await;
+    assert_is_redirect_to(&response, "/admin/call_requests");
+}
+
+#[tokio::test]
+async fn unknown_users_are_reported() {
+    let app = TestApp::spawn().await;
+
+    for args in [
+        ["user", "disable", "nobody"],
+        ["user", "reset-password", "nobody"],
+    ] {
+        let output = run_cli(&app, &args, None);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("There is no user named nobody."));
+    }
+}
+
+#[tokio::test]
+async fn call_requests_are_exported_as_csv() {
+    let app = TestApp::spawn().await;
+    for (contact_name, phone_number) in [
+        ("Rossi, Mario", "320 406 7090"),
+        ("Anna Bianchi", "321 456 7891"),
+    ] {
+        app.post_call_request(&serde_json::json!({
+            "phone_number": phone_number,
+            "contact_name": contact_name,
+        }))
+        .await;
+    }
+
+    let output = run_cli(&app, &["call-requests", "export"], None);
+    assert!(output.status.success(), "{:?}", output);
+
+    let mut reader = csv::Reader::from_reader(output.stdout.as_slice());
+    assert_eq!(
+        reader.headers().unwrap(),
+        vec![
+            "id",
+            "contact_name",
+            "phone_number",
+            "status",
+            "assigned_to",
+            "created_at"
+        ]
+    );
+    let records: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(&records[0][1], "Rossi, Mario");
+    assert_eq!(&records[0][2], "+393204067090");
+    assert_eq!(&records[0][3], "pending");
+    assert_eq!(&records[1][1], "Anna Bianchi");
+
+    let output = run_cli(
+        &app,
+        &["call-requests", "export", "--status", "completed"],
+        None,
+    );
+    let mut reader = csv::Reader::from_reader(output.stdout.as_slice());
+    assert_eq!(reader.records().count(), 0);
+}
+
+#[tokio::test]
+async fn exported_names_are_not_read_as_formulas() {
+    let app = TestApp::spawn().await;
+    for (contact_name, phone_number) in [
+        ("=1+2", "320 406 7090"),
+        ("@SUM", "321 456 7891"),
+        ("-Rino", "322 456 7891"),
+        ("Rino-Pape", "323 456 7891"),
+    ] {
+        app.post_call_request(&serde_json::json!({
+            "phone_number": phone_number,
+            "contact_name": contact_name,
+        }))
+        .await;
+    }
+
+    let output = run_cli(&app, &["call-requests", "export"], None);
+    assert!(output.status.success(), "{:?}", output);
+
+    let mut reader = csv::Reader::from_reader(output.stdout.as_slice());
+    let names: Vec<String> = reader
+        .records()
+        .map(|record| record.unwrap()[1].to_owned())
+        .collect();
+    assert_eq!(names, ["'=1+2", "'@SUM", "'-Rino", "Rino-Pape"]);
+}
+
+#[tokio::test]
+async fn migrations_can_be_applied_again() {
+    let app = TestApp::spawn().await;
+
+    let output = run_cli(&app, &["migrate"], None);
+
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[tokio::test]
+async fn migrating_normalizes_stored_phone_numbers() {
+    let app = TestApp::spawn().await;
+    for phone_number in ["321 456 7891", "+39 (320) 406.7090", "not a number"] {
+        sqlx::query!(
+            r#"
+            INSERT INTO call_requests (id, user_name, phone_number, created_at, last_requested_at)
+            VALUES (gen_random_uuid(), 'Rino Pape', $1, now(), now())
+            "#,
+            phone_number,
+        )
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    }
+
+    let output = run_cli(&app, &["migrate"], None);
+
+    assert!(output.status.success(), "{:?}", output);
+    let phone_numbers =
+        sqlx::query_scalar!("SELECT phone_number FROM call_requests ORDER BY phone_number")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        phone_numbers,
+        ["+393204067090", "+393214567891", "not a number"]
+    );
+}
+
+#[tokio::test]
+async fn configuration_check_reaches_the_database() {
+    let app = TestApp::spawn().await;
+
+    let output = run_cli(&app, &["config", "check"], None);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout(&output).contains("The configuration is valid."));
+    assert!(stdout(&output).contains(&app.database_name));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bubble-services"))
+        .args(["config", "check"])
+        .env("BUBBLE_DATABASE__DATABASE_NAME", "missing_database")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,113 @@
+use std::process::Command;
+
+use bubble_services::configuration::{load_configuration, Configuration, Environment};
+use secrecy::Secret;
+
+/// A local configuration with values acceptable in production.
+fn secure_configuration() -> Configuration {
+    let mut configuration =
+        load_configuration(Environment::Local).expect("Failed to load configuration.");
+    configuration.application.hmac_secret = Secret::new("s".repeat(64));
+    configuration.database.password = Secret::new("a-real-password".into());
+    configuration.database.require_ssl = true;
+    configuration
+}
+
+fn assert_rejects(configuration: &Configuration, key: &str) {
+    let error = configuration
+        .validate(Environment::Production)
+        .expect_err("An insecure configuration was accepted.");
+    assert!(
+        error.to_string().contains(&format!("`{}`", key)),
+        "{} does not name {}",
+        error,
+        key
+    );
+}
+
+#[test]
+fn local_configuration_is_loaded() {
+    let configuration = load_configuration(Environment::Local).unwrap();
+
+    assert_eq!(configuration.environment, Environment::Local);
+    assert_eq!(configuration.application.host, "127.0.0.1");
+    assert!(!configuration.database.require_ssl);
+}
+
+#[test]
+fn environments_are_parsed_case_insensitively() {
+    assert_eq!(
+        Environment::try_from("Production".to_string()).unwrap(),
+        Environment::Production
+    );
+    let error = Environment::try_from("staging".to_string()).unwrap_err();
+    assert!(error.to_string().contains("`staging`"));
+}
+
+#[test]
+fn production_rejects_the_sample_configuration() {
+    let error = load_configuration(Environment::Production).unwrap_err();
+
+    assert!(error.to_string().contains("`application.hmac_secret`"));
+}
+
+#[test]
+fn production_rejects_insecure_values() {
+    secure_configuration()
+        .validate(Environment::Production)
+        .expect("A secure configuration was rejected.");
+
+    let mut configuration = secure_configuration();
+    configuration.application.hmac_secret = load_configuration(Environment::Local)
+        .unwrap()
+        .application
+        .hmac_secret;
+    assert_rejects(&configuration, "application.hmac_secret");
+
+    let mut configuration = secure_configuration();
+    configuration.database.password = Secret::new("password".into());
+    assert_rejects(&configuration, "database.password");
+
+    let mut configuration = secure_configuration();
+    configuration.database.require_ssl = false;
+    assert_rejects(&configuration, "database.require_ssl");
+}
+
+#[test]
+fn short_secrets_are_rejected_everywhere() {
+    let mut configuration = secure_configuration();
+    configuration.application.hmac_secret = Secret::new("too-short".into());
+
+    for environment in [Environment::Local, Environment::Production] {
+        let error = configuration.validate(environment).unwrap_err();
+        assert!(error.to_string().contains("`application.hmac_secret`"));
+    }
+}
+
+#[test]
+fn empty_rate_limit_windows_are_rejected_everywhere() {
+    let mut configuration = secure_configuration();
+    configuration.application.rate_limit.window_seconds = 0;
+
+    for environment in [Environment::Local, Environment::Production] {
+        let error = configuration.validate(environment).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("`application.rate_limit.window_seconds`"));
+    }
+}
+
+#[test]
+fn the_binary_refuses_insecure_production_configurations() {
+    let output = Command::new(env!("CARGO_BIN_EXE_bubble-services"))
+        .args(["config", "check"])
+        .env("APP_ENVIRONMENT", "production")
+        .env("BUBBLE_APPLICATION__HMAC_SECRET", "s".repeat(64))
+        .env("BUBBLE_DATABASE__PASSWORD", "a-real-password")
+        .env("BUBBLE_DATABASE__REQUIRE_SSL", "false")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`database.require_ssl`"));
+}
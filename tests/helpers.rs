@@ -1,15 +1,26 @@
 use bubble_services::{
-    configuration::{get_configuration, DatabaseConfiguration},
+    authentication::{compute_password_hash, issue_api_token},
+    configuration::{get_configuration, Configuration, DatabaseConfiguration, SessionStoreKind},
+    domain::{
+        callback_slot::{CallbackSlot, DayPeriod, OfficeHours, TimeRange},
+        user::UserRole,
+    },
+    i18n::Locale,
+    migration::MIGRATOR,
     startup::{make_database_pool, Application},
     telemetry::{get_subscriber, init_subscriber},
 };
+use chrono::{NaiveTime, Weekday};
 use once_cell::sync::Lazy;
-use reqwest::{Response, Url};
+use reqwest::Response;
+use scraper::{Html, Selector};
+use secrecy::{ExposeSecret, Secret};
 use serde::Serialize;
 use sqlx::{types::Uuid, ConnectOptions, Connection, Executor, PgConnection, PgPool};
+use std::sync::Mutex;
 // Set's up telemetry once.
 static TRACING: Lazy<()> = Lazy::new(|| {
-    let subscriber = get_subscriber("test".into(), "debug".into(), std::io::stdout);
+    let subscriber = get_subscriber("test".into(), "debug".into(), std::io::stdout, None);
     init_subscriber(subscriber);
 });
 
@@ -22,14 +33,109 @@ pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
 /// Test deployment of the application.
 pub struct TestApp {
     pub address: String,
-    pub base_url: Url,
+    /// Address of the admin server exposing the metrics.
+    pub metrics_address: String,
+    pub database_name: String,
     pub db_pool: PgPool,
     pub http_client: reqwest::Client,
-    pub port: u16,
+    pub test_user: TestUser,
+    /// CSRF token of `http_client`, fetched on the first form submission.
+    csrf_token: Mutex<Option<String>>,
 }
 
-/// Creates a database according to the provided settings using the project's migrations.
-async fn configure_database(config: &DatabaseConfiguration) -> PgPool {
+/// Office worker account stored in the test database.
+pub struct TestUser {
+    pub user_id: Uuid,
+    pub username: String,
+    pub password: String,
+}
+
+impl TestUser {
+    pub fn generate() -> Self {
+        Self {
+            user_id: Uuid::new_v4(),
+            username: Uuid::new_v4().to_string(),
+            password: Uuid::new_v4().to_string(),
+        }
+    }
+
+    pub async fn store(&self, pool: &PgPool) {
+        let password_hash = compute_password_hash(Secret::new(self.password.clone()))
+            .expect("Failed to hash the test user password.");
+        sqlx::query!(
+            "INSERT INTO users (user_id, username, password_hash) VALUES ($1, $2, $3)",
+            self.user_id,
+            self.username,
+            password_hash.expose_secret(),
+        )
+        .execute(pool)
+        .await
+        .expect("Failed to store test user.");
+    }
+}
+
+/// Configuration of a test deployment, with its own database.
+pub fn test_configuration() -> Configuration {
+    Lazy::force(&TRACING);
+
+    let mut c = get_configuration().expect("Failed to load configuration.");
+    c.database.database_name = format!("bubble_services_test_{}", Uuid::new_v4());
+    c.application.port = 0; // Connect to a free port!
+    c.application.metrics_port = 0;
+
+    // Tests run against Redis only when explicitly requested.
+    if std::env::var("TEST_REDIS").is_err() {
+        c.application.session_store = SessionStoreKind::Memory;
+    }
+    // Every test submits from the same address, see `with_rate_limits`.
+    c.application.rate_limit.per_client_ip = u32::MAX;
+    c.application.rate_limit.per_phone_number = u32::MAX;
+    // Tests check the English texts, unless they ask for another language.
+    c.application.default_locale = Locale::English;
+    c
+}
+
+/// Applies rate limits to a test deployment. Counters are kept in memory, as
+/// Redis ones would be shared with the other tests.
+pub fn with_rate_limits(c: &mut Configuration, per_client_ip: u32, per_phone_number: u32) {
+    c.application.session_store = SessionStoreKind::Memory;
+    c.application.rate_limit.per_client_ip = per_client_ip;
+    c.application.rate_limit.per_phone_number = per_phone_number;
+}
+
+/// Office hours of a test deployment open all week, all day long, so that
+/// call requests are callable whenever the tests run.
+pub fn always_open() -> OfficeHours {
+    let time = |hour, minute, second| NaiveTime::from_hms_opt(hour, minute, second).unwrap();
+    let weekdays = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ];
+    OfficeHours::new(
+        chrono_tz::UTC,
+        TimeRange {
+            start: time(0, 0, 0),
+            end: time(12, 0, 0),
+        },
+        TimeRange {
+            start: time(12, 0, 0),
+            end: time(23, 59, 59),
+        },
+        weekdays
+            .into_iter()
+            .flat_map(|weekday| DayPeriod::ALL.map(|period| CallbackSlot::new(weekday, period)))
+            .collect(),
+    )
+    .unwrap()
+}
+
+/// Creates an empty database according to the provided settings.
+pub async fn create_database(config: &DatabaseConfiguration) -> PgPool {
     let connection_options = config
         .without_db()
         .log_statements(tracing_log::log::LevelFilter::Trace);
@@ -42,10 +148,15 @@ async fn configure_database(config: &DatabaseConfiguration) -> PgPool {
         .await
         .expect("Failed to create database.");
 
-    let connection_pool = PgPool::connect_with(config.with_db())
+    PgPool::connect_with(config.with_db())
         .await
-        .expect("Failed to connect to Postgres DB!");
-    sqlx::migrate!("./migrations")
+        .expect("Failed to connect to Postgres DB!")
+}
+
+/// Creates a database according to the provided settings using the project's migrations.
+async fn configure_database(config: &DatabaseConfiguration) -> PgPool {
+    let connection_pool = create_database(config).await;
+    MIGRATOR
         .run(&connection_pool)
         .await
         .expect("Failed to migrate the database");
@@ -56,25 +167,20 @@ async fn configure_database(config: &DatabaseConfiguration) -> PgPool {
 impl TestApp {
     /// Spawn the application for testing.
     pub async fn spawn() -> TestApp {
-        // Setting up telemetry
-        Lazy::force(&TRACING);
-
-        // Getting configuration.
-        let configuration = {
-            let mut c = get_configuration().expect("Failed to load configuration.");
-
-            c.database.database_name = format!("bubble_services_test_{}", Uuid::new_v4());
-            c.application.port = 0; // Connect to a free port!
-            c
-        };
+        Self::spawn_with(|_| {}).await
+    }
 
+    /// Spawn the application for testing, after customizing its configuration.
+    pub async fn spawn_with(customize: impl FnOnce(&mut Configuration)) -> TestApp {
+        let mut configuration = test_configuration();
+        customize(&mut configuration);
         configure_database(&configuration.database).await;
 
         let app = Application::build(configuration.clone())
             .await
             .expect("Failed to build the application server");
-        let application_port = app.port();
-        let address = format!("http://127.0.0.1:{}", application_port);
+        let address = format!("http://127.0.0.1:{}", app.port());
+        let metrics_address = format!("http://127.0.0.1:{}", app.metrics_port());
 
         // Spawn application.
         tokio::spawn(app.run_until_stopped());
@@ -85,13 +191,68 @@ impl TestApp {
             .build()
             .unwrap();
 
-        TestApp {
+        let test_app = TestApp {
             address,
+            metrics_address,
+            database_name: configuration.database.database_name.clone(),
             db_pool: make_database_pool(&configuration.database),
             http_client: client,
-            base_url: Url::parse(&configuration.application.base_url).unwrap(),
-            port: application_port,
+            test_user: TestUser::generate(),
+            csrf_token: Mutex::new(None),
+        };
+        test_app.test_user.store(&test_app.db_pool).await;
+        test_app
+    }
+
+    /// CSRF token that the forms of the test client carry.
+    pub async fn csrf_token(&self) -> String {
+        if let Some(token) = self.csrf_token.lock().unwrap().clone() {
+            return token;
         }
+        let page = Html::parse_document(&self.get_login_page().await.text().await.unwrap());
+        let token = page
+            .select(&Selector::parse("input[name='csrf_token']").unwrap())
+            .next()
+            .expect("The login form has no CSRF token.")
+            .attr("value")
+            .unwrap()
+            .to_owned();
+        *self.csrf_token.lock().unwrap() = Some(token.clone());
+        token
+    }
+
+    /// Adds the CSRF token to a form body, as browsers do.
+    pub async fn with_csrf_token(&self, body: &impl Serialize) -> serde_json::Value {
+        let mut body = serde_json::to_value(body).unwrap();
+        body["csrf_token"] = self.csrf_token().await.into();
+        body
+    }
+
+    pub async fn get_ready(&self) -> Response {
+        self.http_client
+            .get(format!("{}/ready", &self.address))
+            .send()
+            .await
+            .expect("Failed to get readiness.")
+    }
+
+    /// Connection to the Postgres server, outside of the test database.
+    pub async fn admin_pool(&self) -> PgPool {
+        let configuration = get_configuration().expect("Failed to load configuration.");
+        PgPool::connect_with(configuration.database.without_db().database("postgres"))
+            .await
+            .expect("Failed to connect to Postgres.")
+    }
+
+    pub async fn get_metrics(&self) -> String {
+        self.http_client
+            .get(format!("{}/metrics", &self.metrics_address))
+            .send()
+            .await
+            .expect("Failed to get metrics.")
+            .text()
+            .await
+            .unwrap()
     }
 
     pub async fn get_home_page(&self) -> Response {
@@ -116,9 +277,233 @@ impl TestApp {
     {
         self.http_client
             .post(format!("{}/call_request", &self.address))
-            .form(body)
+            .form(&self.with_csrf_token(body).await)
             .send()
             .await
             .expect("Could not post call request form!")
     }
+
+    /// Submits the form with the chosen callback slots, repeating their key
+    /// as checkboxes do.
+    pub async fn post_call_request_with_slots(
+        &self,
+        phone_number: &str,
+        contact_name: &str,
+        callback_slots: &[&str],
+    ) -> Response {
+        let csrf_token = self.csrf_token().await;
+        let mut body = vec![
+            ("csrf_token", csrf_token.as_str()),
+            ("phone_number", phone_number),
+            ("contact_name", contact_name),
+        ];
+        body.extend(callback_slots.iter().map(|slot| ("callback_slots", *slot)));
+        self.http_client
+            .post(format!("{}/call_request", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Could not post call request form!")
+    }
+
+    pub async fn get_confirmation_page(&self) -> Response {
+        self.http_client
+            .get(format!("{}/call_request/confirmation", &self.address))
+            .send()
+            .await
+            .expect("Failed to get call request confirmation page.")
+    }
+
+    pub async fn get_status_page(&self) -> Response {
+        self.http_client
+            .get(format!("{}/call_request/status", &self.address))
+            .send()
+            .await
+            .expect("Failed to get call request status page.")
+    }
+
+    pub async fn post_status_lookup(&self, reference: &str, phone_number: &str) -> Response {
+        self.http_client
+            .post(format!("{}/call_request/status", &self.address))
+            .form(
+                &self
+                    .with_csrf_token(&serde_json::json!({
+                        "reference": reference,
+                        "phone_number": phone_number,
+                    }))
+                    .await,
+            )
+            .send()
+            .await
+            .expect("Could not look up call request status!")
+    }
+
+    pub async fn get_login_page(&self) -> Response {
+        self.http_client
+            .get(format!("{}/login", &self.address))
+            .send()
+            .await
+            .expect("Failed to get login page.")
+    }
+
+    pub async fn post_login<Body>(&self, body: &Body) -> Response
+    where
+        Body: Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login", &self.address))
+            .form(&self.with_csrf_token(body).await)
+            .send()
+            .await
+            .expect("Could not post login form!")
+    }
+
+    /// Logs in the test user.
+    pub async fn login(&self) -> Response {
+        self.post_login(&serde_json::json!({
+            "username": &self.test_user.username,
+            "password": &self.test_user.password,
+        }))
+        .await
+    }
+
+    pub async fn get_dashboard(&self) -> Response {
+        self.http_client
+            .get(format!("{}/admin/call_requests", &self.address))
+            .send()
+            .await
+            .expect("Failed to get call request dashboard.")
+    }
+
+    pub async fn post_transition(&self, call_request_id: Uuid, status: &str) -> Response {
+        self.http_client
+            .post(format!(
+                "{}/admin/call_requests/{}/transition",
+                &self.address, call_request_id
+            ))
+            .form(
+                &self
+                    .with_csrf_token(&serde_json::json!({ "status": status }))
+                    .await,
+            )
+            .send()
+            .await
+            .expect("Could not transition call request!")
+    }
+
+    /// Issues an API token for the test user.
+    pub async fn api_token(&self) -> String {
+        issue_api_token(&self.db_pool, self.test_user.user_id)
+            .await
+            .expect("Failed to issue API token.")
+            .expose_secret()
+            .to_owned()
+    }
+
+    pub async fn post_api_call_request(&self, body: &serde_json::Value) -> Response {
+        self.http_client
+            .post(format!("{}/api/v1/call_requests", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Could not post call request to the API!")
+    }
+
+    /// Gets an API resource, authenticating with `token` when provided.
+    pub async fn get_api(&self, path: &str, token: Option<&str>) -> Response {
+        let mut request = self.http_client.get(format!("{}{}", &self.address, path));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("Could not get API resource!")
+    }
+
+    /// Gives the test user another role, effective on its next request.
+    pub async fn set_test_user_role(&self, role: UserRole) {
+        sqlx::query!(
+            "UPDATE users SET role = $1 WHERE user_id = $2",
+            role as UserRole,
+            self.test_user.user_id,
+        )
+        .execute(&self.db_pool)
+        .await
+        .expect("Failed to change the test user role.");
+    }
+
+    pub async fn get_users_page(&self) -> Response {
+        self.http_client
+            .get(format!("{}/admin/users", &self.address))
+            .send()
+            .await
+            .expect("Failed to get user management page.")
+    }
+
+    pub async fn post_role_change(&self, user_id: Uuid, role: &str) -> Response {
+        self.http_client
+            .post(format!("{}/admin/users/{}/role", &self.address, user_id))
+            .form(
+                &self
+                    .with_csrf_token(&serde_json::json!({ "role": role }))
+                    .await,
+            )
+            .send()
+            .await
+            .expect("Could not change user role!")
+    }
+
+    pub async fn get_topics_page(&self) -> Response {
+        self.http_client
+            .get(format!("{}/admin/topics", &self.address))
+            .send()
+            .await
+            .expect("Failed to get topic catalog.")
+    }
+
+    pub async fn post_new_topic<Body>(&self, body: &Body) -> Response
+    where
+        Body: Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/topics", &self.address))
+            .form(&self.with_csrf_token(body).await)
+            .send()
+            .await
+            .expect("Could not add topic!")
+    }
+
+    pub async fn post_topic_update<Body>(&self, topic_id: Uuid, body: &Body) -> Response
+    where
+        Body: Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/topics/{}", &self.address, topic_id))
+            .form(&self.with_csrf_token(body).await)
+            .send()
+            .await
+            .expect("Could not update topic!")
+    }
+
+    /// Adds an offered topic to the catalog, bypassing the admin page.
+    pub async fn store_topic(&self, name: &str, team: Option<&str>) -> Uuid {
+        let topic_id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO topics (id, name, team, created_at) VALUES ($1, $2, $3, now())",
+            topic_id,
+            name,
+            team,
+        )
+        .execute(&self.db_pool)
+        .await
+        .expect("Failed to store topic.");
+        topic_id
+    }
+
+    pub async fn post_logout(&self) -> Response {
+        self.http_client
+            .post(format!("{}/logout", &self.address))
+            .form(&self.with_csrf_token(&serde_json::json!({})).await)
+            .send()
+            .await
+            .expect("Could not post logout!")
+    }
 }
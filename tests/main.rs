@@ -1,2 +1,6 @@
+mod cli;
+mod configuration;
 mod helpers;
 mod routes;
+mod startup;
+mod telemetry;
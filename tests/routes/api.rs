@@ -0,0 +1,348 @@
+use reqwest::StatusCode;
+use scraper::{Html, Selector};
+use sqlx::types::Uuid;
+
+use bubble_services::domain::user::UserRole;
+
+use crate::helpers::{with_rate_limits, TestApp};
+
+fn valid_body() -> serde_json::Value {
+    serde_json::json!({
+        "phone_number": "+39 320 894 6581",
+        "contact_name": "Rino Pape",
+    })
+}
+
+fn assert_is_problem(response: &reqwest::Response, status: StatusCode) {
+    assert_eq!(response.status(), status);
+    assert_eq!(
+        response.headers().get("Content-Type").unwrap(),
+        "application/problem+json"
+    );
+}
+
+#[tokio::test]
+async fn creating_a_call_request_returns_its_location() {
+    let app = TestApp::spawn().await;
+
+    let response = app.post_api_call_request(&valid_body()).await;
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let location = response
+        .headers()
+        .get("Location")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned();
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        location,
+        format!("/api/v1/call_requests/{}", body["id"].as_str().unwrap())
+    );
+    assert_eq!(body["phone_number"], "+393208946581");
+    assert_eq!(body["contact_name"], "Rino Pape");
+    assert_eq!(body["status"], "pending");
+
+    let saved = sqlx::query!("SELECT phone_number, user_name FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved call request.");
+    assert_eq!(saved.phone_number, "+393208946581");
+}
+
+#[tokio::test]
+async fn invalid_call_requests_are_reported_as_problems() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .post_api_call_request(&serde_json::json!({
+            "phone_number": "abcdefghij",
+            "contact_name": "a",
+            "callback_slots": ["sun-morning"],
+        }))
+        .await;
+
+    assert_is_problem(&response, StatusCode::UNPROCESSABLE_ENTITY);
+    let problem: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(problem["status"], 422);
+    let mut invalid_params: Vec<&str> = problem["invalid-params"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|param| param["name"].as_str().unwrap())
+        .collect();
+    invalid_params.sort();
+    assert_eq!(
+        invalid_params,
+        vec!["callback_slots", "contact_name", "phone_number"]
+    );
+}
+
+#[tokio::test]
+async fn callback_slots_can_be_chosen() {
+    let app = TestApp::spawn().await;
+    let mut body = valid_body();
+    body["callback_slots"] = serde_json::json!(["wed-morning"]);
+
+    let response = app.post_api_call_request(&body).await;
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let saved = sqlx::query!(
+        r#"SELECT weekday, period::TEXT AS "period!" FROM call_request_callback_slots"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved callback slot.");
+    assert_eq!((saved.weekday, saved.period.as_str()), (3, "morning"));
+}
+
+#[tokio::test]
+async fn call_requests_get_a_reference_code() {
+    let app = TestApp::spawn().await;
+
+    let response = app.post_api_call_request(&valid_body()).await;
+
+    let resource: serde_json::Value = response.json().await.unwrap();
+    let saved = sqlx::query_scalar!("SELECT reference FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(saved.is_some());
+    assert_eq!(resource["reference"], saved.unwrap());
+}
+
+#[tokio::test]
+async fn topics_can_be_chosen() {
+    let app = TestApp::spawn().await;
+    let topic_id = app.store_topic("ID card", None).await;
+    let mut body = valid_body();
+    body["topic"] = serde_json::json!("Dog licence");
+
+    let response = app.post_api_call_request(&body).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let problem: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(problem["invalid-params"][0]["name"], "topic");
+
+    body["topic"] = serde_json::json!(topic_id);
+    let response = app.post_api_call_request(&body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let resource: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(resource["topic_id"], topic_id.to_string());
+}
+
+#[tokio::test]
+async fn duplicate_call_requests_return_the_open_one() {
+    let app = TestApp::spawn().await;
+    let response = app.post_api_call_request(&valid_body()).await;
+    let location = response.headers()["Location"].to_owned();
+
+    let response = app
+        .post_api_call_request(&serde_json::json!({
+            "phone_number": "0039 320 894 6581",
+            "contact_name": "Gino Pape",
+        }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["Location"], location);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["contact_name"], "Rino Pape / Gino Pape");
+    assert_ne!(body["last_requested_at"], body["created_at"]);
+}
+
+#[tokio::test]
+async fn call_requests_over_the_rate_limit_are_too_many_requests() {
+    let app = TestApp::spawn_with(|c| with_rate_limits(c, 10, 1)).await;
+
+    let response = app.post_api_call_request(&valid_body()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = app.post_api_call_request(&valid_body()).await;
+    assert_is_problem(&response, StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response.headers()["Retry-After"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0);
+    let problem: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(problem["type"], "/problems/too-many-requests");
+    assert_eq!(problem["status"], 429);
+}
+
+#[tokio::test]
+async fn malformed_bodies_are_reported_as_problems() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .post_api_call_request(&serde_json::json!({ "contact_name": "Rino Pape" }))
+        .await;
+
+    assert_is_problem(&response, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn malformed_paths_and_queries_are_reported_as_problems() {
+    let app = TestApp::spawn().await;
+    let token = app.api_token().await;
+
+    let response = app
+        .get_api("/api/v1/call_requests?status=forgotten", Some(&token))
+        .await;
+    assert_is_problem(&response, StatusCode::BAD_REQUEST);
+    let problem: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(problem["type"], "/problems/malformed-query");
+
+    let response = app
+        .get_api("/api/v1/call_requests/not-a-uuid", Some(&token))
+        .await;
+    assert_is_problem(&response, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn staff_endpoints_require_a_valid_token() {
+    let app = TestApp::spawn().await;
+
+    for token in [None, Some("not-a-real-token")] {
+        for path in [
+            "/api/v1/call_requests".to_string(),
+            format!("/api/v1/call_requests/{}", Uuid::new_v4()),
+        ] {
+            let response = app.get_api(&path, token).await;
+            assert_is_problem(&response, StatusCode::UNAUTHORIZED);
+            assert_eq!(
+                response.headers().get("WWW-Authenticate").unwrap(),
+                "Bearer"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn the_authentication_scheme_is_case_insensitive() {
+    let app = TestApp::spawn().await;
+    let token = app.api_token().await;
+
+    for scheme in ["Bearer", "bearer", "BEARER"] {
+        let response = app
+            .http_client
+            .get(format!("{}/api/v1/call_requests", &app.address))
+            .header("Authorization", format!("{} {}", scheme, token))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", scheme);
+    }
+
+    // Other schemes are not mistaken for it.
+    let response = app
+        .http_client
+        .get(format!("{}/api/v1/call_requests", &app.address))
+        .header("Authorization", format!("Basic {}", token))
+        .send()
+        .await
+        .unwrap();
+    assert_is_problem(&response, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn staff_can_list_and_read_call_requests() {
+    let app = TestApp::spawn().await;
+    let token = app.api_token().await;
+    let created: serde_json::Value = app
+        .post_api_call_request(&valid_body())
+        .await
+        .json()
+        .await
+        .unwrap();
+    let id = created["id"].as_str().unwrap();
+
+    let response = app.get_api("/api/v1/call_requests", Some(&token)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let list: Vec<serde_json::Value> = response.json().await.unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0]["id"], id);
+
+    let response = app
+        .get_api("/api/v1/call_requests?status=completed", Some(&token))
+        .await;
+    let list: Vec<serde_json::Value> = response.json().await.unwrap();
+    assert!(list.is_empty());
+
+    let response = app
+        .get_api(&format!("/api/v1/call_requests/{}", id), Some(&token))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let detail: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(detail, created);
+}
+
+#[tokio::test]
+async fn unknown_call_requests_are_not_found() {
+    let app = TestApp::spawn().await;
+    let token = app.api_token().await;
+
+    let response = app
+        .get_api(
+            &format!("/api/v1/call_requests/{}", Uuid::new_v4()),
+            Some(&token),
+        )
+        .await;
+
+    assert_is_problem(&response, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn tokens_generated_from_the_dashboard_work() {
+    let app = TestApp::spawn().await;
+    app.login().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/admin/api_tokens", &app.address))
+        .form(&app.with_csrf_token(&serde_json::json!({})).await)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let page = Html::parse_document(&response.text().await.unwrap());
+    let token: String = page
+        .select(&Selector::parse("pre#api-token").unwrap())
+        .next()
+        .unwrap()
+        .text()
+        .collect();
+
+    let response = app
+        .get_api("/api/v1/call_requests", Some(token.trim()))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn listing_users_is_forbidden_to_office_workers() {
+    let app = TestApp::spawn().await;
+    let token = app.api_token().await;
+
+    let response = app.get_api("/api/v1/users", None).await;
+    assert_is_problem(&response, StatusCode::UNAUTHORIZED);
+
+    let response = app.get_api("/api/v1/users", Some(&token)).await;
+    assert_is_problem(&response, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn administrators_can_list_users() {
+    let app = TestApp::spawn().await;
+    app.set_test_user_role(UserRole::Administrator).await;
+    let token = app.api_token().await;
+
+    let response = app.get_api("/api/v1/users", Some(&token)).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let users: Vec<serde_json::Value> = response.json().await.unwrap();
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0]["username"], app.test_user.username);
+    assert_eq!(users[0]["role"], "administrator");
+}
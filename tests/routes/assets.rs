@@ -0,0 +1,177 @@
+use std::io::Read;
+
+use reqwest::{Response, StatusCode};
+use scraper::{Html, Selector};
+
+use bubble_services::domain::user::UserRole;
+
+use crate::helpers::TestApp;
+
+const STYLESHEET: &str = "static/css/bubble.css";
+
+/// Path of the stylesheet linked by the pages.
+async fn stylesheet_path(app: &TestApp) -> String {
+    let page = Html::parse_document(&app.get_login_page().await.text().await.unwrap());
+    page.select(&Selector::parse("link[rel='stylesheet']").unwrap())
+        .filter_map(|link| link.attr("href"))
+        .find(|href| href.starts_with("/static/css/bubble."))
+        .expect("The page links no static stylesheet.")
+        .to_owned()
+}
+
+async fn get_asset(app: &TestApp, path: &str, accept_encoding: Option<&str>) -> Response {
+    let mut request = app.http_client.get(format!("{}{}", &app.address, path));
+    if let Some(accept_encoding) = accept_encoding {
+        request = request.header("Accept-Encoding", accept_encoding);
+    }
+    request.send().await.unwrap()
+}
+
+fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get(name)
+        .map(|value| value.to_str().unwrap())
+}
+
+#[tokio::test]
+async fn pages_link_fingerprinted_assets_cached_for_a_year() {
+    let app = TestApp::spawn().await;
+
+    let path = stylesheet_path(&app).await;
+    assert!(path.starts_with("/static/css/bubble."), "{}", path);
+    assert_ne!(path, "/static/css/bubble.css");
+
+    let response = get_asset(&app, &path, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(header(&response, "Content-Type"), Some("text/css"));
+    assert_eq!(
+        header(&response, "Cache-Control"),
+        Some("public, max-age=31536000, immutable")
+    );
+    assert!(header(&response, "ETag").is_some());
+    assert_eq!(header(&response, "Content-Encoding"), None);
+    // Shared caches could hand it out to everyone.
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != "csrf_token"));
+    let expected = std::fs::read(STYLESHEET).unwrap();
+    assert_eq!(response.bytes().await.unwrap(), expected);
+}
+
+#[tokio::test]
+async fn plain_names_must_be_revalidated() {
+    let app = TestApp::spawn().await;
+
+    let response = get_asset(&app, "/static/css/bubble.css", None).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(header(&response, "Cache-Control"), Some("public, no-cache"));
+    let expected = std::fs::read(STYLESHEET).unwrap();
+    assert_eq!(response.bytes().await.unwrap(), expected);
+}
+
+#[tokio::test]
+async fn a_matching_etag_is_not_modified() {
+    let app = TestApp::spawn().await;
+    let path = stylesheet_path(&app).await;
+    let etag = header(&get_asset(&app, &path, None).await, "ETag")
+        .unwrap()
+        .to_owned();
+
+    let response = app
+        .http_client
+        .get(format!("{}{}", &app.address, path))
+        .header("If-None-Match", &etag)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(header(&response, "ETag"), Some(etag.as_str()));
+    assert!(response.bytes().await.unwrap().is_empty());
+
+    // A stale copy is sent again.
+    let response = app
+        .http_client
+        .get(format!("{}{}", &app.address, path))
+        .header("If-None-Match", "W/\"0000000000000000\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn precompressed_variants_are_negotiated() {
+    let app = TestApp::spawn().await;
+    let path = stylesheet_path(&app).await;
+    let expected = std::fs::read(STYLESHEET).unwrap();
+
+    let response = get_asset(&app, &path, Some("gzip, deflate, br")).await;
+    assert_eq!(header(&response, "Content-Encoding"), Some("br"));
+    assert_eq!(header(&response, "Vary"), Some("Accept-Encoding"));
+
+    let response = get_asset(&app, &path, Some("gzip, br;q=0")).await;
+    assert_eq!(header(&response, "Content-Encoding"), Some("gzip"));
+    let mut body = vec![];
+    flate2::read::GzDecoder::new(&response.bytes().await.unwrap()[..])
+        .read_to_end(&mut body)
+        .unwrap();
+    assert_eq!(body, expected);
+
+    let response = get_asset(&app, &path, Some("identity")).await;
+    assert_eq!(header(&response, "Content-Encoding"), None);
+    assert_eq!(response.bytes().await.unwrap(), expected);
+}
+
+#[tokio::test]
+async fn unknown_assets_are_not_found() {
+    let app = TestApp::spawn().await;
+
+    for path in [
+        "/static/css/missing.css",
+        "/static/",
+        "/static/../Cargo.toml",
+    ] {
+        let response = get_asset(&app, path, None).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn pages_reference_no_external_origin() {
+    let app = TestApp::spawn().await;
+    app.set_test_user_role(UserRole::Administrator).await;
+    app.login().await;
+    let linked = Selector::parse("[href], [src], [action]").unwrap();
+
+    for path in [
+        "/",
+        "/login",
+        "/call_request",
+        "/call_request/status",
+        "/admin/call_requests",
+        "/admin/users",
+        "/admin/topics",
+    ] {
+        let response = get_asset(&app, path, None).await;
+        assert_eq!(response.status(), StatusCode::OK, "{}", path);
+        let page = Html::parse_document(&response.text().await.unwrap());
+
+        for element in page.select(&linked) {
+            for name in ["href", "src", "action"] {
+                let Some(url) = element.value().attr(name) else {
+                    continue;
+                };
+                assert!(
+                    !url.contains("://") && !url.starts_with("//"),
+                    "{} references {}",
+                    path,
+                    url
+                );
+            }
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use reqwest::StatusCode;
 use scraper::{selectable::Selectable, ElementRef, Html, Selector};
 
-use crate::helpers::{assert_is_redirect_to, TestApp};
+use crate::helpers::{assert_is_redirect_to, with_rate_limits, TestApp};
 
 #[tokio::test]
 async fn home_should_have_link_to_call_request() {
@@ -87,7 +87,7 @@ async fn malformed_call_requests_are_rejected() {
 }
 
 #[tokio::test]
-async fn bad_call_requests_redirect_and_display_error() {
+async fn bad_call_requests_rerender_the_form_with_field_errors() {
     let app = TestApp::spawn().await;
     let test_cases = vec![
         (
@@ -96,6 +96,8 @@ async fn bad_call_requests_redirect_and_display_error() {
                 "contact_name": "a"
             }),
             "bad contact_name",
+            vec!["div#name-error"],
+            vec!["div#phone-error"],
         ),
         (
             serde_json::json!({
@@ -103,20 +105,97 @@ async fn bad_call_requests_redirect_and_display_error() {
                 "phone_number": "3"
             }),
             "bad phone_number",
+            vec!["div#phone-error"],
+            vec!["div#name-error"],
+        ),
+        (
+            serde_json::json!({
+                "contact_name": "<b>",
+                "phone_number": "abcdefghij"
+            }),
+            "bad contact_name and phone_number",
+            vec!["div#phone-error", "div#name-error"],
+            vec![],
         ),
     ];
-    for (body, description) in test_cases {
-        let err_response = app.post_call_request(&body).await;
-        assert_is_redirect_to(&err_response, "/call_request");
-        let response = app.get_call_request_page().await;
-        let response_text = response.text().await.unwrap_or_else(move |err| {
-            panic!(
-                "Could not get response text for test case {}. Error: {}",
-                description, err
-            )
-        });
-        assert!(response_text.contains("h2"),);
+    for (body, description, present, absent) in test_cases {
+        let response = app.post_call_request(&body).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::BAD_REQUEST,
+            "The form was not rejected when the payload had {}",
+            description
+        );
+
+        let page_doc = Html::parse_document(&response.text().await.unwrap());
+        for selector in present {
+            assert_eq!(
+                page_doc.select(&Selector::parse(selector).unwrap()).count(),
+                1,
+                "Missing {} when the payload had {}",
+                selector,
+                description
+            );
+        }
+        for selector in absent {
+            assert_eq!(
+                page_doc.select(&Selector::parse(selector).unwrap()).count(),
+                0,
+                "Unexpected {} when the payload had {}",
+                selector,
+                description
+            );
+        }
+
+        // The submitted values are kept.
+        let phone_input = page_doc
+            .select(&Selector::parse("input#phone").unwrap())
+            .next()
+            .unwrap();
+        assert_eq!(phone_input.attr("value"), body["phone_number"].as_str());
+        let name_input = page_doc
+            .select(&Selector::parse("input#name").unwrap())
+            .next()
+            .unwrap();
+        assert_eq!(name_input.attr("value"), body["contact_name"].as_str());
     }
+
+    let saved = sqlx::query!("SELECT id FROM call_requests")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved call requests.");
+    assert!(saved.is_empty());
+}
+
+#[tokio::test]
+async fn field_errors_describe_the_problem() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .post_call_request(&serde_json::json!({
+            "phone_number": "",
+            "contact_name": "Rino\u{0}Pape",
+        }))
+        .await;
+
+    let page_doc = Html::parse_document(&response.text().await.unwrap());
+    let phone_error: String = page_doc
+        .select(&Selector::parse("div#phone-error").unwrap())
+        .next()
+        .unwrap()
+        .text()
+        .collect();
+    let name_error: String = page_doc
+        .select(&Selector::parse("div#name-error").unwrap())
+        .next()
+        .unwrap()
+        .text()
+        .collect();
+    assert_eq!(phone_error, "The phone number is empty.");
+    assert_eq!(
+        name_error,
+        "The contact name contains characters that are not allowed."
+    );
 }
 
 #[tokio::test]
@@ -127,13 +206,390 @@ async fn submitting_call_request_form_adds_it_to_db() {
         "contact_name": "Rino Pape",
     });
     let response = app.post_call_request(&body).await;
-    assert_is_redirect_to(&response, "/");
+    assert_is_redirect_to(&response, "/call_request/confirmation");
 
     let saved = sqlx::query!("SELECT phone_number, user_name FROM call_requests")
         .fetch_one(&app.db_pool)
         .await
         .expect("Failed to fetch saved subscription.");
 
-    assert_eq!(saved.phone_number, "321 456 7891");
+    assert_eq!(saved.phone_number, "+393214567891");
     assert_eq!(saved.user_name, "Rino Pape");
 }
+
+#[tokio::test]
+async fn phone_numbers_over_the_rate_limit_are_rejected() {
+    let app = TestApp::spawn_with(|c| with_rate_limits(c, 10, 2)).await;
+    let body = |phone_number: &str| {
+        serde_json::json!({
+            "phone_number": phone_number,
+            "contact_name": "Rino Pape",
+        })
+    };
+    for _ in 0..2 {
+        let response = app.post_call_request(&body("321 456 7891")).await;
+        assert_is_redirect_to(&response, "/call_request/confirmation");
+    }
+
+    // The same number, however it is written.
+    let response = app.post_call_request(&body("+39 321-456-7891")).await;
+    assert_is_redirect_to(&response, "/call_request");
+    let html_page = app.get_call_request_page().await.text().await.unwrap();
+    assert!(html_page.contains("You sent too many call requests, please try again later."));
+
+    // Other numbers are not affected.
+    let response = app.post_call_request(&body("320 406 7090")).await;
+    assert_is_redirect_to(&response, "/call_request/confirmation");
+
+    // The second submission was merged into the first one.
+    let saved = sqlx::query_scalar!("SELECT COUNT(*) FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved, Some(2));
+}
+
+#[tokio::test]
+async fn clients_over_the_rate_limit_are_rejected() {
+    let app = TestApp::spawn_with(|c| with_rate_limits(c, 2, 10)).await;
+
+    // Invalid submissions count as well.
+    let response = app
+        .post_call_request(&serde_json::json!({
+            "phone_number": "abcdefghij",
+            "contact_name": "Rino Pape",
+        }))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = app
+        .post_call_request(&serde_json::json!({
+            "phone_number": "321 456 7891",
+            "contact_name": "Rino Pape",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/call_request/confirmation");
+
+    let response = app
+        .post_call_request(&serde_json::json!({
+            "phone_number": "320 406 7090",
+            "contact_name": "Gino Pape",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/call_request");
+    let html_page = app.get_call_request_page().await.text().await.unwrap();
+    assert!(html_page.contains("You sent too many call requests, please try again later."));
+}
+
+#[tokio::test]
+async fn duplicate_call_requests_are_merged_into_the_open_one() {
+    let app = TestApp::spawn().await;
+    for contact_name in ["Rino Pape", "rino pape", "Gino Pape"] {
+        let response = app
+            .post_call_request(&serde_json::json!({
+                "phone_number": "321 456 7891",
+                "contact_name": contact_name,
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/call_request/confirmation");
+    }
+
+    let html_page = app.get_confirmation_page().await.text().await.unwrap();
+    assert!(html_page.contains("your request is still queued"));
+    let saved = sqlx::query!("SELECT user_name, created_at, last_requested_at FROM call_requests")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.len(), 1);
+    // Names differing only by case are not repeated.
+    assert_eq!(saved[0].user_name, "Rino Pape / Gino Pape");
+    assert!(saved[0].last_requested_at > saved[0].created_at);
+}
+
+#[tokio::test]
+async fn merged_call_requests_keep_a_few_names() {
+    let app = TestApp::spawn().await;
+    for contact_name in ["Anna", "Bruno", "Carla", "Dario", "Elena", "Franco", "Gina"] {
+        app.post_call_request(&serde_json::json!({
+            "phone_number": "321 456 7891",
+            "contact_name": contact_name,
+        }))
+        .await;
+    }
+
+    let saved = sqlx::query_scalar!("SELECT user_name FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved, "Anna / Bruno / Carla / Dario / Elena");
+}
+
+#[tokio::test]
+async fn closed_or_old_call_requests_are_not_merged() {
+    let app = TestApp::spawn_with(|c| c.application.duplicate_window_minutes = 60).await;
+    let body = serde_json::json!({
+        "phone_number": "321 456 7891",
+        "contact_name": "Rino Pape",
+    });
+    app.post_call_request(&body).await;
+    sqlx::query!("UPDATE call_requests SET status = 'cancelled'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // A closed call request is not reopened.
+    app.post_call_request(&body).await;
+    let html_page = app.get_confirmation_page().await.text().await.unwrap();
+    assert!(html_page.contains("Call request registered."));
+
+    // Neither is one last requested outside the window.
+    sqlx::query!("UPDATE call_requests SET last_requested_at = now() - interval '61 minutes'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.post_call_request(&body).await;
+
+    let saved = sqlx::query_scalar!("SELECT COUNT(*) FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved, Some(3));
+}
+
+/// Stored callback slots, as written in the form.
+async fn saved_callback_slots(app: &TestApp) -> Vec<String> {
+    sqlx::query!(
+        r#"
+        SELECT weekday, period::TEXT AS "period!"
+        FROM call_request_callback_slots
+        ORDER BY weekday, period
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved callback slots.")
+    .into_iter()
+    .map(|slot| format!("{}-{}", slot.weekday, slot.period))
+    .collect()
+}
+
+#[tokio::test]
+async fn only_slots_within_office_hours_are_offered() {
+    let app = TestApp::spawn().await;
+
+    let page = Html::parse_document(&app.get_call_request_page().await.text().await.unwrap());
+
+    let offered: Vec<&str> = page
+        .select(&Selector::parse("#callback-slots input[type='checkbox']").unwrap())
+        .map(|checkbox| {
+            assert_eq!(checkbox.attr("name"), Some("callback_slots"));
+            assert!(checkbox.attr("checked").is_none());
+            checkbox.attr("value").unwrap()
+        })
+        .collect();
+    // The office is closed on Friday afternoon and at the weekend.
+    assert_eq!(offered.len(), 9);
+    assert!(offered.contains(&"mon-morning"));
+    assert!(offered.contains(&"fri-morning"));
+    assert!(!offered.contains(&"fri-afternoon"));
+    assert!(!offered.contains(&"sat-morning"));
+    let hours = page
+        .select(&Selector::parse("#callback-slots thead").unwrap())
+        .next()
+        .unwrap()
+        .text()
+        .collect::<String>();
+    assert!(hours.contains("Morning (09:00–13:00)"), "{}", hours);
+    assert!(hours.contains("Afternoon (14:00–17:30)"), "{}", hours);
+}
+
+#[tokio::test]
+async fn chosen_callback_slots_are_stored() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .post_call_request_with_slots(
+            "321 456 7891",
+            "Rino Pape",
+            &["thu-afternoon", "mon-morning", "thu-afternoon"],
+        )
+        .await;
+
+    assert_is_redirect_to(&response, "/call_request/confirmation");
+    assert_eq!(
+        saved_callback_slots(&app).await,
+        ["1-morning", "4-afternoon"]
+    );
+}
+
+#[tokio::test]
+async fn callback_slots_outside_office_hours_are_rejected() {
+    let app = TestApp::spawn().await;
+
+    for slots in [
+        ["mon-morning", "fri-afternoon"],
+        ["mon-morning", "mon-evening"],
+    ] {
+        let response = app
+            .post_call_request_with_slots("321 456 7891", "Rino Pape", &slots)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{:?}", slots);
+        let page = Html::parse_document(&response.text().await.unwrap());
+        let error = page
+            .select(&Selector::parse("#callback-slots-error").unwrap())
+            .next()
+            .expect("The slot error is not shown.");
+        assert!(!error.text().collect::<String>().trim().is_empty());
+        // The choice is kept, to be fixed.
+        let checked: Vec<&str> = page
+            .select(&Selector::parse("#callback-slots input[checked]").unwrap())
+            .filter_map(|checkbox| checkbox.attr("value"))
+            .collect();
+        assert_eq!(checked, ["mon-morning"], "{:?}", slots);
+    }
+    let page = app
+        .post_call_request_with_slots("321 456 7891", "Rino Pape", &["sat-morning"])
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("The office does not call back in the chosen time slot."));
+
+    let saved = sqlx::query_scalar!("SELECT COUNT(*) FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved, Some(0));
+}
+
+#[tokio::test]
+async fn merged_call_requests_keep_every_chosen_slot() {
+    let app = TestApp::spawn().await;
+
+    for slots in [["mon-morning"], ["tue-afternoon"], ["mon-morning"]] {
+        let response = app
+            .post_call_request_with_slots("321 456 7891", "Rino Pape", &slots)
+            .await;
+        assert_is_redirect_to(&response, "/call_request/confirmation");
+    }
+
+    assert_eq!(
+        saved_callback_slots(&app).await,
+        ["1-morning", "2-afternoon"]
+    );
+}
+
+#[tokio::test]
+async fn merging_with_any_time_keeps_any_time() {
+    let app = TestApp::spawn().await;
+    let no_slots: [&str; 0] = [];
+
+    // Any time, then a restriction.
+    for slots in [&no_slots[..], &["mon-morning"]] {
+        let response = app
+            .post_call_request_with_slots("321 456 7891", "Rino Pape", slots)
+            .await;
+        assert_is_redirect_to(&response, "/call_request/confirmation");
+    }
+    assert!(saved_callback_slots(&app).await.is_empty());
+
+    // A restriction, then any time.
+    for slots in [&["tue-afternoon"][..], &no_slots] {
+        let response = app
+            .post_call_request_with_slots("320 406 7090", "Rino Pape", slots)
+            .await;
+        assert_is_redirect_to(&response, "/call_request/confirmation");
+    }
+    assert!(saved_callback_slots(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn topics_are_offered_only_when_the_catalog_has_some() {
+    let app = TestApp::spawn().await;
+
+    let html_page = app.get_call_request_page().await.text().await.unwrap();
+    assert!(!html_page.contains(r#"id="topic""#));
+
+    let id_card = app.store_topic("ID card", Some("Registry office")).await;
+    let retired = app.store_topic("Dog licence", None).await;
+    sqlx::query!("UPDATE topics SET active = FALSE WHERE id = $1", retired)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let page = Html::parse_document(&app.get_call_request_page().await.text().await.unwrap());
+    let offered: Vec<&str> = page
+        .select(&Selector::parse("select#topic option").unwrap())
+        .filter_map(|option| option.attr("value"))
+        .collect();
+    assert_eq!(offered, ["".to_string(), id_card.to_string()]);
+}
+
+#[tokio::test]
+async fn chosen_topics_are_stored() {
+    let app = TestApp::spawn().await;
+    let id_card = app.store_topic("ID card", None).await;
+
+    for topic in ["", &id_card.to_string()] {
+        let response = app
+            .post_call_request(&serde_json::json!({
+                "phone_number": "321 456 7891",
+                "contact_name": "Rino Pape",
+                "topic": topic,
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/call_request/confirmation");
+    }
+    // A later request without a topic does not forget it.
+    let response = app
+        .post_call_request(&serde_json::json!({
+            "phone_number": "321 456 7891",
+            "contact_name": "Rino Pape",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/call_request/confirmation");
+
+    let saved = sqlx::query_scalar!("SELECT topic_id FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved, Some(id_card));
+}
+
+#[tokio::test]
+async fn retired_or_unknown_topics_are_rejected() {
+    let app = TestApp::spawn().await;
+    let retired = app.store_topic("Dog licence", None).await;
+    sqlx::query!("UPDATE topics SET active = FALSE WHERE id = $1", retired)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.store_topic("ID card", None).await;
+
+    for topic in [retired.to_string(), "ID card".to_string()] {
+        let response = app
+            .post_call_request(&serde_json::json!({
+                "phone_number": "321 456 7891",
+                "contact_name": "Rino Pape",
+                "topic": topic,
+            }))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", topic);
+        let page = Html::parse_document(&response.text().await.unwrap());
+        let error = page
+            .select(&Selector::parse("#topic-error").unwrap())
+            .next()
+            .expect("The topic error is not shown.");
+        assert_eq!(
+            error.text().collect::<String>().trim(),
+            "The chosen topic is not available."
+        );
+    }
+
+    let saved = sqlx::query_scalar!("SELECT COUNT(*) FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved, Some(0));
+}
@@ -0,0 +1,190 @@
+use reqwest::StatusCode;
+use scraper::{Html, Selector};
+
+use crate::helpers::{assert_is_redirect_to, with_rate_limits, TestApp};
+
+async fn submit_call_request(app: &TestApp, phone_number: &str) -> String {
+    let response = app
+        .post_call_request(&serde_json::json!({
+            "phone_number": phone_number,
+            "contact_name": "Rino Pape",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/call_request/confirmation");
+
+    let page = Html::parse_document(&app.get_confirmation_page().await.text().await.unwrap());
+    page.select(&Selector::parse("#reference-code").unwrap())
+        .next()
+        .expect("The reference code is not shown.")
+        .text()
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+fn shown_status(html_page: &str) -> Option<String> {
+    let page = Html::parse_document(html_page);
+    page.select(&Selector::parse("#call-request-status .status").unwrap())
+        .next()
+        .map(|status| status.text().collect::<String>().trim().to_owned())
+}
+
+#[tokio::test]
+async fn the_reference_code_is_shown_after_submitting() {
+    let app = TestApp::spawn().await;
+
+    let reference = submit_call_request(&app, "321 456 7891").await;
+
+    let saved = sqlx::query_scalar!("SELECT reference FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.as_deref(), Some(reference.as_str()));
+    assert!(reference.starts_with("BBL-"), "{}", reference);
+    let html_page = app.get_confirmation_page().await.text().await.unwrap();
+    assert!(html_page.contains(r#"href="/call_request/status""#));
+}
+
+#[tokio::test]
+async fn the_confirmation_page_needs_a_submission() {
+    let app = TestApp::spawn().await;
+
+    let response = app.get_confirmation_page().await;
+
+    assert_is_redirect_to(&response, "/call_request");
+}
+
+#[tokio::test]
+async fn merged_call_requests_keep_their_reference_code() {
+    let app = TestApp::spawn().await;
+
+    let first = submit_call_request(&app, "321 456 7891").await;
+    let second = submit_call_request(&app, "+39 321 456 7891").await;
+    let other = submit_call_request(&app, "320 406 7090").await;
+
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+}
+
+#[tokio::test]
+async fn citizens_can_look_up_their_call_request() {
+    let app = TestApp::spawn().await;
+    let reference = submit_call_request(&app, "321 456 7891").await;
+
+    // The code of the last submission is filled in.
+    let page = Html::parse_document(&app.get_status_page().await.text().await.unwrap());
+    let field = page
+        .select(&Selector::parse("input#reference").unwrap())
+        .next()
+        .unwrap();
+    assert_eq!(field.attr("value"), Some(reference.as_str()));
+
+    // Codes are read regardless of case and dashes, numbers of their format.
+    let typed = reference.to_lowercase().replace('-', " ");
+    let response = app.post_status_lookup(&typed, "+39 321-456-7891").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let html_page = response.text().await.unwrap();
+    assert_eq!(shown_status(&html_page).as_deref(), Some("Pending"));
+    assert!(html_page.contains(&reference));
+
+    sqlx::query!("UPDATE call_requests SET status = 'completed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let html_page = app
+        .post_status_lookup(&reference, "321 456 7891")
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(shown_status(&html_page).as_deref(), Some("Completed"));
+}
+
+#[tokio::test]
+async fn codes_only_work_with_their_phone_number() {
+    let app = TestApp::spawn().await;
+    let reference = submit_call_request(&app, "321 456 7891").await;
+    let other_reference = submit_call_request(&app, "320 406 7090").await;
+    // Same serial, another check.
+    let mut forged = reference.clone();
+    let last = forged.pop().unwrap();
+    forged.push(if last == 'Z' { 'Y' } else { 'Z' });
+
+    for (reference, phone_number) in [
+        (other_reference.as_str(), "321 456 7891"),
+        (reference.as_str(), "320 406 7090"),
+        (forged.as_str(), "321 456 7891"),
+        ("BBL-0000-00", "321 456 7891"),
+        (reference.as_str(), "abc"),
+        ("", ""),
+    ] {
+        let response = app.post_status_lookup(reference, phone_number).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", reference);
+        let html_page = response.text().await.unwrap();
+        assert_eq!(shown_status(&html_page), None);
+        assert!(html_page.contains("No call request matches this reference code and phone number."));
+    }
+}
+
+#[tokio::test]
+async fn lookups_over_the_rate_limit_are_rejected() {
+    let app = TestApp::spawn_with(|c| with_rate_limits(c, 2, 10)).await;
+    let reference = submit_call_request(&app, "321 456 7891").await;
+
+    // Lookups have their own counter.
+    for _ in 0..2 {
+        let response = app.post_status_lookup(&reference, "321 456 7891").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = app.post_status_lookup(&reference, "321 456 7891").await;
+
+    assert_is_redirect_to(&response, "/call_request/status");
+    let html_page = app.get_status_page().await.text().await.unwrap();
+    assert!(html_page.contains("You checked too many times, please try again later."));
+}
+
+#[tokio::test]
+async fn submissions_fail_when_every_reference_code_tried_is_taken() {
+    let app = TestApp::spawn().await;
+    // Every code looks taken. The sequence counts the attempts, whatever
+    // gets rolled back.
+    for statement in [
+        "CREATE SEQUENCE reference_attempts",
+        r#"
+        CREATE FUNCTION take_every_reference() RETURNS trigger AS $$
+        BEGIN
+            PERFORM nextval('reference_attempts');
+            RAISE unique_violation USING MESSAGE = 'The reference is taken.';
+        END
+        $$ LANGUAGE plpgsql
+        "#,
+        r#"
+        CREATE TRIGGER take_every_reference BEFORE UPDATE OF reference ON call_requests
+        FOR EACH ROW WHEN (NEW.reference IS NOT NULL) EXECUTE FUNCTION take_every_reference()
+        "#,
+    ] {
+        sqlx::query(statement).execute(&app.db_pool).await.unwrap();
+    }
+
+    let response = app
+        .post_call_request(&serde_json::json!({
+            "phone_number": "321 456 7891",
+            "contact_name": "Rino Pape",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/call_request");
+    let html_page = app.get_call_request_page().await.text().await.unwrap();
+    assert!(html_page.contains("Database error!"));
+    let attempts: i64 = sqlx::query_scalar("SELECT last_value FROM reference_attempts")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(attempts, 5);
+    let saved = sqlx::query_scalar!("SELECT COUNT(*) FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved, Some(0));
+}
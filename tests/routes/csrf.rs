@@ -0,0 +1,200 @@
+use bubble_services::domain::user::UserRole;
+use reqwest::StatusCode;
+use scraper::{Html, Selector};
+use sqlx::types::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, TestApp, TestUser};
+
+fn call_request_body() -> serde_json::Value {
+    serde_json::json!({
+        "phone_number": "320 406 7090",
+        "contact_name": "Rino Pape",
+    })
+}
+
+async fn saved_call_requests(app: &TestApp) -> Option<i64> {
+    sqlx::query_scalar!("SELECT COUNT(*) FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+/// Client with its own cookies, like the browser of another user.
+fn other_browser() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn every_form_carries_the_csrf_token() {
+    let app = TestApp::spawn().await;
+    let token = app.csrf_token().await;
+    app.set_test_user_role(UserRole::Administrator).await;
+    TestUser::generate().store(&app.db_pool).await;
+    app.post_call_request(&call_request_body()).await;
+    app.login().await;
+
+    let form_selector = Selector::parse("form").unwrap();
+    let token_selector = Selector::parse("input[type='hidden'][name='csrf_token']").unwrap();
+    for path in [
+        "/",
+        "/login",
+        "/call_request",
+        "/admin/call_requests",
+        "/admin/users",
+    ] {
+        let response = app
+            .http_client
+            .get(format!("{}{}", &app.address, path))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", path);
+        let page = Html::parse_document(&response.text().await.unwrap());
+
+        let forms: Vec<_> = page.select(&form_selector).collect();
+        assert!(!forms.is_empty(), "{} has no form", path);
+        for form in forms {
+            let field = form
+                .select(&token_selector)
+                .next()
+                .unwrap_or_else(|| panic!("A form of {} has no CSRF token", path));
+            assert_eq!(field.attr("value"), Some(token.as_str()));
+        }
+    }
+}
+
+#[tokio::test]
+async fn posts_without_a_valid_token_are_rejected() {
+    let app = TestApp::spawn().await;
+    let token = app.csrf_token().await;
+    let url = format!("{}/call_request", &app.address);
+
+    let mut wrong_token = call_request_body();
+    wrong_token["csrf_token"] = format!("{}x", token).into();
+    for body in [call_request_body(), wrong_token] {
+        let response = app.http_client.post(&url).form(&body).send().await.unwrap();
+        assert_is_redirect_to(&response, "/");
+        let html_page = app.get_home_page().await.text().await.unwrap();
+        assert!(html_page.contains("Your form expired, please submit it again."));
+    }
+
+    assert_eq!(saved_call_requests(&app).await, Some(0));
+}
+
+#[tokio::test]
+async fn tokens_only_work_with_their_own_cookie() {
+    let app = TestApp::spawn().await;
+    let mut body = call_request_body();
+    body["csrf_token"] = app.csrf_token().await.into();
+    let url = format!("{}/call_request", &app.address);
+
+    // A page forging a post from another browser, which has no token cookie…
+    let response = other_browser().post(&url).form(&body).send().await.unwrap();
+    assert_is_redirect_to(&response, "/");
+
+    // …or a cookie for another token.
+    let browser = other_browser();
+    browser
+        .get(format!("{}/call_request", &app.address))
+        .send()
+        .await
+        .unwrap();
+    let response = browser.post(&url).form(&body).send().await.unwrap();
+    assert_is_redirect_to(&response, "/");
+
+    assert_eq!(saved_call_requests(&app).await, Some(0));
+}
+
+#[tokio::test]
+async fn forged_staff_actions_are_rejected() {
+    let app = TestApp::spawn().await;
+    app.post_call_request(&call_request_body()).await;
+    let call_request_id: Uuid = sqlx::query_scalar!("SELECT id FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Logging in without a token does not work.
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/");
+    assert_is_redirect_to(&app.get_dashboard().await, "/login");
+
+    // Neither does changing a call request of a logged in user.
+    app.login().await;
+    let response = app
+        .http_client
+        .post(format!(
+            "{}/admin/call_requests/{}/transition",
+            &app.address, call_request_id
+        ))
+        .form(&serde_json::json!({ "status": "cancelled" }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/");
+    let status = sqlx::query_scalar!(r#"SELECT status::text AS "status!" FROM call_requests"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "pending");
+}
+
+#[tokio::test]
+async fn rejected_forms_redirect_back_to_their_page() {
+    let app = TestApp::spawn().await;
+    app.csrf_token().await;
+    let url = format!("{}/call_request", &app.address);
+
+    let response = app
+        .http_client
+        .post(&url)
+        .header("Referer", &url)
+        .form(&call_request_body())
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/call_request");
+    let html_page = app.get_call_request_page().await.text().await.unwrap();
+    assert!(html_page.contains("Your form expired, please submit it again."));
+
+    // Other sites are not redirected to.
+    let response = app
+        .http_client
+        .post(&url)
+        .header("Referer", "https://evil.example/call_request")
+        .form(&call_request_body())
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/");
+}
+
+#[tokio::test]
+async fn the_token_can_be_sent_in_a_header() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/call_request", &app.address))
+        .header("X-CSRF-Token", app.csrf_token().await)
+        .form(&call_request_body())
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/call_request/confirmation");
+    assert_eq!(saved_call_requests(&app).await, Some(1));
+}
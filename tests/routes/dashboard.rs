@@ -0,0 +1,320 @@
+use std::sync::atomic::{AtomicU16, Ordering};
+
+use bubble_services::domain::callback_slot::CallbackSlot;
+use chrono::Utc;
+use reqwest::StatusCode;
+use scraper::{Html, Selector};
+use sqlx::types::Uuid;
+
+use crate::helpers::{always_open, assert_is_redirect_to, TestApp};
+
+/// Submits a call request for a new phone number, so that it is not merged.
+async fn submit_call_request(app: &TestApp, contact_name: &str) -> Uuid {
+    static NEXT_NUMBER: AtomicU16 = AtomicU16::new(0);
+    let phone_number = format!("320 406 {:04}", NEXT_NUMBER.fetch_add(1, Ordering::Relaxed));
+    let response = app
+        .post_call_request(&serde_json::json!({
+            "phone_number": phone_number,
+            "contact_name": contact_name,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/call_request/confirmation");
+
+    sqlx::query!(
+        "SELECT id FROM call_requests WHERE user_name = $1",
+        contact_name
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved call request.")
+    .id
+}
+
+#[tokio::test]
+async fn anonymous_users_are_redirected_to_login() {
+    let app = TestApp::spawn().await;
+
+    let response = app.get_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+
+    let html_page = app.get_login_page().await.text().await.unwrap();
+    assert!(html_page.contains("You need to log in first."));
+
+    let response = app.post_transition(Uuid::new_v4(), "assigned").await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn dashboard_lists_pending_call_requests_oldest_first() {
+    let app = TestApp::spawn().await;
+    let first = submit_call_request(&app, "First Caller").await;
+    let second = submit_call_request(&app, "Second Caller").await;
+    app.login().await;
+
+    let response = app.get_dashboard().await;
+    assert!(response.status().is_success());
+
+    let page = Html::parse_document(&response.text().await.unwrap());
+    let row_selector = Selector::parse("table#call-requests tbody tr").unwrap();
+    let row_ids: Vec<&str> = page
+        .select(&row_selector)
+        .filter_map(|row| row.attr("id"))
+        .collect();
+
+    assert_eq!(
+        row_ids,
+        vec![
+            format!("call-request-{}", first),
+            format!("call-request-{}", second)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn completing_removes_call_request_from_dashboard() {
+    let app = TestApp::spawn().await;
+    let call_request_id = submit_call_request(&app, "Rino Pape").await;
+    app.login().await;
+
+    let response = app.post_transition(call_request_id, "in_progress").await;
+    assert_is_redirect_to(&response, "/admin/call_requests");
+    let html_page = app.get_dashboard().await.text().await.unwrap();
+    assert!(html_page.contains("Call request moved to In progress."));
+    assert!(html_page.contains(&call_request_id.to_string()));
+
+    let response = app.post_transition(call_request_id, "completed").await;
+    assert_is_redirect_to(&response, "/admin/call_requests");
+    let html_page = app.get_dashboard().await.text().await.unwrap();
+    assert!(html_page.contains("Call request moved to Completed."));
+    assert!(!html_page.contains(&call_request_id.to_string()));
+
+    let history = sqlx::query!(
+        r#"
+        SELECT from_status::TEXT AS "from_status!", to_status::TEXT AS "to_status!", changed_by
+        FROM call_request_transitions
+        WHERE call_request_id = $1
+        ORDER BY changed_at ASC
+        "#,
+        call_request_id
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch call request history.");
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].from_status, "pending");
+    assert_eq!(history[0].to_status, "in_progress");
+    assert_eq!(history[1].from_status, "in_progress");
+    assert_eq!(history[1].to_status, "completed");
+    assert!(history
+        .iter()
+        .all(|t| t.changed_by == app.test_user.user_id));
+}
+
+#[tokio::test]
+async fn assigning_records_the_assignee() {
+    let app = TestApp::spawn().await;
+    let call_request_id = submit_call_request(&app, "Rino Pape").await;
+    app.login().await;
+
+    app.post_transition(call_request_id, "assigned").await;
+
+    let saved = sqlx::query!(
+        r#"SELECT status::TEXT AS "status!", assigned_to FROM call_requests WHERE id = $1"#,
+        call_request_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved call request.");
+    assert_eq!(saved.status, "assigned");
+    assert_eq!(saved.assigned_to, Some(app.test_user.user_id));
+}
+
+#[tokio::test]
+async fn illegal_transitions_are_rejected() {
+    let app = TestApp::spawn().await;
+    let call_request_id = submit_call_request(&app, "Rino Pape").await;
+    app.login().await;
+
+    let response = app.post_transition(call_request_id, "completed").await;
+    assert_is_redirect_to(&response, "/admin/call_requests");
+
+    let html_page = app.get_dashboard().await.text().await.unwrap();
+    assert!(html_page.contains("A call request cannot go from Pending to Completed."));
+
+    let saved = sqlx::query!(
+        r#"SELECT status::TEXT AS "status!" FROM call_requests WHERE id = $1"#,
+        call_request_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved call request.");
+    assert_eq!(saved.status, "pending");
+}
+
+#[tokio::test]
+async fn unknown_call_requests_cannot_be_transitioned() {
+    let app = TestApp::spawn().await;
+    app.login().await;
+
+    let response = app.post_transition(Uuid::new_v4(), "assigned").await;
+    assert_is_redirect_to(&response, "/admin/call_requests");
+
+    let html_page = app.get_dashboard().await.text().await.unwrap();
+    assert!(html_page.contains("The call request does not exist."));
+}
+
+/// Ids of the dashboard rows, in order, and whether they are callable now.
+async fn dashboard_rows(app: &TestApp, path: &str) -> Vec<(String, bool)> {
+    let response = app
+        .http_client
+        .get(format!("{}{}", &app.address, path))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let page = Html::parse_document(&response.text().await.unwrap());
+    let callable = Selector::parse(".callable-now").unwrap();
+    page.select(&Selector::parse("table#call-requests tbody tr").unwrap())
+        .map(|row| {
+            let id = row.attr("id").unwrap().trim_start_matches("call-request-");
+            (id.to_owned(), row.select(&callable).next().is_some())
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn call_requests_callable_now_can_be_sorted_first() {
+    let app = TestApp::spawn_with(|c| c.application.office_hours = always_open()).await;
+    let now = always_open()
+        .current_slot(Utc::now())
+        .expect("The office is always open.");
+    let later = CallbackSlot::new(now.weekday.succ(), now.period);
+    app.post_call_request_with_slots("333 111 0001", "Later Caller", &[&later.to_string()])
+        .await;
+    let any_time = submit_call_request(&app, "Any Time Caller").await;
+    app.post_call_request_with_slots(
+        "333 111 0002",
+        "Now Caller",
+        &[&later.to_string(), &now.to_string()],
+    )
+    .await;
+    let ids = sqlx::query!("SELECT id, user_name FROM call_requests ORDER BY created_at")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    let [later_id, any_time_id, now_id] = [0, 1, 2].map(|i| ids[i].id.to_string());
+    assert_eq!(any_time_id, any_time.to_string());
+    app.login().await;
+
+    assert_eq!(
+        dashboard_rows(&app, "/admin/call_requests").await,
+        [
+            (later_id.clone(), false),
+            (any_time_id.clone(), true),
+            (now_id.clone(), true),
+        ]
+    );
+    assert_eq!(
+        dashboard_rows(&app, "/admin/call_requests?sort=callable_now").await,
+        [(any_time_id, true), (now_id, true), (later_id, false)]
+    );
+}
+
+#[tokio::test]
+async fn preferred_callback_slots_are_shown() {
+    let app = TestApp::spawn().await;
+    app.post_call_request_with_slots(
+        "333 111 0003",
+        "Rino Pape",
+        &["tue-afternoon", "mon-morning"],
+    )
+    .await;
+    submit_call_request(&app, "Gino Pape").await;
+    app.login().await;
+
+    let page = Html::parse_document(&app.get_dashboard().await.text().await.unwrap());
+
+    let slots: Vec<String> = page
+        .select(&Selector::parse("td.callback-slots").unwrap())
+        .map(|cell| {
+            cell.text()
+                .collect::<String>()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    // Whether they are callable now depends on when the tests run.
+    let slots: Vec<&str> = slots
+        .iter()
+        .map(|text| text.trim_start_matches("Callable now").trim())
+        .collect();
+    assert_eq!(slots, ["Monday morning, Tuesday afternoon", "Any time"]);
+}
+
+#[tokio::test]
+async fn unknown_sort_orders_are_rejected() {
+    let app = TestApp::spawn().await;
+    app.login().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/admin/call_requests?sort=newest", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn call_requests_can_be_filtered_by_topic() {
+    let app = TestApp::spawn().await;
+    let id_card = app.store_topic("ID card", Some("Registry office")).await;
+    app.store_topic("Birth certificate", None).await;
+    let response = app
+        .post_call_request(&serde_json::json!({
+            "phone_number": "333 111 0004",
+            "contact_name": "Rino Pape",
+            "topic": id_card.to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/call_request/confirmation");
+    let without_topic = submit_call_request(&app, "Gino Pape").await;
+    let with_topic = sqlx::query_scalar!("SELECT id FROM call_requests WHERE topic_id IS NOT NULL")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    app.login().await;
+
+    let page = Html::parse_document(&app.get_dashboard().await.text().await.unwrap());
+    let cells = |selector: &str| -> Vec<String> {
+        page.select(&Selector::parse(selector).unwrap())
+            .map(|cell| cell.text().collect::<String>().trim().to_owned())
+            .collect()
+    };
+    assert_eq!(cells("td.topic"), ["ID card", ""]);
+    assert_eq!(cells("td.team"), ["Registry office", ""]);
+    let filters: Vec<&str> = page
+        .select(&Selector::parse("#dashboard-topics a").unwrap())
+        .filter_map(|link| link.attr("href"))
+        .collect();
+    assert_eq!(filters.len(), 2);
+    assert!(
+        filters.contains(&format!("/admin/call_requests?sort=oldest&topic={}", id_card).as_str())
+    );
+
+    let path = format!("/admin/call_requests?sort=callable_now&topic={}", id_card);
+    let rows: Vec<String> = dashboard_rows(&app, &path)
+        .await
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    assert_eq!(rows, [with_topic.to_string()]);
+    let rows: Vec<String> = dashboard_rows(&app, "/admin/call_requests")
+        .await
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    assert_eq!(rows, [with_topic.to_string(), without_topic.to_string()]);
+}
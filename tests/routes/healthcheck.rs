@@ -6,10 +6,59 @@ async fn health_check_works() {
 
     let response = app
         .http_client
-        .get(&format!("{}/healthcheck", &app.address))
+        .get(format!("{}/healthcheck", &app.address))
         .send()
         .await
         .expect("Failed to execute request");
 
     assert!(response.status().is_success());
 }
+
+#[tokio::test]
+async fn ready_reports_every_dependency() {
+    let app = TestApp::spawn().await;
+
+    let response = app.get_ready().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "up");
+    assert_eq!(body["checks"]["postgres"]["status"], "up");
+    assert!(body["checks"]["postgres"]["latency_ms"].is_number());
+    // Redis is a dependency only when it stores the sessions.
+    if std::env::var("TEST_REDIS").is_ok() {
+        assert_eq!(body["checks"]["redis"]["status"], "up");
+    } else {
+        assert!(body["checks"].get("redis").is_none());
+    }
+}
+
+#[tokio::test]
+async fn ready_fails_when_postgres_is_unreachable() {
+    let app = TestApp::spawn().await;
+    app.db_pool.close().await;
+    sqlx::query(&format!(
+        r#"DROP DATABASE "{}" WITH (FORCE)"#,
+        app.database_name
+    ))
+    .execute(&app.admin_pool().await)
+    .await
+    .expect("Failed to drop the test database.");
+
+    let response = app.get_ready().await;
+
+    assert_eq!(response.status().as_u16(), 503);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "down");
+    assert_eq!(body["checks"]["postgres"]["status"], "down");
+    assert!(body["checks"]["postgres"]["error"].is_string());
+
+    // Liveness does not depend on Postgres.
+    let response = app
+        .http_client
+        .get(format!("{}/healthcheck", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+}
@@ -0,0 +1,264 @@
+use std::collections::BTreeSet;
+
+use bubble_services::{domain::user::UserRole, i18n::Locale};
+use reqwest::{Method, Response, StatusCode};
+use scraper::{Html, Selector};
+
+use crate::helpers::{assert_is_redirect_to, TestApp};
+
+/// Message ids of a catalog, which pages should never show.
+fn catalog_ids(source: &str) -> BTreeSet<&str> {
+    source
+        .lines()
+        .filter(|line| line.starts_with(|c: char| c.is_ascii_lowercase()))
+        .filter_map(|line| line.split_once(" = "))
+        .map(|(id, _)| id)
+        .collect()
+}
+
+async fn request(
+    app: &TestApp,
+    method: Method,
+    path: &str,
+    form: Option<serde_json::Value>,
+    accept_language: &str,
+) -> Response {
+    let mut request = app
+        .http_client
+        .request(method, format!("{}{}", &app.address, path))
+        .header("Accept-Language", accept_language);
+    if let Some(form) = form {
+        request = request.form(&app.with_csrf_token(&form).await);
+    }
+    request.send().await.unwrap()
+}
+
+fn select_text(page: &Html, selector: &str) -> String {
+    page.select(&Selector::parse(selector).unwrap())
+        .next()
+        .unwrap_or_else(|| panic!("Nothing matches {}", selector))
+        .text()
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn every_template_is_rendered_in_both_languages() {
+    let app = TestApp::spawn().await;
+    app.set_test_user_role(UserRole::Administrator).await;
+    let topic = app.store_topic("ID card", Some("Registry office")).await;
+    app.post_call_request(&serde_json::json!({
+        "phone_number": "320 406 7090",
+        "contact_name": "Rino Pape",
+        "topic": topic.to_string(),
+    }))
+    .await;
+    app.login().await;
+    let ids = catalog_ids(include_str!("../../locales/en.ftl"));
+    let invalid_call_request =
+        serde_json::json!({ "phone_number": "", "contact_name": "", "topic": "ID card" });
+
+    for (accept_language, locale, headings) in [
+        (
+            "en-GB,en;q=0.9",
+            Locale::English,
+            [
+                "Actions",
+                "Login",
+                "Call Request",
+                "Call Request",
+                "Call Request Received",
+                "Call Request Status",
+                "Open Call Requests",
+                "Users",
+                "Topics",
+                "API Token",
+            ],
+        ),
+        (
+            "it-IT,it;q=0.9,en;q=0.8",
+            Locale::Italian,
+            [
+                "Azioni",
+                "Accesso",
+                "Richiesta di chiamata",
+                "Richiesta di chiamata",
+                "Richiesta di chiamata ricevuta",
+                "Stato della richiesta di chiamata",
+                "Richieste di chiamata aperte",
+                "Utenti",
+                "Argomenti",
+                "Token API",
+            ],
+        ),
+    ] {
+        let pages = [
+            (Method::GET, "/", None),
+            (Method::GET, "/login", None),
+            (Method::GET, "/call_request", None),
+            // Shown again with the validation errors.
+            (
+                Method::POST,
+                "/call_request",
+                Some(invalid_call_request.clone()),
+            ),
+            (Method::GET, "/call_request/confirmation", None),
+            (Method::GET, "/call_request/status", None),
+            (Method::GET, "/admin/call_requests", None),
+            (Method::GET, "/admin/users", None),
+            (Method::GET, "/admin/topics", None),
+            (
+                Method::POST,
+                "/admin/api_tokens",
+                Some(serde_json::json!({})),
+            ),
+        ];
+        for ((method, path, form), heading) in pages.into_iter().zip(headings) {
+            let response = request(&app, method.clone(), path, form, accept_language).await;
+            let page = format!("{} {} in {}", method, path, locale.as_str());
+            assert!(
+                response.status().is_success() || response.status() == StatusCode::BAD_REQUEST,
+                "{}",
+                page
+            );
+            let document = Html::parse_document(&response.text().await.unwrap());
+
+            let html = document
+                .select(&Selector::parse("html").unwrap())
+                .next()
+                .unwrap();
+            assert_eq!(html.attr("lang"), Some(locale.as_str()), "{}", page);
+            assert_eq!(select_text(&document, "h1"), heading, "{}", page);
+            for text in document.root_element().text() {
+                assert!(
+                    !ids.contains(text.trim()),
+                    "{} shows the untranslated {:?}",
+                    page,
+                    text
+                );
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn validation_errors_are_translated() {
+    let app = TestApp::spawn().await;
+
+    let response = request(
+        &app,
+        Method::POST,
+        "/call_request",
+        Some(serde_json::json!({ "phone_number": "", "contact_name": "a" })),
+        "it",
+    )
+    .await;
+
+    let page = Html::parse_document(&response.text().await.unwrap());
+    assert_eq!(
+        select_text(&page, "div#phone-error"),
+        "Il numero di telefono è vuoto."
+    );
+    assert_eq!(
+        select_text(&page, "div#name-error"),
+        "Il nome è troppo corto."
+    );
+}
+
+#[tokio::test]
+async fn flash_messages_are_shown_in_the_language_of_the_page() {
+    let app = TestApp::spawn().await;
+    app.login().await;
+    app.post_logout().await;
+
+    let response = request(&app, Method::GET, "/login", None, "it").await;
+
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("info: Sei uscito correttamente."));
+}
+
+#[tokio::test]
+async fn flash_message_arguments_are_translated_too() {
+    let app = TestApp::spawn().await;
+    app.login().await;
+    app.post_call_request(&serde_json::json!({
+        "phone_number": "320 406 7090",
+        "contact_name": "Rino Pape",
+    }))
+    .await;
+    let call_request_id = sqlx::query!(
+        "SELECT id FROM call_requests WHERE user_name = $1",
+        "Rino Pape"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved call request.")
+    .id;
+    app.post_transition(call_request_id, "completed").await;
+
+    let response = request(&app, Method::GET, "/admin/call_requests", None, "it").await;
+
+    let html_page = response.text().await.unwrap();
+    assert!(html_page
+        .contains("errore: Una richiesta di chiamata non può passare da In attesa a Completata."));
+}
+
+#[tokio::test]
+async fn the_switcher_overrides_the_browser_language() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/language", &app.address))
+        .header("Referer", format!("{}/call_request", &app.address))
+        .form(
+            &app.with_csrf_token(&serde_json::json!({ "lang": "it" }))
+                .await,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/call_request");
+
+    let response = request(&app, Method::GET, "/call_request", None, "en").await;
+    let page = Html::parse_document(&response.text().await.unwrap());
+    assert_eq!(select_text(&page, "h1"), "Richiesta di chiamata");
+    // The current language cannot be picked again.
+    let current = page
+        .select(&Selector::parse("#language-form button[value='it']").unwrap())
+        .next()
+        .unwrap();
+    assert!(current.attr("disabled").is_some());
+}
+
+#[tokio::test]
+async fn unsupported_languages_are_rejected_by_the_switcher() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/language", &app.address))
+        .form(
+            &app.with_csrf_token(&serde_json::json!({ "lang": "de" }))
+                .await,
+        )
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.cookies().all(|cookie| cookie.name() != "lang"));
+}
+
+#[tokio::test]
+async fn other_languages_get_the_default_one() {
+    let app = TestApp::spawn_with(|c| c.application.default_locale = Locale::Italian).await;
+
+    for accept_language in ["de-DE,fr;q=0.8", ""] {
+        let response = request(&app, Method::GET, "/", None, accept_language).await;
+
+        let page = Html::parse_document(&response.text().await.unwrap());
+        assert_eq!(select_text(&page, "h1"), "Azioni", "{:?}", accept_language);
+    }
+}
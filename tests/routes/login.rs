@@ -0,0 +1,86 @@
+use reqwest::StatusCode;
+use scraper::{Html, Selector};
+
+use crate::helpers::{assert_is_redirect_to, TestApp};
+
+#[tokio::test]
+async fn login_page_should_have_form() {
+    let app = TestApp::spawn().await;
+
+    let response = app.get_login_page().await;
+
+    assert!(response.status().is_success());
+
+    let page_doc = Html::parse_document(&response.text().await.unwrap());
+    let form_selector = Selector::parse("form#login-form").unwrap();
+    let username_selector = Selector::parse("form#login-form input#username").unwrap();
+    let password_selector =
+        Selector::parse("form#login-form input#password[type='password']").unwrap();
+
+    assert_eq!(page_doc.select(&form_selector).count(), 1);
+    assert_eq!(page_doc.select(&username_selector).count(), 1);
+    assert_eq!(page_doc.select(&password_selector).count(), 1);
+}
+
+#[tokio::test]
+async fn malformed_login_is_rejected() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .post_login(&serde_json::json!({ "username": "someone" }))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn wrong_password_redirects_to_login_with_error() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": "definitely-not-the-password",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let html_page = app.get_login_page().await.text().await.unwrap();
+    assert!(html_page.contains("Authentication failed."));
+
+    // The flash message is shown only once.
+    let html_page = app.get_login_page().await.text().await.unwrap();
+    assert!(!html_page.contains("Authentication failed."));
+}
+
+#[tokio::test]
+async fn unknown_username_redirects_to_login_with_error() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": "nobody",
+            "password": &app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    let html_page = app.get_login_page().await.text().await.unwrap();
+    assert!(html_page.contains("Authentication failed."));
+}
+
+#[tokio::test]
+async fn valid_credentials_redirect_to_dashboard() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/call_requests");
+
+    let html_page = app.get_dashboard().await.text().await.unwrap();
+    assert!(html_page.contains("Welcome back!"));
+}
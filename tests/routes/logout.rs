@@ -0,0 +1,38 @@
+use scraper::{Html, Selector};
+
+use crate::helpers::{assert_is_redirect_to, TestApp};
+
+#[tokio::test]
+async fn home_shows_logout_only_when_logged_in() {
+    let app = TestApp::spawn().await;
+    let logout_selector = Selector::parse("form#logout-form").unwrap();
+    let login_selector = Selector::parse("a#login-link").unwrap();
+
+    let page = Html::parse_document(&app.get_home_page().await.text().await.unwrap());
+    assert_eq!(page.select(&logout_selector).count(), 0);
+    assert_eq!(page.select(&login_selector).count(), 1);
+
+    app.login().await;
+
+    let page = Html::parse_document(&app.get_home_page().await.text().await.unwrap());
+    assert_eq!(page.select(&logout_selector).count(), 1);
+    assert_eq!(page.select(&login_selector).count(), 0);
+}
+
+#[tokio::test]
+async fn logout_clears_session_state() {
+    let app = TestApp::spawn().await;
+
+    let response = app.login().await;
+    assert_is_redirect_to(&response, "/admin/call_requests");
+
+    let response = app.post_logout().await;
+    assert_is_redirect_to(&response, "/login");
+
+    let html_page = app.get_login_page().await.text().await.unwrap();
+    assert!(html_page.contains("You have successfully logged out."));
+
+    let page = Html::parse_document(&app.get_home_page().await.text().await.unwrap());
+    let logout_selector = Selector::parse("form#logout-form").unwrap();
+    assert_eq!(page.select(&logout_selector).count(), 0);
+}
@@ -0,0 +1,199 @@
+use sqlx::types::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, TestApp};
+
+/// Value of the sample with exactly this name and labels, if exposed.
+fn sample(metrics: &str, series: &str) -> Option<f64> {
+    metrics.lines().find_map(|line| {
+        let (name, value) = line.rsplit_once(' ')?;
+        (name == series).then(|| value.parse().unwrap())
+    })
+}
+
+#[tokio::test]
+async fn requests_are_counted_by_route_pattern() {
+    let app = TestApp::spawn().await;
+    for _ in 0..2 {
+        app.http_client
+            .get(format!("{}/healthcheck", &app.address))
+            .send()
+            .await
+            .unwrap();
+    }
+    app.get_api(&format!("/api/v1/call_requests/{}", Uuid::new_v4()), None)
+        .await;
+
+    let metrics = app.get_metrics().await;
+
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"http_requests_total{method="GET",route="/healthcheck",status="200"}"#
+        ),
+        Some(2.0)
+    );
+    // Path parameters do not create a series per value.
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"http_requests_total{method="GET",route="/api/v1/call_requests/{call_request_id}",status="401"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"http_request_duration_seconds_count{method="GET",route="/healthcheck"}"#
+        ),
+        Some(2.0)
+    );
+}
+
+#[tokio::test]
+async fn call_requests_are_counted_by_channel() {
+    let app = TestApp::spawn().await;
+    let valid = serde_json::json!({
+        "phone_number": "+39 320 894 6581",
+        "contact_name": "Rino Pape",
+    });
+    let response = app.post_call_request(&valid).await;
+    assert_is_redirect_to(&response, "/call_request/confirmation");
+    app.post_api_call_request(&serde_json::json!({
+        "phone_number": "320 406 7090",
+        "contact_name": "Rino Pape",
+    }))
+    .await;
+    // Merged into an open call request, nothing is created.
+    app.post_api_call_request(&valid).await;
+    app.post_api_call_request(&serde_json::json!({
+        "phone_number": "abcdefghij",
+        "contact_name": "Rino Pape",
+    }))
+    .await;
+    app.post_call_request(&serde_json::json!({
+        "phone_number": "abcdefghij",
+        "contact_name": "a",
+    }))
+    .await;
+
+    let metrics = app.get_metrics().await;
+
+    for channel in ["form", "api"] {
+        assert_eq!(
+            sample(
+                &metrics,
+                &format!(r#"call_requests_created_total{{channel="{}"}}"#, channel)
+            ),
+            Some(1.0)
+        );
+    }
+    let failures = |channel: &str, field: &str| {
+        sample(
+            &metrics,
+            &format!(
+                r#"call_request_validation_failures_total{{channel="{}",field="{}"}}"#,
+                channel, field
+            ),
+        )
+    };
+    assert_eq!(failures("api", "phone_number"), Some(1.0));
+    assert_eq!(failures("api", "contact_name"), None);
+    assert_eq!(failures("form", "phone_number"), Some(1.0));
+    assert_eq!(failures("form", "contact_name"), Some(1.0));
+}
+
+#[tokio::test]
+async fn completed_call_requests_are_counted_by_worker() {
+    let app = TestApp::spawn().await;
+    app.post_call_request(&serde_json::json!({
+        "phone_number": "320 406 7090",
+        "contact_name": "Rino Pape",
+    }))
+    .await;
+    let call_request_id = sqlx::query_scalar!("SELECT id FROM call_requests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    app.login().await;
+    app.post_transition(call_request_id, "in_progress").await;
+    app.post_transition(call_request_id, "completed").await;
+
+    let metrics = app.get_metrics().await;
+
+    assert_eq!(
+        sample(
+            &metrics,
+            &format!(
+                r#"call_requests_completed_total{{worker="{}"}}"#,
+                app.test_user.user_id
+            )
+        ),
+        Some(1.0)
+    );
+}
+
+#[tokio::test]
+async fn database_pool_usage_is_exposed() {
+    let app = TestApp::spawn().await;
+
+    let metrics = app.get_metrics().await;
+
+    assert!(sample(&metrics, r#"db_pool_connections{state="idle"}"#).is_some());
+    assert!(sample(&metrics, r#"db_pool_connections{state="in_use"}"#).is_some());
+    assert!(sample(&metrics, "db_pool_max_connections").unwrap() > 0.0);
+}
+
+#[tokio::test]
+async fn failed_requests_are_counted() {
+    let app = TestApp::spawn().await;
+
+    // Over the size limit of form bodies.
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(format!("username={}", "a".repeat(512 * 1024)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 413);
+
+    let metrics = app.get_metrics().await;
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"http_requests_total{method="POST",route="/login",status="413"}"#
+        ),
+        Some(1.0)
+    );
+}
+
+#[tokio::test]
+async fn metrics_are_only_served_by_the_admin_server() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/metrics", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app
+        .http_client
+        .get(format!("{}/metrics", &app.metrics_address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let metrics = response.text().await.unwrap();
+    // Requests to the application are still counted.
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"http_requests_total{method="GET",route="unmatched",status="404"}"#
+        ),
+        Some(1.0)
+    );
+}
@@ -1,2 +1,15 @@
+mod api;
+mod assets;
 mod call_request;
+mod call_request_status;
+mod csrf;
+mod dashboard;
 mod healthcheck;
+mod i18n;
+mod login;
+mod logout;
+mod metrics;
+mod openapi;
+mod security_headers;
+mod topics;
+mod users;
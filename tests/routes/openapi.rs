@@ -0,0 +1,138 @@
+use bubble_services::routes::api;
+use reqwest::Method;
+use sqlx::types::Uuid;
+
+use crate::helpers::TestApp;
+
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+async fn get_spec(app: &TestApp) -> serde_json::Value {
+    let response = app
+        .http_client
+        .get(format!("{}/api/openapi.json", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.expect("The spec is not valid JSON.")
+}
+
+/// Replaces every `{param}` segment with a value the routes accept.
+fn concrete_path(template: &str) -> String {
+    template
+        .split('/')
+        .map(|segment| {
+            if segment.starts_with('{') {
+                Uuid::new_v4().to_string()
+            } else {
+                segment.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[tokio::test]
+async fn openapi_spec_is_served() {
+    let app = TestApp::spawn().await;
+
+    let spec = get_spec(&app).await;
+
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+    assert!(spec["paths"]["/api/v1/call_requests"]["post"].is_object());
+    assert!(spec["components"]["securitySchemes"]["api_token"].is_object());
+}
+
+#[tokio::test]
+async fn every_documented_operation_is_routed() {
+    let app = TestApp::spawn().await;
+    let spec = get_spec(&app).await;
+
+    for (template, operations) in spec["paths"].as_object().unwrap() {
+        let path = concrete_path(template);
+        for method in operations.as_object().unwrap().keys() {
+            let response = app
+                .http_client
+                .request(
+                    Method::from_bytes(method.to_uppercase().as_bytes()).unwrap(),
+                    format!("{}{}", &app.address, path),
+                )
+                .send()
+                .await
+                .expect("Failed to execute request");
+
+            let status = response.status().as_u16();
+            let is_problem = response
+                .headers()
+                .get("Content-Type")
+                .is_some_and(|value| value == "application/problem+json");
+            assert_ne!(
+                status, 405,
+                "{method} {template} is documented but not routed"
+            );
+            assert!(
+                status != 404 || is_problem,
+                "{method} {template} is documented but not routed"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn every_api_route_is_documented() {
+    let app = TestApp::spawn().await;
+    let spec = get_spec(&app).await;
+
+    for route in api::routes() {
+        let template = format!("{}{}", api::PREFIX, route.path);
+        let method = route.method.as_str().to_lowercase();
+        assert!(
+            spec["paths"][&template][&method].is_object(),
+            "{method} {template} is routed but not documented"
+        );
+    }
+}
+
+#[tokio::test]
+async fn api_paths_have_no_undocumented_methods() {
+    let app = TestApp::spawn().await;
+    let spec = get_spec(&app).await;
+
+    for (template, operations) in spec["paths"].as_object().unwrap() {
+        if !template.starts_with("/api/") {
+            continue;
+        }
+        let path = concrete_path(template);
+        for method in METHODS.iter().filter(|m| operations.get(**m).is_none()) {
+            let response = app
+                .http_client
+                .request(
+                    Method::from_bytes(method.to_uppercase().as_bytes()).unwrap(),
+                    format!("{}{}", &app.address, path),
+                )
+                .send()
+                .await
+                .expect("Failed to execute request");
+
+            assert!(
+                matches!(response.status().as_u16(), 404 | 405),
+                "{method} {template} is routed but not documented"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn swagger_ui_is_served() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/api/docs/", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("swagger"));
+}
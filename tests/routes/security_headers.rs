@@ -0,0 +1,201 @@
+use bubble_services::domain::user::UserRole;
+use reqwest::{Method, Response};
+use scraper::{Html, Selector};
+
+use crate::helpers::TestApp;
+
+/// Every route, with the request reaching it. Admin routes are requested
+/// while logged in as an administrator.
+const ROUTES: [(Method, &str); 19] = [
+    (Method::GET, "/"),
+    (Method::GET, "/static/css/bubble.css"),
+    (Method::GET, "/healthcheck"),
+    (Method::GET, "/ready"),
+    (Method::GET, "/call_request"),
+    (Method::POST, "/call_request"),
+    (Method::GET, "/login"),
+    (Method::POST, "/login"),
+    (Method::GET, "/admin/call_requests"),
+    (Method::POST, "/admin/call_requests/unknown/transition"),
+    (Method::POST, "/admin/api_tokens"),
+    (Method::GET, "/admin/users"),
+    (Method::POST, "/admin/users/unknown/role"),
+    (Method::GET, "/api/docs/"),
+    (Method::GET, "/api/openapi.json"),
+    (Method::POST, "/api/v1/call_requests"),
+    (Method::GET, "/api/v1/call_requests"),
+    (Method::GET, "/api/v1/users"),
+    (Method::POST, "/logout"),
+];
+
+async fn request(app: &TestApp, method: Method, path: &str) -> Response {
+    let mut request = app
+        .http_client
+        .request(method.clone(), format!("{}{}", &app.address, path));
+    // Forms carry the CSRF token, so that they reach the handlers.
+    if method == Method::POST && !path.starts_with("/api/") {
+        request = request.form(&app.with_csrf_token(&serde_json::json!({})).await);
+    }
+    request.send().await.unwrap()
+}
+
+fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get(name)
+        .map(|value| value.to_str().unwrap())
+}
+
+fn csp_nonce(response: &Response) -> String {
+    let policy = header(response, "Content-Security-Policy").unwrap();
+    policy
+        .split("'nonce-")
+        .nth(1)
+        .and_then(|rest| rest.split('\'').next())
+        .expect("The policy has no nonce.")
+        .to_owned()
+}
+
+#[tokio::test]
+async fn every_route_sets_the_security_headers() {
+    let app = TestApp::spawn().await;
+    app.set_test_user_role(UserRole::Administrator).await;
+    app.login().await;
+
+    for (method, path) in ROUTES.into_iter().chain([(Method::GET, "/not-found")]) {
+        let response = request(&app, method.clone(), path).await;
+        let route = format!("{} {} ({})", method, path, response.status());
+
+        assert_eq!(
+            header(&response, "X-Content-Type-Options"),
+            Some("nosniff"),
+            "{}",
+            route
+        );
+        assert_eq!(
+            header(&response, "X-Frame-Options"),
+            Some("DENY"),
+            "{}",
+            route
+        );
+        assert_eq!(
+            header(&response, "Referrer-Policy"),
+            Some("strict-origin-when-cross-origin"),
+            "{}",
+            route
+        );
+        let policy = header(&response, "Content-Security-Policy").unwrap_or_else(|| {
+            panic!("{} has no Content-Security-Policy", route);
+        });
+        assert!(policy.contains("default-src 'self'"), "{}", route);
+        assert!(policy.contains("frame-ancestors 'none'"), "{}", route);
+        assert!(!policy.contains("unsafe-inline"), "{}", route);
+        assert!(policy.contains("style-src 'self';"), "{}", route);
+        // Plain HTTP, browsers would ignore it anyway.
+        assert_eq!(
+            header(&response, "Strict-Transport-Security"),
+            None,
+            "{}",
+            route
+        );
+    }
+}
+
+#[tokio::test]
+async fn scripts_carry_the_nonce_of_their_response() {
+    let app = TestApp::spawn().await;
+    let script_selector = Selector::parse("script").unwrap();
+
+    let mut nonces = vec![];
+    for path in ["/", "/login", "/call_request", "/login"] {
+        let response = request(&app, Method::GET, path).await;
+        let nonce = csp_nonce(&response);
+        let page = Html::parse_document(&response.text().await.unwrap());
+
+        let scripts: Vec<_> = page.select(&script_selector).collect();
+        assert!(!scripts.is_empty(), "{} has no script", path);
+        for script in scripts {
+            assert_eq!(script.attr("nonce"), Some(nonce.as_str()), "{}", path);
+        }
+        nonces.push(nonce);
+    }
+
+    // Every response has a new one, even for the same page.
+    nonces.sort();
+    nonces.dedup();
+    assert_eq!(nonces.len(), 4);
+}
+
+async fn get_forwarded_over_tls(app: &TestApp) -> Response {
+    app.http_client
+        .get(format!("{}/", &app.address))
+        .header("X-Forwarded-Proto", "https")
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn hsts_is_sent_over_tls_forwarded_by_a_trusted_proxy() {
+    let app = TestApp::spawn_with(|c| {
+        c.application.security_headers.trust_forwarded_proto = true;
+    })
+    .await;
+
+    let response = get_forwarded_over_tls(&app).await;
+
+    assert_eq!(
+        header(&response, "Strict-Transport-Security"),
+        Some("max-age=31536000; includeSubDomains")
+    );
+}
+
+#[tokio::test]
+async fn forwarded_schemes_are_ignored_by_default() {
+    let app = TestApp::spawn().await;
+
+    let response = get_forwarded_over_tls(&app).await;
+
+    assert_eq!(header(&response, "Strict-Transport-Security"), None);
+}
+
+#[tokio::test]
+async fn error_responses_set_the_security_headers() {
+    let app = TestApp::spawn().await;
+
+    // Beyond the payload limit of the CSRF check, which fails the request.
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(format!("username={}", "a".repeat(300_000)))
+        .send()
+        .await
+        .unwrap();
+
+    assert!(response.status().is_client_error(), "{}", response.status());
+    assert_eq!(header(&response, "X-Content-Type-Options"), Some("nosniff"));
+    assert_eq!(header(&response, "X-Frame-Options"), Some("DENY"));
+    assert!(header(&response, "Content-Security-Policy").is_some());
+}
+
+#[tokio::test]
+async fn security_headers_are_configurable() {
+    let app = TestApp::spawn_with(|c| {
+        let headers = &mut c.application.security_headers;
+        headers.hsts_max_age_seconds = None;
+        headers.frame_options = "SAMEORIGIN".into();
+        headers.referrer_policy = "no-referrer".into();
+        headers.csp_report_only = true;
+        headers.trust_forwarded_proto = true;
+    })
+    .await;
+
+    let response = get_forwarded_over_tls(&app).await;
+
+    assert_eq!(header(&response, "Strict-Transport-Security"), None);
+    assert_eq!(header(&response, "X-Frame-Options"), Some("SAMEORIGIN"));
+    assert_eq!(header(&response, "Referrer-Policy"), Some("no-referrer"));
+    assert_eq!(header(&response, "Content-Security-Policy"), None);
+    assert!(header(&response, "Content-Security-Policy-Report-Only").is_some());
+}
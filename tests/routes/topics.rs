@@ -0,0 +1,155 @@
+use bubble_services::domain::user::UserRole;
+use scraper::{Html, Selector};
+use sqlx::types::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, TestApp};
+
+struct StoredTopic {
+    name: String,
+    team: Option<String>,
+    active: bool,
+}
+
+async fn stored_topics(app: &TestApp) -> Vec<StoredTopic> {
+    sqlx::query_as!(
+        StoredTopic,
+        "SELECT name, team, active FROM topics ORDER BY created_at"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch topics.")
+}
+
+#[tokio::test]
+async fn office_workers_cannot_manage_topics() {
+    let app = TestApp::spawn().await;
+    app.login().await;
+
+    let response = app.get_topics_page().await;
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_page().await.text().await.unwrap();
+    assert!(html_page.contains("You are not allowed to access that page."));
+
+    let response = app
+        .post_new_topic(&serde_json::json!({ "name": "ID card" }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+    assert!(stored_topics(&app).await.is_empty());
+
+    let html_page = app.get_dashboard().await.text().await.unwrap();
+    assert!(!html_page.contains(r#"id="topics-link""#));
+}
+
+#[tokio::test]
+async fn supervisors_can_add_topics() {
+    let app = TestApp::spawn().await;
+    app.set_test_user_role(UserRole::Supervisor).await;
+    app.login().await;
+
+    let html_page = app.get_topics_page().await.text().await.unwrap();
+    assert!(html_page.contains(r#"id="no-topics""#));
+
+    let response = app
+        .post_new_topic(&serde_json::json!({
+            "name": " ID card ",
+            "team": "Registry office",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/topics");
+    let response = app
+        .post_new_topic(&serde_json::json!({ "name": "Birth certificate", "team": "" }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/topics");
+
+    let html_page = app.get_topics_page().await.text().await.unwrap();
+    assert!(html_page.contains("Topic Birth certificate added."));
+    let page = Html::parse_document(&html_page);
+    let rows = Selector::parse("table#topics tbody tr").unwrap();
+    assert_eq!(page.select(&rows).count(), 2);
+
+    let topics = stored_topics(&app).await;
+    assert_eq!(topics[0].name, "ID card");
+    assert_eq!(topics[0].team.as_deref(), Some("Registry office"));
+    assert!(topics[0].active);
+    assert_eq!(topics[1].name, "Birth certificate");
+    assert_eq!(topics[1].team, None);
+
+    let html_page = app.get_dashboard().await.text().await.unwrap();
+    assert!(html_page.contains(r#"id="topics-link""#));
+}
+
+#[tokio::test]
+async fn topic_names_must_be_valid_and_unique() {
+    let app = TestApp::spawn().await;
+    app.set_test_user_role(UserRole::Supervisor).await;
+    app.login().await;
+    app.store_topic("ID card", None).await;
+
+    for (name, message) in [
+        ("  ", "The name is empty."),
+        ("id CARD", "A topic with that name already exists."),
+        (
+            "ID\u{7}card",
+            "The name contains characters that are not allowed.",
+        ),
+    ] {
+        let response = app
+            .post_new_topic(&serde_json::json!({ "name": name }))
+            .await;
+        assert_is_redirect_to(&response, "/admin/topics");
+
+        let html_page = app.get_topics_page().await.text().await.unwrap();
+        assert!(html_page.contains(message), "{}", message);
+    }
+    assert_eq!(stored_topics(&app).await.len(), 1);
+}
+
+#[tokio::test]
+async fn supervisors_can_rename_route_and_retire_topics() {
+    let app = TestApp::spawn().await;
+    app.set_test_user_role(UserRole::Supervisor).await;
+    app.login().await;
+    let topic_id = app.store_topic("ID card", None).await;
+
+    // Unchecked checkboxes are not sent, retiring the topic.
+    let response = app
+        .post_topic_update(
+            topic_id,
+            &serde_json::json!({ "name": "Identity card", "team": "Registry office" }),
+        )
+        .await;
+    assert_is_redirect_to(&response, "/admin/topics");
+
+    let html_page = app.get_topics_page().await.text().await.unwrap();
+    assert!(html_page.contains("Topic Identity card saved."));
+    let topics = stored_topics(&app).await;
+    assert_eq!(topics[0].name, "Identity card");
+    assert_eq!(topics[0].team.as_deref(), Some("Registry office"));
+    assert!(!topics[0].active);
+
+    let response = app
+        .post_topic_update(
+            topic_id,
+            &serde_json::json!({ "name": "Identity card", "active": "on" }),
+        )
+        .await;
+    assert_is_redirect_to(&response, "/admin/topics");
+    let topics = stored_topics(&app).await;
+    assert_eq!(topics[0].team, None);
+    assert!(topics[0].active);
+}
+
+#[tokio::test]
+async fn unknown_topics_are_reported() {
+    let app = TestApp::spawn().await;
+    app.set_test_user_role(UserRole::Supervisor).await;
+    app.login().await;
+
+    let response = app
+        .post_topic_update(Uuid::new_v4(), &serde_json::json!({ "name": "ID card" }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/topics");
+
+    let html_page = app.get_topics_page().await.text().await.unwrap();
+    assert!(html_page.contains("The topic does not exist."));
+}
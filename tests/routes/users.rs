@@ -0,0 +1,146 @@
+use bubble_services::domain::user::UserRole;
+use scraper::{Html, Selector};
+use sqlx::types::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, TestApp, TestUser};
+
+async fn stored_role(app: &TestApp, user_id: Uuid) -> UserRole {
+    sqlx::query!(
+        r#"SELECT role AS "role: UserRole" FROM users WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch user role.")
+    .role
+}
+
+#[tokio::test]
+async fn anonymous_users_are_redirected_to_login() {
+    let app = TestApp::spawn().await;
+
+    let response = app.get_users_page().await;
+    assert_is_redirect_to(&response, "/login");
+
+    let html_page = app.get_login_page().await.text().await.unwrap();
+    assert!(html_page.contains("You need to log in first."));
+}
+
+#[tokio::test]
+async fn office_workers_and_supervisors_cannot_manage_users() {
+    let app = TestApp::spawn().await;
+    app.login().await;
+
+    for role in [UserRole::OfficeWorker, UserRole::Supervisor] {
+        app.set_test_user_role(role).await;
+
+        let response = app.get_users_page().await;
+        assert_is_redirect_to(&response, "/login");
+        let html_page = app.get_login_page().await.text().await.unwrap();
+        assert!(html_page.contains("You are not allowed to access that page."));
+
+        let response = app
+            .post_role_change(app.test_user.user_id, "administrator")
+            .await;
+        assert_is_redirect_to(&response, "/login");
+    }
+    assert_eq!(
+        stored_role(&app, app.test_user.user_id).await,
+        UserRole::Supervisor
+    );
+}
+
+#[tokio::test]
+async fn every_role_can_use_the_dashboard() {
+    let app = TestApp::spawn().await;
+    app.login().await;
+
+    for role in UserRole::ALL {
+        app.set_test_user_role(role).await;
+        let response = app.get_dashboard().await;
+        assert!(response.status().is_success());
+
+        let html_page = response.text().await.unwrap();
+        assert_eq!(
+            html_page.contains(r#"id="users-link""#),
+            role == UserRole::Administrator
+        );
+    }
+}
+
+#[tokio::test]
+async fn administrators_see_every_user() {
+    let app = TestApp::spawn().await;
+    let colleague = TestUser::generate();
+    colleague.store(&app.db_pool).await;
+    app.set_test_user_role(UserRole::Administrator).await;
+    app.login().await;
+
+    let response = app.get_users_page().await;
+    assert!(response.status().is_success());
+
+    let page = Html::parse_document(&response.text().await.unwrap());
+    let row_selector = Selector::parse("table#users tbody tr").unwrap();
+    let mut row_ids: Vec<&str> = page
+        .select(&row_selector)
+        .filter_map(|row| row.attr("id"))
+        .collect();
+    row_ids.sort();
+    let mut expected = vec![
+        format!("user-{}", app.test_user.user_id),
+        format!("user-{}", colleague.user_id),
+    ];
+    expected.sort();
+    assert_eq!(row_ids, expected);
+}
+
+#[tokio::test]
+async fn administrators_can_change_roles() {
+    let app = TestApp::spawn().await;
+    let colleague = TestUser::generate();
+    colleague.store(&app.db_pool).await;
+    app.set_test_user_role(UserRole::Administrator).await;
+    app.login().await;
+
+    let response = app.post_role_change(colleague.user_id, "supervisor").await;
+    assert_is_redirect_to(&response, "/admin/users");
+
+    let html_page = app.get_users_page().await.text().await.unwrap();
+    assert!(html_page.contains(&format!("{} is now Supervisor.", colleague.username)));
+    assert_eq!(
+        stored_role(&app, colleague.user_id).await,
+        UserRole::Supervisor
+    );
+}
+
+#[tokio::test]
+async fn administrators_cannot_change_their_own_role() {
+    let app = TestApp::spawn().await;
+    app.set_test_user_role(UserRole::Administrator).await;
+    app.login().await;
+
+    let response = app
+        .post_role_change(app.test_user.user_id, "office_worker")
+        .await;
+    assert_is_redirect_to(&response, "/admin/users");
+
+    let html_page = app.get_users_page().await.text().await.unwrap();
+    assert!(html_page.contains("You cannot change your own role."));
+    assert_eq!(
+        stored_role(&app, app.test_user.user_id).await,
+        UserRole::Administrator
+    );
+}
+
+#[tokio::test]
+async fn unknown_users_are_reported() {
+    let app = TestApp::spawn().await;
+    app.set_test_user_role(UserRole::Administrator).await;
+    app.login().await;
+
+    let response = app.post_role_change(Uuid::new_v4(), "supervisor").await;
+    assert_is_redirect_to(&response, "/admin/users");
+
+    let html_page = app.get_users_page().await.text().await.unwrap();
+    assert!(html_page.contains("The user does not exist."));
+}
@@ -0,0 +1,112 @@
+use bubble_services::{migration::MIGRATOR, startup::Application};
+use sqlx::PgPool;
+
+use crate::helpers::{create_database, test_configuration};
+
+async fn applied_migrations(pool: &PgPool) -> i64 {
+    sqlx::query_scalar("SELECT count(*) FROM _sqlx_migrations WHERE success")
+        .fetch_one(pool)
+        .await
+        .expect("Failed to count applied migrations.")
+}
+
+#[tokio::test]
+async fn migrations_are_applied_on_startup_when_enabled() {
+    let mut configuration = test_configuration();
+    configuration.application.migrate_on_startup = true;
+    let pool = create_database(&configuration.database).await;
+
+    Application::build(configuration)
+        .await
+        .expect("Failed to build the application.");
+
+    assert_eq!(
+        applied_migrations(&pool).await,
+        MIGRATOR.iter().count() as i64
+    );
+}
+
+#[tokio::test]
+async fn migrations_are_not_applied_on_startup_by_default() {
+    let configuration = test_configuration();
+    let pool = create_database(&configuration.database).await;
+
+    Application::build(configuration)
+        .await
+        .expect("Failed to build the application.");
+
+    let migrated: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(!migrated);
+}
+
+#[tokio::test]
+async fn the_server_starts_without_the_database_when_not_migrating() {
+    let mut configuration = test_configuration();
+    // Nothing listens there.
+    configuration.database.port = 1;
+
+    Application::build(configuration)
+        .await
+        .expect("Failed to build the application.");
+}
+
+#[tokio::test]
+async fn replicas_starting_together_migrate_once() {
+    let mut configuration = test_configuration();
+    configuration.application.migrate_on_startup = true;
+    let pool = create_database(&configuration.database).await;
+
+    let (first, second) = tokio::join!(
+        Application::build(configuration.clone()),
+        Application::build(configuration.clone()),
+    );
+
+    first.expect("Failed to build the first replica.");
+    second.expect("Failed to build the second replica.");
+    assert_eq!(
+        applied_migrations(&pool).await,
+        MIGRATOR.iter().count() as i64
+    );
+}
+
+#[tokio::test]
+async fn startup_fails_when_the_schema_is_newer_than_the_binary() {
+    let mut configuration = test_configuration();
+    let pool = create_database(&configuration.database).await;
+    MIGRATOR.run(&pool).await.unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+        VALUES (29990101000000, 'from the future', true, '\x00', 0)
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    for migrate_on_startup in [true, false] {
+        configuration.application.migrate_on_startup = migrate_on_startup;
+        let error = Application::build(configuration.clone())
+            .await
+            .err()
+            .expect("The application started with a newer schema.");
+        assert!(
+            error.to_string().contains("newer than this binary"),
+            "{}",
+            error
+        );
+    }
+
+    // The failed migration released its lock, or this would hang.
+    configuration.application.migrate_on_startup = true;
+    let attempt = tokio::time::timeout(
+        std::time::Duration::from_secs(10),
+        Application::build(configuration),
+    )
+    .await
+    .expect("The migration lock was kept.");
+    assert!(attempt.is_err());
+}
@@ -0,0 +1,140 @@
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+
+use actix_web::{
+    test::{call_and_read_body, init_service, TestRequest},
+    web, App, HttpResponse, HttpServer,
+};
+use bubble_services::{
+    configuration::TelemetryConfiguration,
+    telemetry::{get_subscriber, init_tracer_provider, propagate_trace_context},
+};
+use tracing_actix_web::TracingLogger;
+
+const TRACE_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+const PARENT_SPAN_ID: &str = "b7ad6b7169203331";
+
+/// OpenTelemetry collector stub, keeping every OTLP/HTTP JSON export received.
+struct Collector {
+    endpoint: String,
+    exports: Arc<Mutex<Vec<serde_json::Value>>>,
+}
+
+impl Collector {
+    fn spawn() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!(
+            "http://127.0.0.1:{}/v1/traces",
+            listener.local_addr().unwrap().port()
+        );
+        let exports = Arc::new(Mutex::new(vec![]));
+        let received = web::Data::from(exports.clone());
+        let server = HttpServer::new(move || {
+            App::new().app_data(received.clone()).route(
+                "/v1/traces",
+                web::post().to(
+                    |body: web::Json<serde_json::Value>,
+                     received: web::Data<Mutex<Vec<serde_json::Value>>>| async move {
+                        received.lock().unwrap().push(body.into_inner());
+                        HttpResponse::Ok().json(serde_json::json!({}))
+                    },
+                ),
+            )
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_web::rt::spawn(server);
+        Self { endpoint, exports }
+    }
+
+    /// Every span exported so far.
+    fn spans(&self) -> Vec<serde_json::Value> {
+        let mut spans = vec![];
+        for export in self.exports.lock().unwrap().iter() {
+            for resource_spans in export["resourceSpans"].as_array().unwrap() {
+                for scope_spans in resource_spans["scopeSpans"].as_array().unwrap() {
+                    spans.extend(scope_spans["spans"].as_array().unwrap().iter().cloned());
+                }
+            }
+        }
+        spans
+    }
+}
+
+/// Handler making an outgoing call, answering with the `traceparent` it sent.
+async fn outgoing_call() -> HttpResponse {
+    let request = propagate_trace_context(reqwest::Client::new().get("http://127.0.0.1/"))
+        .build()
+        .unwrap();
+    let traceparent = request.headers()["traceparent"].to_str().unwrap();
+    HttpResponse::Ok().body(traceparent.to_owned())
+}
+
+#[actix_web::test]
+async fn request_spans_join_the_incoming_trace_and_are_exported() {
+    let collector = Collector::spawn();
+    let tracer_provider = init_tracer_provider(
+        "test",
+        &TelemetryConfiguration {
+            otlp_endpoint: Some(collector.endpoint.clone()),
+        },
+    )
+    .unwrap()
+    .expect("Export is configured.");
+    let subscriber = get_subscriber(
+        "test".into(),
+        "info".into(),
+        std::io::sink,
+        Some(&tracer_provider),
+    );
+    let _guard = tracing::subscriber::set_default(subscriber);
+    let app = init_service(
+        App::new()
+            .wrap(TracingLogger::default())
+            .route("/outgoing", web::get().to(outgoing_call)),
+    )
+    .await;
+
+    let request = TestRequest::get()
+        .uri("/outgoing")
+        .insert_header((
+            "traceparent",
+            format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID),
+        ))
+        .to_request();
+    let outgoing_traceparent =
+        String::from_utf8(call_and_read_body(&app, request).await.to_vec()).unwrap();
+    // Flushing blocks until the collector answers, which runs on this thread.
+    let provider = tracer_provider.clone();
+    actix_web::rt::task::spawn_blocking(move || provider.force_flush())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let spans = collector.spans();
+    let request_span = spans
+        .iter()
+        .find(|span| span["parentSpanId"] == PARENT_SPAN_ID)
+        .expect("The request span was not exported.");
+    assert_eq!(request_span["traceId"], TRACE_ID);
+
+    // Outgoing calls carry the same trace, with the request span as parent.
+    let [version, trace_id, parent_id, _flags] = outgoing_traceparent
+        .split('-')
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap();
+    assert_eq!(version, "00");
+    assert_eq!(trace_id, TRACE_ID);
+    assert_eq!(parent_id, request_span["spanId"]);
+}
+
+#[test]
+fn nothing_is_exported_without_an_endpoint() {
+    let tracer_provider = init_tracer_provider("test", &TelemetryConfiguration::default()).unwrap();
+    assert!(tracer_provider.is_none());
+}